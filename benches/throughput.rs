@@ -0,0 +1,52 @@
+//! Times parsing a large f32 file with the read loop and with a memory map,
+//! and the two passes of --histogram over it, for some buffer sizes.
+//!
+//! Run with `cargo bench`, `PARSEBIN_BENCH_MIB` sets the size of the file
+//! (1024 MiB by default). Only the parsing is timed, the output goes to
+//! /dev/null.
+
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Instant;
+
+fn main() {
+    let mib: u64 = std::env::var("PARSEBIN_BENCH_MIB")
+        .ok()
+        .and_then(|mib| mib.parse().ok())
+        .unwrap_or(1024);
+    let path = std::env::temp_dir().join(format!("parsebin-bench-{}.bin", std::process::id()));
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+    let chunk: Vec<u8> = (0..1 << 18)
+        .flat_map(|v: u32| (v as f32 * 0.5).to_le_bytes())
+        .collect();
+    for _ in 0..mib {
+        file.write_all(&chunk).unwrap();
+    }
+    file.flush().unwrap();
+    drop(file);
+    let path = path.to_string_lossy().into_owned();
+    for (name, args) in [
+        ("read 4 KiB", &["--no-mmap"][..]),
+        ("read 64 KiB", &["--no-mmap", "--buffer-size", "64KiB"]),
+        ("read 1 MiB", &["--no-mmap", "--buffer-size", "1MiB"]),
+        ("mmap", &["--mmap"]),
+        ("hist 4 KiB", &["--no-mmap", "--histogram", "16"]),
+        (
+            "hist 1 MiB",
+            &["--no-mmap", "--histogram", "16", "--buffer-size", "1MiB"],
+        ),
+    ] {
+        let start = Instant::now();
+        let status = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+            .args(["f32", &path])
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let seconds = start.elapsed().as_secs_f64();
+        println!("{:<12} {:8.1} MiB/s", name, mib as f64 / seconds);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,602 @@
+//! Writer of Apache Arrow IPC files, also known as Feather v2, for --arrow.
+//!
+//! The file starts and ends with the magic `ARROW1`. In between are the
+//! schema and the record batches as encapsulated messages, each a
+//! continuation marker, the length of its metadata and the metadata as a
+//! FlatBuffer, followed by the body of a record batch. A footer with the
+//! schema and the offsets of the record batches lets readers seek to them.
+//! Only non-nullable columns of primitive integers and floats are written,
+//! always little-endian.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use parsebin::ParseType;
+use parsebin::SizeOf;
+use parsebin::Value;
+
+const MAGIC: &[u8] = b"ARROW1";
+
+/// Marker before the length of every message.
+const CONTINUATION: u32 = 0xffff_ffff;
+
+/// MetadataVersion V5 of the format.
+const VERSION: i16 = 4;
+
+// Members of the MessageHeader union.
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+
+// Members of the Type union.
+const TYPE_INT: u8 = 2;
+const TYPE_FLOATING_POINT: u8 = 3;
+
+/// A field of a FlatBuffer table being built.
+enum Slot {
+    Scalar(Vec<u8>),
+    /// Position of an object built before, see [`Builder`].
+    Offset(usize),
+}
+
+/// Builder of a FlatBuffer. Like the reference implementation it fills the
+/// buffer from the back, so objects are built before the tables referring
+/// to them. Objects are identified by their distance from the end of the
+/// buffer, the bytes are kept in reverse until finished.
+struct Builder {
+    reversed: Vec<u8>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Builder {
+            reversed: Vec::new(),
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.reversed.len()
+    }
+
+    fn prepend(&mut self, bytes: &[u8]) {
+        self.reversed.extend(bytes.iter().rev());
+    }
+
+    /// Pads so that `size` more bytes start at a multiple of `align`.
+    fn align(&mut self, size: usize, align: usize) {
+        let padding = (align - (self.position() + size) % align) % align;
+        self.reversed.extend(std::iter::repeat_n(0, padding));
+    }
+
+    fn scalar(&mut self, bytes: &[u8]) -> usize {
+        self.align(bytes.len(), bytes.len());
+        self.prepend(bytes);
+        self.position()
+    }
+
+    /// An offset to `target`, counted from where the offset is.
+    fn offset(&mut self, target: usize) -> usize {
+        self.align(4, 4);
+        let offset = (self.position() + 4 - target) as u32;
+        self.scalar(&offset.to_le_bytes())
+    }
+
+    fn string(&mut self, s: &str) -> usize {
+        self.align(4 + s.len() + 1, 4);
+        self.prepend(&[0]);
+        self.prepend(s.as_bytes());
+        self.scalar(&(s.len() as u32).to_le_bytes())
+    }
+
+    fn offsets(&mut self, targets: &[usize]) -> usize {
+        self.align(4 * (targets.len() + 1), 4);
+        for target in targets.iter().rev() {
+            self.offset(*target);
+        }
+        self.scalar(&(targets.len() as u32).to_le_bytes())
+    }
+
+    /// A vector of `count` structs of 8 byte alignment.
+    fn structs(&mut self, bytes: &[u8], count: usize) -> usize {
+        self.align(bytes.len(), 8);
+        self.prepend(bytes);
+        self.scalar(&(count as u32).to_le_bytes())
+    }
+
+    /// A table of `slots` by field id, followed by its vtable.
+    fn table(&mut self, slots: &[(usize, Slot)]) -> usize {
+        let end = self.position();
+        let mut fields = Vec::new();
+        for (id, slot) in slots {
+            let position = match slot {
+                Slot::Scalar(bytes) => self.scalar(bytes),
+                Slot::Offset(target) => self.offset(*target),
+            };
+            fields.push((*id, position));
+        }
+        // The offset to the vtable is filled in once it's built.
+        let table = self.scalar(&[0; 4]);
+        let len = slots.iter().map(|(id, _)| id + 1).max().unwrap_or(0);
+        let mut entries = vec![0u16; len];
+        for (id, position) in fields {
+            entries[id] = (table - position) as u16;
+        }
+        for entry in entries.iter().rev() {
+            self.prepend(&entry.to_le_bytes());
+        }
+        self.prepend(&((table - end) as u16).to_le_bytes());
+        self.prepend(&(4 + 2 * len as u16).to_le_bytes());
+        let vtable = self.position();
+        let offset = ((vtable - table) as i32).to_le_bytes();
+        for (i, byte) in offset.iter().enumerate() {
+            self.reversed[table - 1 - i] = *byte;
+        }
+        table
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        self.align(4, 8);
+        self.offset(root);
+        self.reversed.reverse();
+        self.reversed
+    }
+}
+
+/// The Arrow Int or FloatingPoint type of values of `parse_type`, 24 bit
+/// integers are widened to 32 bits and LEB128 ones are 64 bits.
+pub fn column_type(parse_type: &ParseType) -> ParseType {
+    match parse_type {
+        ParseType::U24 => ParseType::U32,
+        ParseType::I24 => ParseType::I32,
+        ParseType::Uleb128 => ParseType::U64,
+        ParseType::Sleb128 => ParseType::I64,
+        parse_type => parse_type.clone(),
+    }
+}
+
+/// The Schema table of columns of `types` named `names`.
+fn schema(builder: &mut Builder, names: &[String], types: &[ParseType]) -> usize {
+    let mut fields = Vec::new();
+    for (name, parse_type) in names.iter().zip(types) {
+        let (type_type, arrow_type) = match parse_type {
+            ParseType::F32 | ParseType::F64 => {
+                let precision: i16 = match parse_type {
+                    ParseType::F32 => 1,
+                    _ => 2,
+                };
+                let slots = [(0, Slot::Scalar(precision.to_le_bytes().to_vec()))];
+                (TYPE_FLOATING_POINT, builder.table(&slots))
+            }
+            _ => {
+                let bits = parse_type.size_of() as i32 * 8;
+                let signed = matches!(
+                    parse_type,
+                    ParseType::I8 | ParseType::I16 | ParseType::I32 | ParseType::I64
+                );
+                let slots = [
+                    (0, Slot::Scalar(bits.to_le_bytes().to_vec())),
+                    (1, Slot::Scalar(vec![signed as u8])),
+                ];
+                (TYPE_INT, builder.table(&slots))
+            }
+        };
+        let name = builder.string(name);
+        let children = builder.offsets(&[]);
+        fields.push(builder.table(&[
+            (0, Slot::Offset(name)),
+            (1, Slot::Scalar(vec![0])),
+            (2, Slot::Scalar(vec![type_type])),
+            (3, Slot::Offset(arrow_type)),
+            (5, Slot::Offset(children)),
+        ]));
+    }
+    let fields = builder.offsets(&fields);
+    // Little-endian.
+    builder.table(&[(0, Slot::Scalar(vec![0, 0])), (1, Slot::Offset(fields))])
+}
+
+/// A Message of `header_type` with a body of `body_len` bytes.
+fn message(mut builder: Builder, header_type: u8, header: usize, body_len: u64) -> Vec<u8> {
+    let message = builder.table(&[
+        (3, Slot::Scalar(body_len.to_le_bytes().to_vec())),
+        (0, Slot::Scalar(VERSION.to_le_bytes().to_vec())),
+        (1, Slot::Scalar(vec![header_type])),
+        (2, Slot::Offset(header)),
+    ]);
+    builder.finish(message)
+}
+
+/// Writes values a row at a time as columns of an Arrow IPC file, a record
+/// batch every `batch_size` rows.
+pub struct ArrowWriter {
+    file: BufWriter<File>,
+    names: Vec<String>,
+    types: Vec<ParseType>,
+    batch_size: usize,
+    // Values of the current batch, a column after another.
+    columns: Vec<Vec<u8>>,
+    // Column of the next value.
+    column: usize,
+    rows: usize,
+    position: u64,
+    // Offset, metadata length and body length of the record batches.
+    blocks: Vec<(u64, u32, u64)>,
+}
+
+impl ArrowWriter {
+    /// Creates `path` with a column of every type of `types`, see
+    /// [`column_type`], named `names`.
+    pub fn create(
+        path: &str,
+        names: Vec<String>,
+        types: Vec<ParseType>,
+        batch_size: usize,
+    ) -> std::io::Result<Self> {
+        let mut writer = ArrowWriter {
+            file: BufWriter::new(File::create(path)?),
+            columns: vec![Vec::new(); types.len()],
+            names,
+            types,
+            batch_size,
+            column: 0,
+            rows: 0,
+            position: 0,
+            blocks: Vec::new(),
+        };
+        writer.write(&[MAGIC, &[0, 0]].concat())?;
+        let mut builder = Builder::new();
+        let schema = schema(&mut builder, &writer.names, &writer.types);
+        let metadata = message(builder, HEADER_SCHEMA, schema, 0);
+        writer.write_message(&metadata, &[])?;
+        Ok(writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.position += bytes.len() as u64;
+        self.file.write_all(bytes)
+    }
+
+    /// Writes an encapsulated message and returns the length of its metadata.
+    fn write_message(&mut self, metadata: &[u8], body: &[u8]) -> std::io::Result<u32> {
+        let padding = (8 - metadata.len() % 8) % 8;
+        let len = (metadata.len() + padding) as u32;
+        self.write(&CONTINUATION.to_le_bytes())?;
+        self.write(&len.to_le_bytes())?;
+        self.write(metadata)?;
+        self.write(&vec![0; padding])?;
+        self.write(body)?;
+        Ok(8 + len)
+    }
+
+    /// Adds `value` to the next column, converted to the type of the column.
+    pub fn push(&mut self, value: Value) -> std::io::Result<()> {
+        let (int, float) = (value.as_i128().unwrap_or(0), value.as_f64());
+        let bytes = &mut self.columns[self.column];
+        match self.types[self.column] {
+            ParseType::U8 => bytes.extend((int as u8).to_le_bytes()),
+            ParseType::I8 => bytes.extend((int as i8).to_le_bytes()),
+            ParseType::U16 => bytes.extend((int as u16).to_le_bytes()),
+            ParseType::I16 => bytes.extend((int as i16).to_le_bytes()),
+            ParseType::U32 => bytes.extend((int as u32).to_le_bytes()),
+            ParseType::I32 => bytes.extend((int as i32).to_le_bytes()),
+            ParseType::I64 => bytes.extend((int as i64).to_le_bytes()),
+            ParseType::F32 => bytes.extend((float as f32).to_le_bytes()),
+            ParseType::F64 => bytes.extend(float.to_le_bytes()),
+            _ => bytes.extend((int as u64).to_le_bytes()),
+        }
+        self.column = (self.column + 1) % self.types.len();
+        if self.column == 0 {
+            self.rows += 1;
+            if self.rows == self.batch_size {
+                self.write_batch()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> std::io::Result<()> {
+        let mut body = Vec::new();
+        let mut nodes = Vec::new();
+        let mut buffers = Vec::new();
+        for column in &mut self.columns {
+            nodes.extend((self.rows as u64).to_le_bytes());
+            nodes.extend(0u64.to_le_bytes());
+            // Without nulls the validity bitmap is left out.
+            buffers.extend((body.len() as u64).to_le_bytes());
+            buffers.extend(0u64.to_le_bytes());
+            buffers.extend((body.len() as u64).to_le_bytes());
+            buffers.extend((column.len() as u64).to_le_bytes());
+            body.append(column);
+            body.resize(body.len().div_ceil(8) * 8, 0);
+        }
+        let mut builder = Builder::new();
+        let buffers = builder.structs(&buffers, 2 * self.types.len());
+        let nodes = builder.structs(&nodes, self.types.len());
+        let batch = builder.table(&[
+            (0, Slot::Scalar((self.rows as u64).to_le_bytes().to_vec())),
+            (1, Slot::Offset(nodes)),
+            (2, Slot::Offset(buffers)),
+        ]);
+        let metadata = message(builder, HEADER_RECORD_BATCH, batch, body.len() as u64);
+        let offset = self.position;
+        let metadata_len = self.write_message(&metadata, &body)?;
+        self.blocks.push((offset, metadata_len, body.len() as u64));
+        self.rows = 0;
+        Ok(())
+    }
+
+    /// Writes the last record batch and the footer and returns the number
+    /// of values of an incomplete last row, which are dropped.
+    pub fn finish(mut self) -> std::io::Result<usize> {
+        let dropped = self.column;
+        for (column, parse_type) in self.columns.iter_mut().zip(&self.types) {
+            column.truncate(self.rows * parse_type.size_of() as usize);
+        }
+        if self.rows > 0 {
+            self.write_batch()?;
+        }
+        // End of the stream.
+        self.write(&CONTINUATION.to_le_bytes())?;
+        self.write(&0u32.to_le_bytes())?;
+        let mut builder = Builder::new();
+        let mut blocks = Vec::new();
+        for (offset, metadata_len, body_len) in &self.blocks {
+            blocks.extend(offset.to_le_bytes());
+            blocks.extend(metadata_len.to_le_bytes());
+            blocks.extend([0; 4]);
+            blocks.extend(body_len.to_le_bytes());
+        }
+        let batches = builder.structs(&blocks, self.blocks.len());
+        let dictionaries = builder.structs(&[], 0);
+        let schema = schema(&mut builder, &self.names, &self.types);
+        let footer = builder.table(&[
+            (0, Slot::Scalar(VERSION.to_le_bytes().to_vec())),
+            (1, Slot::Offset(schema)),
+            (2, Slot::Offset(dictionaries)),
+            (3, Slot::Offset(batches)),
+        ]);
+        let footer = builder.finish(footer);
+        self.write(&footer)?;
+        self.write(&(footer.len() as u32).to_le_bytes())?;
+        self.write(MAGIC)?;
+        self.file.flush()?;
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+    }
+
+    /// Where field `id` of the table at `table` is, as a FlatBuffer reader
+    /// finds it through the vtable.
+    fn field(bytes: &[u8], table: usize, id: usize) -> Option<usize> {
+        let vtable = (table as i64 - u32_at(bytes, table) as i32 as i64) as usize;
+        if 4 + 2 * id >= u16_at(bytes, vtable) as usize {
+            return None;
+        }
+        match u16_at(bytes, vtable + 4 + 2 * id) {
+            0 => None,
+            offset => Some(table + offset as usize),
+        }
+    }
+
+    /// The object an offset at `at` refers to.
+    fn follow(bytes: &[u8], at: usize) -> usize {
+        at + u32_at(bytes, at) as usize
+    }
+
+    fn string(bytes: &[u8], at: usize) -> &str {
+        let len = u32_at(bytes, at) as usize;
+        std::str::from_utf8(&bytes[at + 4..at + 4 + len]).unwrap()
+    }
+
+    /// Name, Type union member, bit width or float precision and signedness.
+    type Column = (String, u8, u32, bool);
+
+    fn columns(bytes: &[u8], schema: usize) -> Vec<Column> {
+        let fields = follow(bytes, field(bytes, schema, 1).unwrap());
+        (0..u32_at(bytes, fields) as usize)
+            .map(|i| {
+                let column = follow(bytes, fields + 4 + 4 * i);
+                let name = string(bytes, follow(bytes, field(bytes, column, 0).unwrap()));
+                let type_type = bytes[field(bytes, column, 2).unwrap()];
+                let arrow_type = follow(bytes, field(bytes, column, 3).unwrap());
+                let children = follow(bytes, field(bytes, column, 5).unwrap());
+                assert_eq!(u32_at(bytes, children), 0);
+                let (bits, signed) = match type_type {
+                    TYPE_INT => (
+                        u32_at(bytes, field(bytes, arrow_type, 0).unwrap()),
+                        bytes[field(bytes, arrow_type, 1).unwrap()] == 1,
+                    ),
+                    _ => (
+                        u16_at(bytes, field(bytes, arrow_type, 0).unwrap()) as u32,
+                        true,
+                    ),
+                };
+                (name.to_string(), type_type, bits, signed)
+            })
+            .collect()
+    }
+
+    /// Reads the file like a reader going through the footer, returns the
+    /// columns and the bytes of every column of every record batch.
+    fn read(bytes: &[u8]) -> (Vec<Column>, Vec<Vec<Vec<u8>>>) {
+        assert_eq!(&bytes[..8], b"ARROW1\0\0");
+        assert_eq!(&bytes[bytes.len() - 6..], MAGIC);
+        let footer_len = u32_at(bytes, bytes.len() - 10) as usize;
+        let footer = &bytes[bytes.len() - 10 - footer_len..bytes.len() - 10];
+        let root = follow(footer, 0);
+        assert_eq!(
+            u16_at(footer, field(footer, root, 0).unwrap()),
+            VERSION as u16
+        );
+        let columns = columns(footer, follow(footer, field(footer, root, 1).unwrap()));
+        let blocks = follow(footer, field(footer, root, 3).unwrap());
+        let mut batches = Vec::new();
+        for block in 0..u32_at(footer, blocks) as usize {
+            let block = blocks + 4 + 24 * block;
+            let offset = u64_at(footer, block) as usize;
+            let metadata_len = u32_at(footer, block + 8) as usize;
+            let body_len = u64_at(footer, block + 16) as usize;
+            assert_eq!(offset % 8, 0);
+            assert_eq!(u32_at(bytes, offset), CONTINUATION);
+            assert_eq!(u32_at(bytes, offset + 4) as usize + 8, metadata_len);
+            let message = &bytes[offset + 8..offset + metadata_len];
+            let root = follow(message, 0);
+            assert_eq!(
+                message[field(message, root, 1).unwrap()],
+                HEADER_RECORD_BATCH
+            );
+            assert_eq!(
+                u64_at(message, field(message, root, 3).unwrap()) as usize,
+                body_len
+            );
+            let batch = follow(message, field(message, root, 2).unwrap());
+            let rows = u64_at(message, field(message, batch, 0).unwrap());
+            let nodes = follow(message, field(message, batch, 1).unwrap());
+            let buffers = follow(message, field(message, batch, 2).unwrap());
+            let body = &bytes[offset + metadata_len..offset + metadata_len + body_len];
+            let mut batch = Vec::new();
+            for (column, (_, type_type, bits, _)) in columns.iter().enumerate() {
+                // Vectors of structs of 8 byte alignment.
+                assert_eq!((nodes + 4) % 8, 0);
+                assert_eq!(u64_at(message, nodes + 4 + 16 * column), rows);
+                assert_eq!(u64_at(message, nodes + 12 + 16 * column), 0);
+                let data = buffers + 4 + 32 * column + 16;
+                let start = u64_at(message, data) as usize;
+                let len = u64_at(message, data + 8) as usize;
+                let size = match *type_type {
+                    TYPE_INT => bits / 8,
+                    _ => 2 << bits,
+                };
+                assert_eq!(len as u64, rows * size as u64);
+                batch.push(body[start..start + len].to_vec());
+            }
+            batches.push(batch);
+        }
+        (columns, batches)
+    }
+
+    fn temp_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("parsebin-arrow-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn columns_in_record_batches() {
+        let path = temp_path("columns.arrow");
+        let names = vec!["a".to_string(), "bee".to_string()];
+        let types = vec![ParseType::U16, ParseType::F64];
+        let mut writer = ArrowWriter::create(&path, names, types, 2).unwrap();
+        for row in 0..5u16 {
+            writer.push(Value::U16(row)).unwrap();
+            writer.push(Value::F64(row as f64 / 2.0)).unwrap();
+        }
+        // A row without its last column is dropped.
+        writer.push(Value::U16(9)).unwrap();
+        assert_eq!(writer.finish().unwrap(), 1);
+        let bytes = std::fs::read(&path).unwrap();
+        let (columns, batches) = read(&bytes);
+        assert_eq!(
+            columns,
+            [
+                ("a".to_string(), TYPE_INT, 16, false),
+                ("bee".to_string(), TYPE_FLOATING_POINT, 2, true)
+            ]
+        );
+        let sizes: Vec<usize> = batches.iter().map(|batch| batch[0].len() / 2).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        let a: Vec<u8> = batches.iter().flat_map(|batch| batch[0].clone()).collect();
+        let expected: Vec<u8> = (0..5u16).flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(a, expected);
+        let bee: Vec<u8> = batches.iter().flat_map(|batch| batch[1].clone()).collect();
+        let expected: Vec<u8> = (0..5)
+            .flat_map(|v| (v as f64 / 2.0).to_le_bytes())
+            .collect();
+        assert_eq!(bee, expected);
+    }
+
+    #[test]
+    fn values_are_converted_to_the_column_type() {
+        let path = temp_path("types.arrow");
+        let types = [
+            ParseType::I24,
+            ParseType::Sleb128,
+            ParseType::F32,
+            ParseType::I8,
+        ]
+        .iter()
+        .map(column_type)
+        .collect();
+        let names = (0..4).map(|column| format!("col{}", column)).collect();
+        let mut writer = ArrowWriter::create(&path, names, types, 100).unwrap();
+        writer.push(Value::I24(-2)).unwrap();
+        writer.push(Value::I64(i64::MIN)).unwrap();
+        writer.push(Value::F32(1.5)).unwrap();
+        writer.push(Value::I8(-128)).unwrap();
+        assert_eq!(writer.finish().unwrap(), 0);
+        let (columns, batches) = read(&std::fs::read(&path).unwrap());
+        let types: Vec<(u8, u32, bool)> = columns.iter().map(|c| (c.1, c.2, c.3)).collect();
+        assert_eq!(
+            types,
+            [
+                (TYPE_INT, 32, true),
+                (TYPE_INT, 64, true),
+                (TYPE_FLOATING_POINT, 1, true),
+                (TYPE_INT, 8, true)
+            ]
+        );
+        assert_eq!(batches[0][0], (-2i32).to_le_bytes());
+        assert_eq!(batches[0][1], i64::MIN.to_le_bytes());
+        assert_eq!(batches[0][2], 1.5f32.to_le_bytes());
+        assert_eq!(batches[0][3], [0x80]);
+    }
+
+    #[test]
+    fn no_values() {
+        let path = temp_path("empty.arrow");
+        let writer = ArrowWriter::create(&path, vec!["x".to_string()], vec![ParseType::U8], 10);
+        assert_eq!(writer.unwrap().finish().unwrap(), 0);
+        let (columns, batches) = read(&std::fs::read(&path).unwrap());
+        assert_eq!(columns.len(), 1);
+        assert!(batches.is_empty());
+    }
+
+    #[test]
+    fn schema_message_at_the_start() {
+        let path = temp_path("schema.arrow");
+        let writer = ArrowWriter::create(&path, vec!["x".to_string()], vec![ParseType::I64], 10);
+        writer.unwrap().finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(u32_at(&bytes, 8), CONTINUATION);
+        let len = u32_at(&bytes, 12) as usize;
+        assert_eq!(len % 8, 0);
+        let message = &bytes[16..16 + len];
+        let root = follow(message, 0);
+        assert_eq!(message[field(message, root, 1).unwrap()], HEADER_SCHEMA);
+        let schema = follow(message, field(message, root, 2).unwrap());
+        assert_eq!(
+            columns(message, schema),
+            [("x".to_string(), TYPE_INT, 64, true)]
+        );
+        // The end of stream marker follows right after.
+        assert_eq!(
+            &bytes[16 + len..24 + len],
+            [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::Read;
+
+use clap::ValueEnum;
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+use parsebin::SizeOf;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum BitOrder {
+    /// Fields start at the most significant bit of a byte, like JPEG
+    Msb,
+    /// Fields start at the least significant bit of a byte, like DEFLATE
+    Lsb,
+}
+
+impl BitOrder {
+    /// Bit order matching `byte_order`, most significant first for big-endian.
+    pub fn of(byte_order: &ByteOrder) -> BitOrder {
+        let big_endian = match byte_order {
+            ByteOrder::LittleEndian => false,
+            ByteOrder::BigEndian => true,
+            ByteOrder::Native => cfg!(target_endian = "big"),
+        };
+        match big_endian {
+            true => BitOrder::Msb,
+            false => BitOrder::Lsb,
+        }
+    }
+}
+
+/// What was read of packed fields.
+pub struct Unpacked {
+    /// Packed bytes the fields were read from.
+    pub packed_len: u64,
+    /// Bits at the end of the input too few for another field.
+    pub trailing_bits: u32,
+}
+
+/// Reads at most `number` fields of `width` bits from `reader` and widens
+/// them to `parse_type` in `byte_order`, so they can be parsed like any other
+/// values. Fields are sign-extended for signed types.
+///
+/// The input is read a chunk at a time and the widened fields of every chunk
+/// are passed to `emit`, bits of a field split between chunks are carried
+/// over. Reading stops early once `emit` returns false.
+///
+/// With `BitOrder::Msb` the first bit of a field is its most significant one
+/// and bytes are consumed from their most significant bit, with
+/// `BitOrder::Lsb` both start at the least significant bit.
+pub fn unpack(
+    reader: &mut impl Read,
+    width: u32,
+    bit_order: BitOrder,
+    parse_type: &ParseType,
+    byte_order: &ByteOrder,
+    number: u64,
+    mut emit: impl FnMut(&[u8]) -> std::io::Result<bool>,
+) -> std::io::Result<Unpacked> {
+    let size = parse_type.size_of() as usize;
+    let signed = matches!(
+        parse_type,
+        ParseType::I8 | ParseType::I16 | ParseType::I24 | ParseType::I32 | ParseType::I64
+    );
+    let little_endian = match byte_order {
+        ByteOrder::LittleEndian => true,
+        ByteOrder::BigEndian => false,
+        ByteOrder::Native => cfg!(target_endian = "little"),
+    };
+    let mask = match width {
+        64 => u64::MAX,
+        _ => (1 << width) - 1,
+    };
+    let mut chunk = vec![0; 1 << 16];
+    let mut bytes = Vec::new();
+    // Bits read but not yet part of a field, at most 7 more than a field.
+    let mut bits = 0u128;
+    let mut bit_count = 0;
+    let mut fields = 0;
+    let mut stopped = false;
+    while fields < number && !stopped {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for byte in &chunk[..n] {
+            match bit_order {
+                BitOrder::Msb => bits = bits << 8 | *byte as u128,
+                BitOrder::Lsb => bits |= (*byte as u128) << bit_count,
+            }
+            bit_count += 8;
+            while bit_count >= width && fields < number {
+                let mut field = match bit_order {
+                    BitOrder::Msb => (bits >> (bit_count - width)) as u64 & mask,
+                    BitOrder::Lsb => {
+                        let field = bits as u64 & mask;
+                        bits >>= width;
+                        field
+                    }
+                };
+                bit_count -= width;
+                if bit_order == BitOrder::Msb {
+                    bits &= (1 << bit_count) - 1;
+                }
+                if signed && width < 64 && field >> (width - 1) & 1 == 1 {
+                    field |= u64::MAX << width;
+                }
+                let widened = &field.to_le_bytes()[..size];
+                match little_endian {
+                    true => bytes.extend(widened),
+                    false => bytes.extend(widened.iter().rev()),
+                }
+                fields += 1;
+            }
+            if fields == number {
+                break;
+            }
+        }
+        stopped = !emit(&bytes)?;
+        bytes.clear();
+    }
+    let trailing_bits = match fields < number && !stopped {
+        true => bit_count,
+        false => 0,
+    };
+    Ok(Unpacked {
+        packed_len: (fields * width as u64).div_ceil(8),
+        trailing_bits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The unpacked bytes and what was read.
+    fn unpack_all(
+        mut reader: impl Read,
+        width: u32,
+        bit_order: BitOrder,
+        parse_type: ParseType,
+        number: u64,
+    ) -> (Vec<u8>, Unpacked) {
+        let mut bytes = Vec::new();
+        let unpacked = unpack(
+            &mut reader,
+            width,
+            bit_order,
+            &parse_type,
+            &ByteOrder::LittleEndian,
+            number,
+            |chunk| {
+                bytes.extend(chunk);
+                Ok(true)
+            },
+        )
+        .unwrap();
+        (bytes, unpacked)
+    }
+
+    #[test]
+    fn nibbles_in_both_bit_orders() {
+        let packed = [0b1010_0101, 0b1100_0011];
+        let (msb, _) = unpack_all(&packed[..], 4, BitOrder::Msb, ParseType::U8, u64::MAX);
+        assert_eq!(msb, [10, 5, 12, 3]);
+        let (lsb, _) = unpack_all(&packed[..], 4, BitOrder::Lsb, ParseType::U8, u64::MAX);
+        assert_eq!(lsb, [5, 10, 3, 12]);
+    }
+
+    #[test]
+    fn fields_across_bytes_and_reads() {
+        let packed = [0xab, 0xcd, 0xef];
+        // Every byte is a read of its own.
+        let split = (&packed[..1]).chain(&packed[1..2]).chain(&packed[2..]);
+        let (bytes, unpacked) = unpack_all(split, 12, BitOrder::Msb, ParseType::U16, u64::MAX);
+        assert_eq!(bytes, [0xbc, 0x0a, 0xef, 0x0d]);
+        assert_eq!((unpacked.packed_len, unpacked.trailing_bits), (3, 0));
+        let split = (&packed[..1]).chain(&packed[1..2]).chain(&packed[2..]);
+        let (bytes, _) = unpack_all(split, 12, BitOrder::Lsb, ParseType::U16, u64::MAX);
+        assert_eq!(bytes, [0xab, 0x0d, 0xfc, 0x0e]);
+    }
+
+    #[test]
+    fn signed_fields_are_sign_extended() {
+        let (bytes, _) = unpack_all(&[0xf7][..], 4, BitOrder::Msb, ParseType::I8, u64::MAX);
+        assert_eq!(bytes, [0xff, 0x07]);
+        let packed = u64::MAX.to_be_bytes();
+        let (bytes, _) = unpack_all(&packed[..], 64, BitOrder::Msb, ParseType::I64, u64::MAX);
+        assert_eq!(bytes, packed);
+    }
+
+    #[test]
+    fn trailing_bits() {
+        let (bytes, unpacked) = unpack_all(&[0; 3][..], 5, BitOrder::Msb, ParseType::U8, u64::MAX);
+        assert_eq!(bytes.len(), 4);
+        assert_eq!((unpacked.packed_len, unpacked.trailing_bits), (3, 4));
+        // Bits left after the last field asked for aren't trailing.
+        let (_, unpacked) = unpack_all(&[0; 3][..], 5, BitOrder::Msb, ParseType::U8, 2);
+        assert_eq!((unpacked.packed_len, unpacked.trailing_bits), (2, 0));
+    }
+
+    #[test]
+    fn endless_input() {
+        let (bytes, _) = unpack_all(std::io::repeat(0x12), 4, BitOrder::Msb, ParseType::U8, 3);
+        assert_eq!(bytes, [1, 2, 1]);
+        let mut chunks = 0;
+        let unpacked = unpack(
+            &mut std::io::repeat(0),
+            8,
+            BitOrder::Lsb,
+            &ParseType::U8,
+            &ByteOrder::LittleEndian,
+            u64::MAX,
+            |_| {
+                chunks += 1;
+                Ok(chunks < 2)
+            },
+        )
+        .unwrap();
+        assert_eq!(chunks, 2);
+        assert_eq!(unpacked.trailing_bits, 0);
+    }
+}
@@ -0,0 +1,121 @@
+use clap::ValueEnum;
+
+/// Stops of the gradient from the lowest to the highest value, taken from the
+/// viridis color map which stays readable on dark and light backgrounds.
+const GRADIENT: [(f64, f64, f64); 5] = [
+    (68.0, 1.0, 84.0),
+    (59.0, 82.0, 139.0),
+    (33.0, 145.0, 140.0),
+    (94.0, 201.0, 98.0),
+    (253.0, 231.0, 37.0),
+];
+
+/// Bold red for NaN and infinities so they stand out among the gradient.
+pub const SPECIAL: &str = "\x1b[1;31m";
+
+/// Bold yellow for subnormal floats highlighted by --highlight-special.
+pub const SUBNORMAL: &str = "\x1b[1;33m";
+
+pub const RESET: &str = "\x1b[0m";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// Color if the output is a terminal and NO_COLOR isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Maps values to truecolor escapes on a gradient between `min` and `max`.
+///
+/// Without known bounds the range grows with the values seen so far, so the
+/// first values of a stream are colored against each other only.
+#[derive(Debug, Clone)]
+pub struct ColorScale {
+    min: f64,
+    max: f64,
+    growing: bool,
+}
+
+impl ColorScale {
+    pub fn new(min: f64, max: f64) -> Self {
+        ColorScale {
+            min,
+            max,
+            growing: false,
+        }
+    }
+
+    pub fn growing() -> Self {
+        ColorScale {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            growing: true,
+        }
+    }
+
+    /// Returns the escape that starts the color of `value`.
+    pub fn escape(&mut self, value: f64) -> String {
+        if !value.is_finite() {
+            return SPECIAL.to_string();
+        }
+        if self.growing {
+            self.min = f64::min(self.min, value);
+            self.max = f64::max(self.max, value);
+        }
+        let position = match self.max > self.min {
+            true => ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0),
+            false => 0.5,
+        };
+        let scaled = position * (GRADIENT.len() - 1) as f64;
+        let stop = std::cmp::min(scaled as usize, GRADIENT.len() - 2);
+        let fraction = scaled - stop as f64;
+        let (from, to) = (GRADIENT[stop], GRADIENT[stop + 1]);
+        let mix = |a: f64, b: f64| (a + (b - a) * fraction).round() as u8;
+        format!(
+            "\x1b[38;2;{};{};{}m",
+            mix(from.0, to.0),
+            mix(from.1, to.1),
+            mix(from.2, to.2)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_and_middle_of_the_gradient() {
+        let mut scale = ColorScale::new(-1.0, 1.0);
+        assert_eq!(scale.escape(-1.0), "\x1b[38;2;68;1;84m");
+        assert_eq!(scale.escape(0.0), "\x1b[38;2;33;145;140m");
+        assert_eq!(scale.escape(1.0), "\x1b[38;2;253;231;37m");
+        // Halfway between the first two stops.
+        assert_eq!(scale.escape(-0.75), "\x1b[38;2;64;42;112m");
+        // Values outside of the bounds get the color of the nearest one.
+        assert_eq!(scale.escape(-5.0), scale.escape(-1.0));
+        assert_eq!(scale.escape(5.0), scale.escape(1.0));
+    }
+
+    #[test]
+    fn non_finite_values_are_special() {
+        let mut scale = ColorScale::new(0.0, 1.0);
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(scale.escape(value), SPECIAL);
+        }
+    }
+
+    #[test]
+    fn growing_range() {
+        let mut scale = ColorScale::growing();
+        // A single value is the middle of an empty range.
+        assert_eq!(scale.escape(3.0), "\x1b[38;2;33;145;140m");
+        assert_eq!(scale.escape(5.0), "\x1b[38;2;253;231;37m");
+        assert_eq!(scale.escape(1.0), "\x1b[38;2;68;1;84m");
+        assert_eq!(scale.escape(3.0), "\x1b[38;2;33;145;140m");
+        // NaN doesn't widen the range.
+        scale.escape(f64::NAN);
+        assert_eq!(scale.escape(5.0), "\x1b[38;2;253;231;37m");
+    }
+}
@@ -0,0 +1,182 @@
+//! Shell completion scripts generated from the clap definition of the options.
+
+use clap::Arg;
+use clap::Command;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// An option as far as completion is concerned.
+struct Flag {
+    /// Names with their dashes, e.g. `-b` and `--byte-order`.
+    names: Vec<String>,
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    takes_value: bool,
+    values: Vec<String>,
+}
+
+fn flags(command: &Command) -> Vec<Flag> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .map(|arg| {
+            let long = arg.get_long().map(|long| long.to_string());
+            let aliases = arg.get_visible_aliases().unwrap_or_default();
+            let names = arg
+                .get_short()
+                .map(|short| format!("-{}", short))
+                .into_iter()
+                .chain(long.iter().map(|long| format!("--{}", long)))
+                .chain(aliases.iter().map(|alias| format!("--{}", alias)))
+                .collect();
+            Flag {
+                names,
+                long,
+                short: arg.get_short(),
+                help: help(arg),
+                takes_value: arg.get_action().takes_values(),
+                values: values(arg),
+            }
+        })
+        .collect()
+}
+
+/// First line of the help of `arg` without characters that need quoting.
+fn help(arg: &Arg) -> String {
+    let help = arg
+        .get_help()
+        .map(|help| help.to_string())
+        .unwrap_or_default();
+    let line = help.lines().next().unwrap_or_default();
+    line.chars()
+        .filter(|c| !matches!(c, '\'' | '"' | '[' | ']' | ':' | '`' | '$' | '\\'))
+        .collect()
+}
+
+fn values(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+/// Values of the first positional argument, the type.
+fn types(command: &Command) -> Vec<String> {
+    command
+        .get_positionals()
+        .next()
+        .map(values)
+        .unwrap_or_default()
+}
+
+/// Completion script of `command` for `shell`.
+pub fn generate(shell: Shell, command: &Command) -> String {
+    let name = command.get_name();
+    let flags = flags(command);
+    let types = types(command).join(" ");
+    let mut script = String::new();
+    match shell {
+        Shell::Bash => {
+            let function = format!("_{}", name.replace('-', "_"));
+            let names: Vec<&str> = flags
+                .iter()
+                .flat_map(|flag| flag.names.iter().map(|name| name.as_str()))
+                .collect();
+            script += &format!("{}() {{\n", function);
+            script += "    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n";
+            script += "    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n";
+            script += "    case \"$prev\" in\n";
+            for flag in flags.iter().filter(|flag| flag.takes_value) {
+                let reply = match flag.values.is_empty() {
+                    true => "compgen -f -- \"$cur\"".to_string(),
+                    false => format!("compgen -W \"{}\" -- \"$cur\"", flag.values.join(" ")),
+                };
+                script += &format!(
+                    "        {})\n            COMPREPLY=($({}))\n            return ;;\n",
+                    flag.names.join("|"),
+                    reply
+                );
+            }
+            script += "    esac\n";
+            script += "    if [[ \"$cur\" == -* ]]; then\n";
+            script += &format!(
+                "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+                names.join(" ")
+            );
+            script += "    else\n";
+            script += &format!(
+                "        COMPREPLY=($(compgen -W \"{}\" -f -- \"$cur\"))\n",
+                types
+            );
+            script += "    fi\n}\n";
+            script += &format!("complete -o filenames -F {} {}\n", function, name);
+        }
+        Shell::Zsh => {
+            script += &format!("#compdef {}\n\n_arguments -s \\\n", name);
+            for flag in &flags {
+                let action = match (flag.takes_value, flag.values.is_empty()) {
+                    (false, _) => String::new(),
+                    (true, true) => ":value:_files".to_string(),
+                    (true, false) => format!(":value:({})", flag.values.join(" ")),
+                };
+                for name in &flag.names {
+                    script += &format!("    '{}[{}]{}' \\\n", name, flag.help, action);
+                }
+            }
+            script += &format!("    '::type:({})' \\\n", types);
+            script += "    '*:file:_files'\n";
+        }
+        Shell::Fish => {
+            script += &format!("complete -c {} -a '{}'\n", name, types);
+            for flag in &flags {
+                let mut line = format!("complete -c {}", name);
+                if let Some(short) = flag.short {
+                    line += &format!(" -s {}", short);
+                }
+                if let Some(long) = &flag.long {
+                    line += &format!(" -l {}", long);
+                }
+                line += &format!(" -d '{}'", flag.help);
+                match (flag.takes_value, flag.values.is_empty()) {
+                    (false, _) => (),
+                    (true, true) => line += " -r",
+                    (true, false) => line += &format!(" -x -a '{}'", flag.values.join(" ")),
+                }
+                script += &line;
+                script += "\n";
+            }
+        }
+        Shell::Powershell => {
+            let quoted = |words: Vec<&str>| {
+                let words: Vec<String> = words.iter().map(|word| format!("'{}'", word)).collect();
+                words.join(", ")
+            };
+            let names = flags
+                .iter()
+                .flat_map(|flag| flag.names.iter().map(|name| name.as_str()))
+                .collect();
+            script += &format!(
+                "Register-ArgumentCompleter -Native -CommandName '{}' -ScriptBlock {{\n",
+                name
+            );
+            script += "    param($wordToComplete, $commandAst, $cursorPosition)\n";
+            script += &format!("    $options = @({})\n", quoted(names));
+            script += &format!("    $types = @({})\n", quoted(types.split(' ').collect()));
+            script +=
+                "    $candidates = if ($wordToComplete -like '-*') { $options } else { $types }\n";
+            script += "    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n";
+            script += "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n";
+            script += "    }\n}\n";
+        }
+    }
+    script
+}
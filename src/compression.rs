@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decompressed bytes read to check that a file detected by its magic bytes
+/// is really compressed.
+const PROBE_LEN: u64 = 1 << 16;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// Detect by the .gz or .zst extension
+    Auto,
+    /// Detect by the extension or else by the magic bytes, falling back to
+    /// the raw bytes if they don't decompress
+    Magic,
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Resolves `Auto` and `Magic` to the compression used by the file at
+    /// `path`, the magic bytes are only read if `sniff` is set since that
+    /// consumes them from pipes.
+    pub fn detect(self, path: &str, sniff: bool) -> std::io::Result<Compression> {
+        if !matches!(self, Compression::Auto | Compression::Magic) {
+            return Ok(self);
+        }
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("gz") => return Ok(Compression::Gzip),
+            Some("zst") => return Ok(Compression::Zstd),
+            _ if self == Compression::Auto || !sniff => return Ok(Compression::None),
+            _ => (),
+        }
+        let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+        File::open(path)?
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+        let compression = if magic.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if magic.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            return Ok(Compression::None);
+        };
+        // Raw data may start like compressed data by chance.
+        let decompresses = open(path, compression)
+            .and_then(|reader| std::io::copy(&mut reader.take(PROBE_LEN), &mut std::io::sink()));
+        Ok(match decompresses {
+            Ok(_) => compression,
+            Err(_) => Compression::None,
+        })
+    }
+}
+
+/// Opens `path` and wraps it in a decompressor unless `compression` is `None`.
+pub fn open(path: &str, compression: Compression) -> std::io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    Ok(match compression.detect(path, false)? {
+        Compression::Auto | Compression::Magic | Compression::None => Box::new(file),
+        // Concatenated gzip members decompress to the concatenated data, like zcat.
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+    })
+}
@@ -0,0 +1,144 @@
+//! A tiny self-describing container for raw values.
+//!
+//! Layout of the fixed 24 byte header (all multi-byte fields little-endian):
+//!
+//! | offset | size | field                                            |
+//! |--------|------|--------------------------------------------------|
+//! | 0      | 8    | magic, `PARSEBIN`                                |
+//! | 8      | 1    | version, currently 1                             |
+//! | 9      | 1    | parse type code, see `type_code`                 |
+//! | 10     | 1    | byte order of the payload, 0 = little, 1 = big   |
+//! | 11     | 1    | reserved, written as 0                           |
+//! | 12     | 4    | header length in bytes, payload starts after it  |
+//! | 16     | 8    | number of elements in the payload                |
+//!
+//! New fields are only ever appended after the existing ones and announced by
+//! bumping the version and header length. A reader therefore understands any
+//! header with a known layout prefix and skips the rest using the header
+//! length, while plain consumers can skip the whole header with `--offset 24`.
+
+use crate::{ByteOrder, ParseType};
+
+pub const MAGIC: &[u8; 8] = b"PARSEBIN";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 24;
+
+#[derive(Debug)]
+pub struct Header {
+    pub version: u8,
+    pub parse_type: ParseType,
+    pub byte_order: ByteOrder,
+    pub header_len: u32,
+    pub count: u64,
+}
+
+fn type_code(parse_type: &ParseType) -> u8 {
+    match parse_type {
+        ParseType::U8 => 1,
+        ParseType::U16 => 2,
+        ParseType::U32 => 3,
+        ParseType::U64 => 4,
+        ParseType::I8 => 5,
+        ParseType::I16 => 6,
+        ParseType::I32 => 7,
+        ParseType::I64 => 8,
+        ParseType::F32 => 9,
+        ParseType::F64 => 10,
+        ParseType::U24 => 11,
+        ParseType::I24 => 12,
+        ParseType::Uleb128 => 13,
+        ParseType::Sleb128 => 14,
+    }
+}
+
+fn type_from_code(code: u8) -> Option<ParseType> {
+    match code {
+        1 => Some(ParseType::U8),
+        2 => Some(ParseType::U16),
+        3 => Some(ParseType::U32),
+        4 => Some(ParseType::U64),
+        5 => Some(ParseType::I8),
+        6 => Some(ParseType::I16),
+        7 => Some(ParseType::I32),
+        8 => Some(ParseType::I64),
+        9 => Some(ParseType::F32),
+        10 => Some(ParseType::F64),
+        11 => Some(ParseType::U24),
+        12 => Some(ParseType::I24),
+        13 => Some(ParseType::Uleb128),
+        14 => Some(ParseType::Sleb128),
+        _ => None,
+    }
+}
+
+impl Header {
+    pub fn new(parse_type: ParseType, byte_order: ByteOrder, count: u64) -> Self {
+        Header {
+            version: VERSION,
+            parse_type,
+            byte_order,
+            header_len: HEADER_LEN as u32,
+            count,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..8].copy_from_slice(MAGIC);
+        bytes[8] = self.version;
+        bytes[9] = type_code(&self.parse_type);
+        bytes[10] = match self.byte_order {
+            ByteOrder::LittleEndian => 0,
+            ByteOrder::BigEndian => 1,
+            ByteOrder::Native if cfg!(target_endian = "big") => 1,
+            ByteOrder::Native => 0,
+        };
+        bytes[12..16].copy_from_slice(&self.header_len.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.count.to_le_bytes());
+        bytes
+    }
+
+    /// Parses a header from the start of `bytes`.
+    ///
+    /// Returns `None` if the bytes don't start with the magic, and an error if
+    /// they do but the header is truncated or unsupported.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Result<Header, String>> {
+        if !bytes.starts_with(MAGIC) {
+            return None;
+        }
+        if bytes.len() < HEADER_LEN {
+            return Some(Err(format!(
+                "Truncated parsebin header: {} < {} bytes",
+                bytes.len(),
+                HEADER_LEN
+            )));
+        }
+        let version = bytes[8];
+        if version == 0 {
+            return Some(Err("Unsupported parsebin header version: 0".to_string()));
+        }
+        let parse_type = match type_from_code(bytes[9]) {
+            Some(t) => t,
+            None => return Some(Err(format!("Unknown type code in header: {}", bytes[9]))),
+        };
+        let byte_order = match bytes[10] {
+            0 => ByteOrder::LittleEndian,
+            1 => ByteOrder::BigEndian,
+            b => return Some(Err(format!("Unknown byte order in header: {}", b))),
+        };
+        let header_len = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+        if (header_len as usize) < HEADER_LEN {
+            return Some(Err(format!("Invalid header length: {}", header_len)));
+        }
+        let count = u64::from_le_bytes([
+            bytes[16], bytes[17], bytes[18], bytes[19], bytes[20], bytes[21], bytes[22], bytes[23],
+        ]);
+        Some(Ok(Header {
+            version,
+            parse_type,
+            byte_order,
+            header_len,
+            count,
+        }))
+    }
+}
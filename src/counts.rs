@@ -0,0 +1,74 @@
+//! Occurrences of the distinct values of a column for --value-counts.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use parsebin::Value;
+
+/// Counts of distinct values, at most `max_distinct` of them so data with
+/// too many distinct values fails instead of using up the memory.
+pub struct ValueCounts {
+    // Integers by their value and floats by their bits, with the value.
+    counts: HashMap<i128, (Value, u64)>,
+    max_distinct: usize,
+    total: u64,
+}
+
+/// Order of values by their number, NaN after all others.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a.as_i128(), b.as_i128()) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.as_f64().total_cmp(&b.as_f64()),
+    }
+}
+
+impl ValueCounts {
+    pub fn new(max_distinct: usize) -> Self {
+        ValueCounts {
+            counts: HashMap::new(),
+            max_distinct,
+            total: 0,
+        }
+    }
+
+    /// Counts `value`, fails with the maximum if it would be one distinct
+    /// value too many.
+    pub fn push(&mut self, value: Value) -> Result<(), usize> {
+        // All NaNs count as one and -0 as 0.
+        let value = match value {
+            Value::F32(v) => Value::F32(v + 0.0),
+            Value::F64(v) => Value::F64(v + 0.0),
+            value => value,
+        };
+        let key = match value.as_i128() {
+            Some(v) => v,
+            None if value.as_f64().is_nan() => f64::NAN.to_bits() as i128,
+            None => value.as_f64().to_bits() as i128,
+        };
+        if !self.counts.contains_key(&key) && self.counts.len() == self.max_distinct {
+            return Err(self.max_distinct);
+        }
+        self.counts.entry(key).or_insert((value, 0)).1 += 1;
+        self.total += 1;
+        Ok(())
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The values with their counts, the most frequent first or ordered by
+    /// value with `by_value`. Equal counts are ordered by value.
+    pub fn sorted(&self, by_value: bool) -> Vec<(Value, u64)> {
+        let mut counts: Vec<(Value, u64)> = self.counts.values().copied().collect();
+        counts.sort_by(|a, b| compare(&a.0, &b.0));
+        if !by_value {
+            counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        }
+        counts
+    }
+}
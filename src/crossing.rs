@@ -0,0 +1,150 @@
+use std::fmt::Display;
+use std::io::Write;
+
+use parsebin::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Rising,
+    Falling,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Rising => write!(f, "rising"),
+            Direction::Falling => write!(f, "falling"),
+        }
+    }
+}
+
+/// Detector of the crossings of a threshold by a stream of values.
+///
+/// With a hysteresis the signal has to rise to `threshold + hysteresis / 2`
+/// for a rising crossing and fall below `threshold - hysteresis / 2` for a
+/// falling one, so noise around the threshold isn't reported as crossings.
+/// Values start out neither low nor high until they leave that band, and
+/// NaN is skipped.
+#[derive(Debug, Clone)]
+pub struct Crossings {
+    low: f64,
+    high: f64,
+    // Direction of the last crossing or where the values started.
+    level: Option<Direction>,
+    previous: Option<Value>,
+    index: u64,
+    rising: u64,
+    falling: u64,
+}
+
+impl Crossings {
+    pub fn new(threshold: f64, hysteresis: f64) -> Self {
+        Crossings {
+            low: threshold - hysteresis / 2.0,
+            high: threshold + hysteresis / 2.0,
+            level: None,
+            previous: None,
+            index: 0,
+            rising: 0,
+            falling: 0,
+        }
+    }
+
+    /// Adds the next value and returns its index, the direction and the value
+    /// before it if it completes a crossing.
+    pub fn push(&mut self, value: Value) -> Option<(u64, Direction, Value)> {
+        let index = self.index;
+        self.index += 1;
+        let v = value.as_f64();
+        if v.is_nan() {
+            return None;
+        }
+        let previous = self.previous.replace(value);
+        let level = match v {
+            v if v >= self.high => Direction::Rising,
+            v if v < self.low => Direction::Falling,
+            _ => return None,
+        };
+        match self.level.replace(level) {
+            Some(current) if current != level => {
+                match level {
+                    Direction::Rising => self.rising += 1,
+                    Direction::Falling => self.falling += 1,
+                }
+                previous.map(|previous| (index, level, previous))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn print_summary(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{} crossings, {} rising and {} falling",
+            self.rising + self.falling,
+            self.rising,
+            self.falling
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten periods of a sine of 100 samples, shifted by half a sample so no
+    /// sample is exactly 0.
+    fn sine() -> impl Iterator<Item = Value> {
+        (0..1000).map(|k| Value::F64((2.0 * std::f64::consts::PI * (k as f64 + 0.5) / 100.0).sin()))
+    }
+
+    #[test]
+    fn crossings_of_a_sine() {
+        let mut crossings = Crossings::new(0.0, 0.0);
+        let found: Vec<(u64, Direction)> = sine()
+            .filter_map(|value| crossings.push(value))
+            .map(|(index, direction, _)| (index, direction))
+            .collect();
+        // It starts high, falls in the middle of every period and rises at
+        // the start of all but the first.
+        assert_eq!(found.len(), 19);
+        assert_eq!(found[0], (50, Direction::Falling));
+        assert_eq!(found[1], (100, Direction::Rising));
+        assert_eq!(found[18], (950, Direction::Falling));
+        let mut out = Vec::new();
+        crossings.print_summary(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "19 crossings, 9 rising and 10 falling\n"
+        );
+    }
+
+    #[test]
+    fn hysteresis_ignores_noise() {
+        let mut crossings = Crossings::new(1.0, 0.5);
+        let noise = [1.1, 0.9, 1.2, 0.8, 1.3, 0.5, 0.9, 1.1, 1.2, 1.25];
+        let found: Vec<(u64, Direction, Value)> = noise
+            .iter()
+            .filter_map(|v| crossings.push(Value::F64(*v)))
+            .collect();
+        // Within 0.75 and 1.25 nothing is a crossing, 1.3 only starts out high.
+        assert_eq!(
+            found,
+            [
+                (5, Direction::Falling, Value::F64(1.3)),
+                (9, Direction::Rising, Value::F64(1.2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn nan_is_skipped() {
+        let mut crossings = Crossings::new(0.0, 0.0);
+        assert_eq!(crossings.push(Value::F32(-1.0)), None);
+        assert_eq!(crossings.push(Value::F32(f32::NAN)), None);
+        assert_eq!(
+            crossings.push(Value::F32(1.0)),
+            Some((2, Direction::Rising, Value::F32(-1.0)))
+        );
+    }
+}
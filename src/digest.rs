@@ -0,0 +1,329 @@
+//! Checksums of the bytes in the selected range, CRC-32 as used by gzip and
+//! zip, MD5 (RFC 1321) and SHA-256 (FIPS 180-4).
+
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Crc32,
+    Md5,
+    Sha256,
+}
+
+pub enum Digest {
+    Crc32(flate2::Crc),
+    Md5(Blocks<Md5>),
+    Sha256(Blocks<Sha256>),
+}
+
+impl Digest {
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Crc32 => Digest::Crc32(flate2::Crc::new()),
+            Algorithm::Md5 => Digest::Md5(Blocks::new(Md5 {
+                state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            })),
+            Algorithm::Sha256 => Digest::Sha256(Blocks::new(Sha256 {
+                state: [
+                    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+                    0x1f83d9ab, 0x5be0cd19,
+                ],
+            })),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Digest::Crc32(crc) => crc.update(bytes),
+            Digest::Md5(md5) => md5.update(bytes),
+            Digest::Sha256(sha256) => sha256.update(bytes),
+        }
+    }
+
+    /// The checksum in hex, CRC-32 as a big-endian number like `crc32` prints it.
+    pub fn finish(self) -> String {
+        let bytes = match self {
+            Digest::Crc32(crc) => crc.sum().to_be_bytes().to_vec(),
+            Digest::Md5(md5) => md5.finish(),
+            Digest::Sha256(sha256) => sha256.finish(),
+        };
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Compression function of a hash over blocks of 64 bytes.
+pub trait Compress {
+    /// Whether the length at the end of the padding and the words are big-endian.
+    const BIG_ENDIAN: bool;
+    fn compress(&mut self, block: &[u8; 64]);
+    fn words(&self) -> &[u32];
+}
+
+/// Splits the input of a hash into blocks and pads the last one.
+pub struct Blocks<C> {
+    hash: C,
+    block: [u8; 64],
+    filled: usize,
+    len: u64,
+}
+
+impl<C: Compress> Blocks<C> {
+    fn new(hash: C) -> Self {
+        Blocks {
+            hash,
+            block: [0; 64],
+            filled: 0,
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.len = self.len.wrapping_add(bytes.len() as u64);
+        while !bytes.is_empty() {
+            let n = std::cmp::min(64 - self.filled, bytes.len());
+            self.block[self.filled..self.filled + n].copy_from_slice(&bytes[..n]);
+            self.filled += n;
+            bytes = &bytes[n..];
+            if self.filled == 64 {
+                self.hash.compress(&self.block);
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let bits = self.len.wrapping_mul(8);
+        // A 1 bit, zeros up to 56 bytes into a block and the length in bits.
+        let zeros = (119 - self.len % 64) % 64;
+        let mut padding = vec![0x80];
+        padding.extend(std::iter::repeat_n(0, zeros as usize));
+        match C::BIG_ENDIAN {
+            true => padding.extend(bits.to_be_bytes()),
+            false => padding.extend(bits.to_le_bytes()),
+        }
+        let len = self.len;
+        self.update(&padding);
+        self.len = len;
+        self.hash
+            .words()
+            .iter()
+            .flat_map(|word| match C::BIG_ENDIAN {
+                true => word.to_be_bytes(),
+                false => word.to_le_bytes(),
+            })
+            .collect()
+    }
+}
+
+pub struct Md5 {
+    state: [u32; 4],
+}
+
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const MD5_CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+impl Compress for Md5 {
+    const BIG_ENDIAN: bool = false;
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(MD5_CONSTANTS[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_SHIFTS[i]));
+        }
+        for (state, word) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(word);
+        }
+    }
+
+    fn words(&self) -> &[u32] {
+        &self.state
+    }
+}
+
+pub struct Sha256 {
+    state: [u32; 8],
+}
+
+const SHA256_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+impl Compress for Sha256 {
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_CONSTANTS[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, word) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(word);
+        }
+    }
+
+    fn words(&self) -> &[u32] {
+        &self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(algorithm: Algorithm, bytes: &[u8]) -> String {
+        let mut digest = Digest::new(algorithm);
+        digest.update(bytes);
+        digest.finish()
+    }
+
+    /// Inputs around the ends of the padding, where the length still fits
+    /// into the last block (55 bytes), just doesn't (56) and a whole block
+    /// of data is followed by one of padding (64), and a multi-block input.
+    fn inputs() -> Vec<Vec<u8>> {
+        vec![
+            Vec::new(),
+            b"abc".to_vec(),
+            vec![b'a'; 55],
+            vec![b'a'; 56],
+            vec![b'a'; 64],
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+            (0..1000).map(|i| (i % 251) as u8).collect(),
+        ]
+    }
+
+    #[test]
+    fn md5_known_answers() {
+        let expected = [
+            "d41d8cd98f00b204e9800998ecf8427e",
+            "900150983cd24fb0d6963f7d28e17f72",
+            "ef1772b6dff9a122358552954ad0df65",
+            "3b0c8ac703f828b04c6c197006d17218",
+            "014842d480b571495a4a0363793f7367",
+            "8215ef0796a20bcaaae116d3876c664a",
+            "a24f1e3ef66950e1327f210e3997ba2c",
+        ];
+        for (input, expected) in inputs().iter().zip(expected) {
+            assert_eq!(
+                digest(Algorithm::Md5, input),
+                expected,
+                "{} bytes",
+                input.len()
+            );
+        }
+    }
+
+    #[test]
+    fn sha256_known_answers() {
+        let expected = [
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+            "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+            "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            "4e4c294b331f7a2099a379bec34b9f9fc03dc46ab465d998f4d683da53487e6d",
+        ];
+        for (input, expected) in inputs().iter().zip(expected) {
+            assert_eq!(
+                digest(Algorithm::Sha256, input),
+                expected,
+                "{} bytes",
+                input.len()
+            );
+        }
+    }
+
+    #[test]
+    fn crc32_known_answers() {
+        assert_eq!(digest(Algorithm::Crc32, b""), "00000000");
+        assert_eq!(digest(Algorithm::Crc32, b"123456789"), "cbf43926");
+    }
+
+    #[test]
+    fn updates_of_any_size() {
+        let input: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        for algorithm in [Algorithm::Crc32, Algorithm::Md5, Algorithm::Sha256] {
+            let whole = digest(algorithm, &input);
+            for piece in [1, 7, 63, 64, 65, 999] {
+                let mut digest = Digest::new(algorithm);
+                for chunk in input.chunks(piece) {
+                    digest.update(chunk);
+                }
+                assert_eq!(
+                    digest.finish(),
+                    whole,
+                    "{:?} in pieces of {}",
+                    algorithm,
+                    piece
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Guess of the byte order of values from how plausible they look.
+//!
+//! Real data is rarely spread over the whole range of its type, integers
+//! are mostly small and floats lie within a few orders of magnitude of 1.
+//! Read with the wrong byte order the low bytes become the high ones, which
+//! turns small integers into large ones and floats into tiny, huge,
+//! subnormal or NaN values.
+
+use clap::ValueEnum;
+
+use parsebin::decode;
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+use parsebin::SizeOf;
+use parsebin::Value;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Endianness {
+    LittleEndian,
+    BigEndian,
+    /// Byte order of the machine running parsebin
+    Native,
+    /// Guess from the first values whether they are little- or big-endian
+    Auto,
+}
+
+impl Endianness {
+    /// The byte order, `None` for `Auto`.
+    pub fn byte_order(self) -> Option<ByteOrder> {
+        match self {
+            Endianness::LittleEndian => Some(ByteOrder::LittleEndian),
+            Endianness::BigEndian => Some(ByteOrder::BigEndian),
+            Endianness::Native => Some(ByteOrder::Native),
+            Endianness::Auto => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guess {
+    pub byte_order: ByteOrder,
+    /// Share of the implausibility of both byte orders that falls on the
+    /// other one, from 0.5 when they look the same to 1.
+    pub confidence: f64,
+    /// Number of values whose byte order matters.
+    pub values: u64,
+}
+
+/// How implausible `value` is, 0 for 0 and growing with the bits of the
+/// magnitude of integers and the binary orders of magnitude of floats away
+/// from 1. NaN, infinity and subnormal floats are rare in real data and
+/// cost as much as the most extreme values.
+fn cost(value: &Value) -> f64 {
+    let float = |v: f64, subnormal: bool, limit: f64| match v {
+        _ if !v.is_finite() || subnormal => limit,
+        0.0 => 0.0,
+        v => v.abs().log2().abs(),
+    };
+    match value {
+        Value::F32(v) => float(*v as f64, v.is_subnormal(), 150.0),
+        Value::F64(v) => float(*v, v.is_subnormal(), 1075.0),
+        value => {
+            let magnitude = value.as_i128().map_or(0, |v| v.unsigned_abs());
+            (128 - magnitude.leading_zeros()) as f64
+        }
+    }
+}
+
+/// Guesses the byte order of the records in `bytes`, every one
+/// `skip_before` bytes, then `fields` and padding up to `span` bytes.
+///
+/// A tie, e.g. when all values are 0 or the same read either way, is
+/// little-endian, as are types of single bytes.
+pub fn guess(bytes: &[u8], fields: &[ParseType], skip_before: usize, span: usize) -> Guess {
+    let size: usize = fields.iter().map(|field| field.size_of() as usize).sum();
+    let (mut little, mut big) = (0.0, 0.0);
+    let mut values = 0;
+    let mut records = bytes.chunks(span);
+    while let Some(record) = records.next().filter(|r| r.len() >= skip_before + size) {
+        let mut start = skip_before;
+        for field in fields {
+            let size = field.size_of() as usize;
+            let bytes = &record[start..];
+            if let (true, Some((little_value, _)), Some((big_value, _))) = (
+                size > 1,
+                decode(field, bytes, &ByteOrder::LittleEndian),
+                decode(field, bytes, &ByteOrder::BigEndian),
+            ) {
+                little += cost(&little_value);
+                big += cost(&big_value);
+                values += 1;
+            }
+            start += size;
+        }
+    }
+    let (byte_order, lower, higher) = match big < little {
+        true => (ByteOrder::BigEndian, big, little),
+        false => (ByteOrder::LittleEndian, little, big),
+    };
+    let confidence = match lower + higher {
+        0.0 => 0.5,
+        total => higher / total,
+    };
+    Guess {
+        byte_order,
+        confidence,
+        values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(values: &[f64], parse_type: &ParseType, byte_order: ByteOrder) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| {
+                let value = match parse_type {
+                    ParseType::U16 => Value::U16(*v as u16),
+                    ParseType::I32 => Value::I32(*v as i32),
+                    ParseType::F32 => Value::F32(*v as f32),
+                    _ => Value::F64(*v),
+                };
+                value.to_bytes(&byte_order)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn all_zeros_are_a_tie_and_little_endian() {
+        for parse_type in [
+            ParseType::U16,
+            ParseType::I32,
+            ParseType::F32,
+            ParseType::F64,
+        ] {
+            let bytes = vec![0; 64];
+            let result = guess(
+                &bytes,
+                std::slice::from_ref(&parse_type),
+                0,
+                parse_type.size_of() as usize,
+            );
+            assert_eq!(result.byte_order, ByteOrder::LittleEndian);
+            assert_eq!(result.confidence, 0.5);
+            assert!(result.values > 0);
+        }
+    }
+
+    #[test]
+    fn symmetric_values_are_a_tie_and_little_endian() {
+        // Palindromes of bytes read the same in both byte orders.
+        let bytes = [0x12, 0x34, 0x34, 0x12].repeat(16);
+        let result = guess(&bytes, &[ParseType::I32], 0, 4);
+        assert_eq!(result.byte_order, ByteOrder::LittleEndian);
+        assert_eq!(result.confidence, 0.5);
+        let bytes = [0x7a, 0x7a].repeat(16);
+        let result = guess(&bytes, &[ParseType::U16], 0, 2);
+        assert_eq!(result.byte_order, ByteOrder::LittleEndian);
+        assert_eq!(result.confidence, 0.5);
+    }
+
+    #[test]
+    fn single_bytes_are_little_endian() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let result = guess(&bytes, &[ParseType::U8], 0, 1);
+        assert_eq!(result.byte_order, ByteOrder::LittleEndian);
+        assert_eq!(result.values, 0);
+    }
+
+    #[test]
+    fn small_values_in_both_byte_orders() {
+        let values: Vec<f64> = (1..100).map(|v| v as f64).collect();
+        for parse_type in [
+            ParseType::U16,
+            ParseType::I32,
+            ParseType::F32,
+            ParseType::F64,
+        ] {
+            for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+                let bytes = encoded(&values, &parse_type, byte_order.clone());
+                let result = guess(
+                    &bytes,
+                    std::slice::from_ref(&parse_type),
+                    0,
+                    parse_type.size_of() as usize,
+                );
+                assert_eq!(result.byte_order, byte_order, "{}", parse_type);
+                assert!(result.confidence > 0.5, "{}", parse_type);
+                assert_eq!(result.values, 99);
+            }
+        }
+    }
+
+    #[test]
+    fn records_with_skipped_bytes_and_padding() {
+        // A byte skipped before every i32 and 3 of padding after it.
+        let mut bytes = Vec::new();
+        for v in 1..50i32 {
+            bytes.push(0xff);
+            bytes.extend(v.to_be_bytes());
+            bytes.extend([0xff; 3]);
+        }
+        let result = guess(&bytes, &[ParseType::I32], 1, 8);
+        assert_eq!(result.byte_order, ByteOrder::BigEndian);
+        assert_eq!(result.values, 49);
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::Write;
+
+/// Number of occurrences of every byte value.
+pub struct ByteCounts {
+    counts: [u64; 256],
+}
+
+impl ByteCounts {
+    pub fn new() -> Self {
+        ByteCounts { counts: [0; 256] }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.counts[*byte as usize] += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Shannon entropy of the byte values in bits per byte, from 0 for a
+    /// single value to 8 for evenly distributed ones, e.g. random or
+    /// compressed data.
+    pub fn entropy(&self) -> f64 {
+        let total = match self.total() {
+            0 => return 0.0,
+            total => total as f64,
+        };
+        self.counts
+            .iter()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / total;
+                p * (1.0 / p).log2()
+            })
+            .sum()
+    }
+
+    /// Prints a table of the byte values that occur with their count and
+    /// share, the most frequent first or in the order of the values with
+    /// `by_value`, limited to `top` lines, and a line with the totals.
+    pub fn print(
+        &self,
+        by_value: bool,
+        top: Option<usize>,
+        out: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let total = self.total();
+        let mut bytes: Vec<usize> = (0..256).filter(|byte| self.counts[*byte] > 0).collect();
+        if !by_value {
+            // Stable, so equal counts stay in the order of the values.
+            bytes.sort_by_key(|byte| std::cmp::Reverse(self.counts[*byte]));
+        }
+        let distinct = bytes.len();
+        bytes.truncate(top.unwrap_or(256));
+        let width = std::cmp::max(total.to_string().len(), "count".len());
+        writeln!(out, "byte {:>width$} percent", "count", width = width)?;
+        for byte in bytes {
+            let count = self.counts[byte];
+            writeln!(
+                out,
+                "{:02x}   {:>width$} {:>6.2}%",
+                byte,
+                count,
+                100.0 * count as f64 / total as f64,
+                width = width
+            )?;
+        }
+        writeln!(out, "total {} bytes, {} distinct values", total, distinct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entropy_of_known_distributions() {
+        let mut constant = ByteCounts::new();
+        constant.push(&[0x42; 1000]);
+        assert_eq!(constant.entropy(), 0.0);
+        let mut uniform = ByteCounts::new();
+        for _ in 0..4 {
+            uniform.push(&(0..=255).collect::<Vec<u8>>());
+        }
+        assert_eq!(uniform.entropy(), 8.0);
+        // Two values as often as each other are one bit.
+        let mut two = ByteCounts::new();
+        two.push(&[0, 1, 1, 0, 0, 1]);
+        assert_eq!(two.entropy(), 1.0);
+        // Four values of 1/2, 1/4 and 1/8 twice are 1.75 bits.
+        let mut skewed = ByteCounts::new();
+        skewed.push(&[1, 1, 1, 1, 2, 2, 3, 4]);
+        assert_eq!(skewed.entropy(), 1.75);
+        assert_eq!(ByteCounts::new().entropy(), 0.0);
+    }
+
+    #[test]
+    fn table_of_the_most_frequent_bytes() {
+        let mut counts = ByteCounts::new();
+        counts.push(&[7, 0xff, 7, 3, 0xff, 7]);
+        let mut out = Vec::new();
+        counts.print(false, Some(2), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "byte count percent\n07       3  50.00%\nff       2  33.33%\ntotal 6 bytes, 3 distinct values\n"
+        );
+        let mut out = Vec::new();
+        counts.print(true, None, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let bytes: Vec<&str> = text.lines().skip(1).map(|line| &line[..2]).collect();
+        assert_eq!(bytes, ["03", "07", "ff", "to"]);
+    }
+}
@@ -0,0 +1,280 @@
+//! Text of the values for the printed rows, and the width of their columns for --align.
+
+use std::fmt::Display;
+use std::fmt::LowerExp;
+
+use clap::ValueEnum;
+use parsebin::ParseType;
+use parsebin::Value;
+
+use crate::ieee;
+
+/// Unit of the integers printed with --as-time.
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum TimeUnit {
+    #[default]
+    S,
+    Ms,
+    Us,
+    Ns,
+}
+
+impl TimeUnit {
+    fn nanos(self) -> i128 {
+        match self {
+            TimeUnit::S => 1_000_000_000,
+            TimeUnit::Ms => 1_000_000,
+            TimeUnit::Us => 1_000,
+            TimeUnit::Ns => 1,
+        }
+    }
+}
+
+/// How values are printed, from --precision, --sci, --trim-zeros, --bits,
+/// --float-bits, --float-bits-only and --as-time.
+#[derive(Debug, Clone, Default)]
+pub struct TextFormat {
+    pub precision: Option<usize>,
+    pub sci: bool,
+    pub trim_zeros: bool,
+    pub bits: bool,
+    pub float_bits: bool,
+    pub float_bits_only: bool,
+    /// Integers are timestamps counting this unit.
+    pub as_time: Option<TimeUnit>,
+    /// No warning about timestamps out of range.
+    pub quiet: bool,
+}
+
+impl TextFormat {
+    /// Whether values are printed as they display, so they need no formatting.
+    pub fn is_plain(&self) -> bool {
+        self.precision.is_none()
+            && !self.sci
+            && self.as_time.is_none()
+            && !self.bits
+            && !self.float_bits
+            && !self.float_bits_only
+    }
+
+    pub fn float<T: Display + LowerExp>(&self, v: T) -> String {
+        let formatted = match (self.precision, self.sci) {
+            (None, false) => v.to_string(),
+            (Some(precision), false) => format!("{:.*}", precision, v),
+            (None, true) => format!("{:e}", v),
+            (Some(precision), true) => format!("{:.*e}", precision, v),
+        };
+        if !self.trim_zeros {
+            return formatted;
+        }
+        let (mantissa, exponent) =
+            formatted.split_at(formatted.find('e').unwrap_or(formatted.len()));
+        match mantissa.contains('.') {
+            true => format!(
+                "{}{}",
+                mantissa.trim_end_matches('0').trim_end_matches('.'),
+                exponent
+            ),
+            false => formatted.clone(),
+        }
+    }
+
+    /// Formats an integer counting `unit` since the Unix epoch as a UTC
+    /// timestamp, or as the number if it's outside of the supported dates.
+    fn time(&self, v: i128, unit: TimeUnit) -> String {
+        static OUT_OF_RANGE: std::sync::Once = std::sync::Once::new();
+        let nanos = v.checked_mul(unit.nanos());
+        let time = nanos.and_then(|nanos| {
+            let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+            chrono::DateTime::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32)
+        });
+        match time {
+            Some(time) => time.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            None => {
+                OUT_OF_RANGE.call_once(|| {
+                    if !self.quiet {
+                        eprintln!(
+                            "Warning: {} is out of the range of --as-time, such values are printed as numbers",
+                            v
+                        );
+                    }
+                });
+                v.to_string()
+            }
+        }
+    }
+
+    /// Formats a value for text output, floats according to --precision, --sci,
+    /// --bits and --float-bits and integers as timestamps with --as-time.
+    pub fn value(&self, value: Value) -> String {
+        match value {
+            Value::F32(v) if self.bits => ieee::describe_f32(v),
+            Value::F64(v) if self.bits => ieee::describe_f64(v),
+            Value::F32(v) if self.float_bits_only => format!("0x{:08x}", v.to_bits()),
+            Value::F64(v) if self.float_bits_only => format!("0x{:016x}", v.to_bits()),
+            Value::F32(v) if self.float_bits => {
+                format!("{} (0x{:08x})", self.float(v), v.to_bits())
+            }
+            Value::F64(v) if self.float_bits => {
+                format!("{} (0x{:016x})", self.float(v), v.to_bits())
+            }
+            Value::F32(v) => self.float(v),
+            Value::F64(v) => self.float(v),
+            v => match self.as_time {
+                Some(unit) => self.time(v.as_i128().unwrap(), unit),
+                None => v.to_string(),
+            },
+        }
+    }
+
+    /// Width of the widest text of a value of `parse_type`, including the sign.
+    ///
+    /// Floats in scientific notation have a bounded width. Other floats are
+    /// usually much shorter than their widest text, e.g. 39 digits for a large
+    /// f32, so they get a typical width instead and wider values stick out.
+    pub fn field_width(&self, parse_type: &ParseType) -> usize {
+        // Sign, leading digit and the point followed by the fraction digits.
+        let mantissa = |digits: usize| 2 + std::cmp::min(digits, 1) + digits;
+        match (parse_type, self.sci, self.precision) {
+            (ParseType::U8, _, _) => 3,
+            (ParseType::I8, _, _) => 4,
+            (ParseType::U16, _, _) => 5,
+            (ParseType::I16, _, _) => 6,
+            (ParseType::U24, _, _) => 8,
+            (ParseType::I24, _, _) => 8,
+            (ParseType::U32, _, _) => 10,
+            (ParseType::I32, _, _) => 11,
+            (ParseType::U64 | ParseType::Uleb128, _, _) => 20,
+            (ParseType::I64 | ParseType::Sleb128, _, _) => 20,
+            // Mantissa and an exponent like e-38 or e-308.
+            (ParseType::F32, true, precision) => mantissa(precision.unwrap_or(8)) + 4,
+            (ParseType::F64, true, precision) => mantissa(precision.unwrap_or(16)) + 5,
+            (ParseType::F32, false, Some(precision)) => mantissa(precision) + 5,
+            (ParseType::F64, false, Some(precision)) => mantissa(precision) + 9,
+            (ParseType::F32, false, None) => 12,
+            (ParseType::F64, false, None) => 20,
+        }
+    }
+}
+
+/// `text` right-aligned to `width`, in brackets if it's `marked` like a
+/// --highlight-special value without colors.
+pub fn align(text: &str, width: usize, marked: bool) -> String {
+    match marked {
+        true => format!("{:>width$}", format!("[{}]", text), width = width),
+        false => format!("{:>width$}", text, width = width),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `value` as --align prints it in a column of its type.
+    fn aligned(format: &TextFormat, parse_type: ParseType, value: Value) -> String {
+        align(&format.value(value), format.field_width(&parse_type), false)
+    }
+
+    #[test]
+    fn integers_are_as_wide_as_their_widest_value() {
+        let format = TextFormat::default();
+        assert_eq!(aligned(&format, ParseType::U8, Value::U8(7)), "  7");
+        assert_eq!(aligned(&format, ParseType::I8, Value::I8(-128)), "-128");
+        assert_eq!(aligned(&format, ParseType::I16, Value::I16(-5)), "    -5");
+        assert_eq!(
+            aligned(&format, ParseType::U32, Value::U32(42)),
+            "        42"
+        );
+        assert_eq!(
+            aligned(&format, ParseType::I64, Value::I64(i64::MIN)),
+            "-9223372036854775808"
+        );
+    }
+
+    #[test]
+    fn floats_are_as_wide_as_their_precision() {
+        let format = TextFormat {
+            precision: Some(2),
+            ..TextFormat::default()
+        };
+        assert_eq!(
+            aligned(&format, ParseType::F32, Value::F32(-1.5)),
+            "     -1.50"
+        );
+        assert_eq!(
+            aligned(&format, ParseType::F32, Value::F32(f32::NAN)),
+            "       NaN"
+        );
+        assert_eq!(
+            aligned(&format, ParseType::F64, Value::F64(f64::NEG_INFINITY)),
+            "          -inf"
+        );
+        let format = TextFormat {
+            sci: true,
+            ..TextFormat::default()
+        };
+        assert_eq!(
+            aligned(&format, ParseType::F32, Value::F32(-1.25e-38)),
+            "      -1.25e-38"
+        );
+        // Without --precision floats get a typical width.
+        let format = TextFormat::default();
+        assert_eq!(
+            aligned(&format, ParseType::F32, Value::F32(-0.5)),
+            "        -0.5"
+        );
+        assert_eq!(
+            aligned(&format, ParseType::F64, Value::F64(f64::NAN)),
+            "                 NaN"
+        );
+    }
+
+    #[test]
+    fn marked_values_are_aligned_with_their_brackets() {
+        assert_eq!(align("NaN", 6, true), " [NaN]");
+        assert_eq!(align("-inf", 4, true), "[-inf]");
+        assert_eq!(align("1", 4, false), "   1");
+    }
+
+    #[test]
+    fn trailing_zeros_and_bits_of_floats() {
+        let format = TextFormat {
+            precision: Some(3),
+            trim_zeros: true,
+            ..TextFormat::default()
+        };
+        assert_eq!(format.value(Value::F32(1.5)), "1.5");
+        assert_eq!(format.value(Value::F64(-2.0)), "-2");
+        let format = TextFormat {
+            sci: true,
+            precision: Some(3),
+            trim_zeros: true,
+            ..TextFormat::default()
+        };
+        assert_eq!(format.value(Value::F64(-1500.0)), "-1.5e3");
+        let format = TextFormat {
+            float_bits: true,
+            ..TextFormat::default()
+        };
+        assert_eq!(format.value(Value::F32(-0.0)), "-0 (0x80000000)");
+        let format = TextFormat {
+            float_bits_only: true,
+            ..TextFormat::default()
+        };
+        assert_eq!(format.value(Value::F32(1.5)), "0x3fc00000");
+        assert_eq!(format.value(Value::F64(1.5)), "0x3ff8000000000000");
+    }
+
+    #[test]
+    fn integers_as_timestamps() {
+        let format = TextFormat {
+            as_time: Some(TimeUnit::Ms),
+            quiet: true,
+            ..TextFormat::default()
+        };
+        assert_eq!(format.value(Value::I64(-1)), "1969-12-31T23:59:59.999Z");
+        assert_eq!(format.value(Value::F32(1.5)), "1.5");
+        // Out of the supported dates they're numbers.
+        assert_eq!(format.value(Value::U64(u64::MAX)), u64::MAX.to_string());
+    }
+}
@@ -0,0 +1,296 @@
+//! Ranking of the types and byte orders data could have by how plausible
+//! its values look, to get a first idea of an undocumented file.
+//!
+//! Floats are plausible if they are finite, normal and of a magnitude real
+//! data has, and more so if their magnitudes are close together. Integers
+//! are plausible if their most significant bytes vary little and their
+//! values are small for their width, as integers rarely use their whole
+//! range.
+
+use parsebin::decode;
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+use parsebin::SizeOf;
+use parsebin::Value;
+
+use crate::entropy::ByteCounts;
+
+const TYPES: [ParseType; 12] = [
+    ParseType::U8,
+    ParseType::I8,
+    ParseType::U16,
+    ParseType::I16,
+    ParseType::U24,
+    ParseType::I24,
+    ParseType::U32,
+    ParseType::I32,
+    ParseType::U64,
+    ParseType::I64,
+    ParseType::F32,
+    ParseType::F64,
+];
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub parse_type: ParseType,
+    pub byte_order: ByteOrder,
+    /// From 0 for implausible to 1 for plausible values.
+    pub score: f64,
+    /// The first values.
+    pub preview: Vec<Value>,
+}
+
+/// Whether a float looks like a measurement rather than reinterpreted bits.
+fn plausible_float(value: &Value) -> bool {
+    let (v, normal) = match value {
+        Value::F32(v) => (*v as f64, v.is_normal()),
+        Value::F64(v) => (*v, v.is_normal()),
+        _ => return false,
+    };
+    v == 0.0 || (normal && (1e-6..=1e9).contains(&v.abs()))
+}
+
+/// Score of the floats `values`, the share of plausible ones, lowered if
+/// their orders of magnitude spread widely.
+fn float_score(values: &[Value]) -> f64 {
+    let plausible: Vec<f64> = values
+        .iter()
+        .filter(|v| plausible_float(v))
+        .map(|v| v.as_f64().abs())
+        .collect();
+    let share = plausible.len() as f64 / values.len() as f64;
+    let magnitudes: Vec<f64> = plausible
+        .iter()
+        .filter(|v| **v > 0.0)
+        .map(|v| v.log10())
+        .collect();
+    if magnitudes.is_empty() {
+        return share;
+    }
+    let n = magnitudes.len() as f64;
+    let mean = magnitudes.iter().sum::<f64>() / n;
+    let variance = magnitudes.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    share / (1.0 + variance / 16.0)
+}
+
+/// Score of the integers `values` of `parse_type`, whose most significant
+/// bytes are `high_bytes`.
+fn integer_score(values: &[Value], high_bytes: &ByteCounts, parse_type: &ParseType) -> f64 {
+    let width = parse_type.size_of() as f64 * 8.0;
+    let bits: f64 = values
+        .iter()
+        .map(|value| {
+            let magnitude = value.as_i128().map_or(0, |v| v.unsigned_abs());
+            (128 - magnitude.leading_zeros()) as f64
+        })
+        .sum();
+    let smallness = 1.0 - bits / values.len() as f64 / width;
+    (1.0 - high_bytes.entropy() / 8.0) / 2.0 + smallness / 2.0
+}
+
+fn score(bytes: &[u8], parse_type: &ParseType, byte_order: &ByteOrder) -> Candidate {
+    let size = parse_type.size_of() as usize;
+    let mut high_bytes = ByteCounts::new();
+    let values: Vec<Value> = bytes
+        .chunks_exact(size)
+        .filter_map(|chunk| {
+            let high = match byte_order {
+                ByteOrder::BigEndian => chunk[0],
+                _ => chunk[size - 1],
+            };
+            high_bytes.push(&[high]);
+            decode(parse_type, chunk, byte_order).map(|(value, _)| value)
+        })
+        .collect();
+    let score = match values.len() {
+        0 => 0.0,
+        _ if parse_type.is_float() => float_score(&values),
+        _ => integer_score(&values, &high_bytes, parse_type),
+    };
+    Candidate {
+        parse_type: parse_type.clone(),
+        byte_order: byte_order.clone(),
+        score,
+        preview: values.into_iter().take(4).collect(),
+    }
+}
+
+/// Every type with both byte orders, single bytes only once, ranked by the
+/// score of the values in `bytes`, the most plausible first. Equal scores
+/// keep the order of the types, narrower and unsigned ones first.
+pub fn rank(bytes: &[u8]) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = TYPES
+        .iter()
+        .flat_map(|parse_type| {
+            let orders = match parse_type.size_of() {
+                1 => vec![ByteOrder::LittleEndian],
+                _ => vec![ByteOrder::LittleEndian, ByteOrder::BigEndian],
+            };
+            orders
+                .into_iter()
+                .map(move |byte_order| score(bytes, parse_type, &byte_order))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values like real data of `parse_type` has: slow waves around a level,
+    /// counters, timestamps and small counts.
+    fn fixture(parse_type: &ParseType, byte_order: &ByteOrder) -> Vec<u8> {
+        (0..512)
+            .flat_map(|i| {
+                let x = i as f64 / 512.0 * std::f64::consts::TAU;
+                let value = match parse_type {
+                    ParseType::U8 => Value::U8((i % 24) as u8),
+                    ParseType::I8 => Value::I8((20.0 * x.sin()) as i8),
+                    ParseType::U16 => Value::U16((2000.0 + 1000.0 * x.sin()) as u16),
+                    ParseType::I16 => Value::I16((3000.0 * x.sin()) as i16),
+                    ParseType::U24 => Value::U24((70000.0 + 5000.0 * x.sin()) as u32),
+                    ParseType::I24 => Value::I24((50000.0 * x.sin()) as i32),
+                    ParseType::U32 => Value::U32(1_000_000 + i * 37),
+                    ParseType::I32 => Value::I32((100000.0 * x.sin()) as i32),
+                    ParseType::U64 => Value::U64(1_700_000_000_000 + i as u64 * 1000),
+                    ParseType::I64 => Value::I64((1e7 * x.sin()) as i64),
+                    ParseType::F32 => Value::F32((20.0 + 5.0 * x.sin()) as f32),
+                    _ => Value::F64(1013.25 + 10.0 * x.cos()),
+                };
+                value.to_bytes(byte_order)
+            })
+            .collect()
+    }
+
+    /// Whether the values of `parse_type` in `byte_order` rank first, as a
+    /// type of the same width and byte order they read the same as may too.
+    fn ranks_first(bytes: &[u8], parse_type: &ParseType, byte_order: &ByteOrder) -> bool {
+        let ranked = rank(bytes);
+        let real = ranked
+            .iter()
+            .find(|c| {
+                &c.parse_type == parse_type
+                    && (parse_type.size_of() == 1 || &c.byte_order == byte_order)
+            })
+            .unwrap();
+        real.score == ranked[0].score
+    }
+
+    #[test]
+    fn fixtures_of_every_type_rank_first() {
+        for parse_type in &TYPES {
+            for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+                let bytes = fixture(parse_type, &byte_order);
+                assert!(
+                    ranks_first(&bytes, parse_type, &byte_order),
+                    "{} {}",
+                    parse_type,
+                    byte_order
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn signed_fixtures_rank_above_unsigned() {
+        for (signed, unsigned) in [
+            (ParseType::I16, ParseType::U16),
+            (ParseType::I24, ParseType::U24),
+            (ParseType::I32, ParseType::U32),
+        ] {
+            let ranked = rank(&fixture(&signed, &ByteOrder::LittleEndian));
+            let position = |parse_type: &ParseType| {
+                ranked
+                    .iter()
+                    .position(|c| {
+                        &c.parse_type == parse_type && c.byte_order == ByteOrder::LittleEndian
+                    })
+                    .unwrap()
+            };
+            assert!(position(&signed) < position(&unsigned), "{}", signed);
+        }
+    }
+
+    #[test]
+    fn float_fixtures_score_fully() {
+        for parse_type in [ParseType::F32, ParseType::F64] {
+            let candidate = score(
+                &fixture(&parse_type, &ByteOrder::BigEndian),
+                &parse_type,
+                &ByteOrder::BigEndian,
+            );
+            assert!(candidate.score > 0.99, "{}", parse_type);
+            let candidate = score(
+                &fixture(&parse_type, &ByteOrder::BigEndian),
+                &parse_type,
+                &ByteOrder::LittleEndian,
+            );
+            assert!(candidate.score < 0.5, "{}", parse_type);
+        }
+    }
+
+    #[test]
+    fn plausible_floats() {
+        assert!(plausible_float(&Value::F32(0.0)));
+        assert!(plausible_float(&Value::F32(-20.5)));
+        assert!(plausible_float(&Value::F64(1e9)));
+        assert!(!plausible_float(&Value::F64(1e10)));
+        assert!(!plausible_float(&Value::F64(1e-7)));
+        assert!(!plausible_float(&Value::F32(f32::NAN)));
+        assert!(!plausible_float(&Value::F64(f64::INFINITY)));
+        assert!(!plausible_float(&Value::F64(f64::MIN_POSITIVE / 2.0)));
+        assert!(!plausible_float(&Value::I32(1)));
+    }
+
+    #[test]
+    fn float_scores() {
+        let values = [
+            Value::F64(1.0),
+            Value::F64(2.0),
+            Value::F64(f64::NAN),
+            Value::F64(1e20),
+        ];
+        // Half the values are plausible, and 1 and 2 are close together.
+        let score = float_score(&values);
+        assert!(score < 0.5 && score > 0.49, "{}", score);
+        assert_eq!(float_score(&[Value::F32(0.0), Value::F32(0.0)]), 1.0);
+        let spread = float_score(&[Value::F64(1e-5), Value::F64(1e8)]);
+        assert!(spread < 0.5, "{}", spread);
+    }
+
+    #[test]
+    fn integer_scores() {
+        // Zeros of constant high bytes and no magnitude are as plausible as can be.
+        let mut high_bytes = ByteCounts::new();
+        high_bytes.push(&[0; 4]);
+        let zeros = [Value::U32(0); 4];
+        assert_eq!(integer_score(&zeros, &high_bytes, &ParseType::U32), 1.0);
+        let mut high_bytes = ByteCounts::new();
+        high_bytes.push(&[0xff; 2]);
+        let full = [Value::U16(u16::MAX); 2];
+        assert_eq!(integer_score(&full, &high_bytes, &ParseType::U16), 0.5);
+        let mut high_bytes = ByteCounts::new();
+        high_bytes.push(&(0..=255).collect::<Vec<u8>>());
+        let random: Vec<Value> = (0..256).map(|v| Value::U16(v << 8 | 0xff)).collect();
+        assert!(integer_score(&random, &high_bytes, &ParseType::U16) < 0.1);
+    }
+
+    #[test]
+    fn too_short_for_a_value() {
+        let candidate = score(&[1, 2, 3], &ParseType::U32, &ByteOrder::LittleEndian);
+        assert_eq!(candidate.score, 0.0);
+        assert!(candidate.preview.is_empty());
+        // Single bytes are ranked once, with either byte order.
+        assert_eq!(rank(&[1, 2, 3]).len(), 22);
+    }
+
+    #[test]
+    fn preview_of_the_first_values() {
+        let bytes: Vec<u8> = (1..=6u16).flat_map(|v| v.to_be_bytes()).collect();
+        let candidate = score(&bytes, &ParseType::U16, &ByteOrder::BigEndian);
+        let preview: Vec<String> = candidate.preview.iter().map(|v| v.to_string()).collect();
+        assert_eq!(preview, ["1", "2", "3", "4"]);
+    }
+}
@@ -0,0 +1,602 @@
+use std::cmp::Ordering;
+use std::io::Write;
+
+use parsebin::Value;
+
+/// Narrowest bar of the fullest bin, however little room the line leaves.
+const MIN_BAR_WIDTH: usize = 10;
+
+/// Values kept to find the bounds of the bins, later ones are binned right away.
+const SAMPLE_SIZE: usize = 1 << 20;
+
+/// Equal-width bins between two bounds that are part of the last bin.
+#[derive(Debug, Clone, PartialEq)]
+enum Bins {
+    /// Bins of whole numbers, there may be fewer than requested if the range
+    /// is small.
+    Integer {
+        min: i128,
+        max: i128,
+        width: i128,
+        len: usize,
+    },
+    /// Bins of finite floats, `min` < `max` unless there is a single bin.
+    /// Halves are kept so the range of the widest floats doesn't overflow.
+    Float {
+        min: f64,
+        max: f64,
+        half_width: f64,
+        len: usize,
+    },
+}
+
+impl Bins {
+    /// `bins` bins of integers from `min` to `max`, which must not be below `min`.
+    fn integer(min: i128, max: i128, bins: usize) -> Self {
+        let span = max - min + 1;
+        let width = (span + bins as i128 - 1) / bins as i128;
+        Bins::Integer {
+            min,
+            max,
+            width,
+            len: ((span + width - 1) / width) as usize,
+        }
+    }
+
+    /// `bins` bins of floats from `min` to `max`, which must not be below `min`.
+    fn float(min: f64, max: f64, bins: usize) -> Self {
+        let len = if max == min { 1 } else { bins };
+        Bins::Float {
+            min,
+            max,
+            half_width: (max / 2.0 - min / 2.0) / len as f64,
+            len,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Bins::Integer { len, .. } | Bins::Float { len, .. } => *len,
+        }
+    }
+
+    /// The bin of `value`, or whether it is below or above all bins.
+    fn place_integer(&self, value: i128) -> Result<usize, Ordering> {
+        match self {
+            Bins::Integer { min, .. } if value < *min => Err(Ordering::Less),
+            Bins::Integer { max, .. } if value > *max => Err(Ordering::Greater),
+            Bins::Integer { .. } => Ok(self.integer_bin(value)),
+            Bins::Float { .. } => self.place_float(value as f64),
+        }
+    }
+
+    /// The bin of `value`, or whether it is below or above all bins.
+    fn place_float(&self, value: f64) -> Result<usize, Ordering> {
+        match self {
+            Bins::Float { min, .. } if value < *min => Err(Ordering::Less),
+            Bins::Float { max, .. } if value > *max => Err(Ordering::Greater),
+            Bins::Float { .. } => Ok(self.float_bin(value)),
+            Bins::Integer { .. } => self.place_integer(value as i128),
+        }
+    }
+
+    /// The lowest and highest value of the bins as text.
+    fn bounds(&self, format: &impl Fn(f64) -> String) -> (String, String) {
+        match self {
+            Bins::Integer { min, max, .. } => (min.to_string(), max.to_string()),
+            Bins::Float { min, max, .. } => (format(*min), format(*max)),
+        }
+    }
+
+    fn integer_bin(&self, value: i128) -> usize {
+        match self {
+            Bins::Integer { min, width, .. } => ((value - min) / width) as usize,
+            Bins::Float { .. } => self.float_bin(value as f64),
+        }
+    }
+
+    fn float_bin(&self, value: f64) -> usize {
+        match self {
+            Bins::Float {
+                min,
+                half_width,
+                len,
+                ..
+            } if *half_width > 0.0 => {
+                let bin = ((value / 2.0 - min / 2.0) / half_width) as usize;
+                std::cmp::min(bin, len - 1)
+            }
+            Bins::Float { .. } => 0,
+            Bins::Integer { .. } => self.integer_bin(value as i128),
+        }
+    }
+
+    /// Range of `bin` as text, with the edges formatted by `format` if floats.
+    fn range(&self, bin: usize, format: &impl Fn(f64) -> String) -> String {
+        match self {
+            Bins::Integer {
+                min, max, width, ..
+            } => {
+                let start = min + bin as i128 * width;
+                let end = std::cmp::min(start + width - 1, *max);
+                format!("[{}, {}]", start, end)
+            }
+            Bins::Float {
+                min,
+                max,
+                half_width,
+                len,
+            } => {
+                let edge = |bin: usize| match bin {
+                    _ if bin == *len => *max,
+                    _ => (min / 2.0 + bin as f64 * half_width) * 2.0,
+                };
+                match bin + 1 == *len {
+                    true => format!("[{}, {}]", format(edge(bin)), format(*max)),
+                    false => format!("[{}, {})", format(edge(bin)), format(edge(bin + 1))),
+                }
+            }
+        }
+    }
+}
+
+/// Lowest and highest finite value seen, of the integers and floats apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bounds {
+    integers: Option<(i128, i128)>,
+    floats: Option<(f64, f64)>,
+}
+
+impl Bounds {
+    fn push_integer(&mut self, v: i128) {
+        self.integers = Some(match self.integers {
+            Some((min, max)) => (min.min(v), max.max(v)),
+            None => (v, v),
+        });
+    }
+
+    fn push_float(&mut self, v: f64) {
+        self.floats = Some(match self.floats {
+            Some((min, max)) => (min.min(v), max.max(v)),
+            None => (v, v),
+        });
+    }
+}
+
+/// Distribution of a stream of values over equal-width bins.
+///
+/// The bins span the observed minimum and maximum. Input that can be read
+/// twice gives them in a first pass, see [`Histogram::measure`]. Otherwise
+/// the values are kept, but at most [`SAMPLE_SIZE`] of them: the bins are
+/// then fixed to the bounds of those, and later values outside them are only
+/// counted. Fixed bounds can be given instead, the bins are set right away if
+/// both are. NaN and infinite values are counted apart. Integers are binned
+/// exactly, whatever their magnitude.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bins: usize,
+    integer: bool,
+    // Values kept until the bins are set, unless only measuring their bounds.
+    integers: Vec<i128>,
+    floats: Vec<f64>,
+    keep: bool,
+    observed: Bounds,
+    sample_size: usize,
+    // The bins with their counts once set.
+    binned: Option<(Bins, Vec<u64>)>,
+    // Values the bins were set from, if set from a sample.
+    sampled: Option<usize>,
+    nan_count: u64,
+    negative_infinite: u64,
+    positive_infinite: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+    underflow: u64,
+    overflow: u64,
+}
+
+impl Histogram {
+    /// Creates a histogram of `bins` bins, of whole numbers if `integer`, that
+    /// span `min` and `max` where given instead of the observed bounds.
+    pub fn new(bins: usize, integer: bool, min: Option<f64>, max: Option<f64>) -> Self {
+        let mut histogram = Histogram {
+            bins,
+            integer,
+            integers: Vec::new(),
+            floats: Vec::new(),
+            keep: true,
+            observed: Bounds::default(),
+            sample_size: SAMPLE_SIZE,
+            binned: None,
+            sampled: None,
+            nan_count: 0,
+            negative_infinite: 0,
+            positive_infinite: 0,
+            min,
+            max,
+            underflow: 0,
+            overflow: 0,
+        };
+        if min.is_some() && max.is_some() {
+            histogram.set_bins();
+        }
+        histogram
+    }
+
+    /// Like [`Histogram::new`], but only finds the bounds of the values pushed
+    /// to give to [`Histogram::with_bounds`].
+    pub fn measure(bins: usize, integer: bool, min: Option<f64>, max: Option<f64>) -> Self {
+        Histogram {
+            keep: false,
+            ..Histogram::new(bins, integer, min, max)
+        }
+    }
+
+    /// Bounds of the values pushed, of those within `min` and `max` where given.
+    pub fn bounds(&self) -> Bounds {
+        self.observed
+    }
+
+    /// Sets the bins to span `bounds`, those of all values to come.
+    pub fn with_bounds(mut self, bounds: Bounds) -> Self {
+        self.observed = bounds;
+        if self.binned.is_none() {
+            self.set_bins();
+        }
+        self
+    }
+
+    pub fn push(&mut self, value: Value) {
+        if let (Some(v), true) = (value.as_i128(), self.integer) {
+            // Compared as integers, large ones don't survive a conversion to f64.
+            match v {
+                _ if self.min.is_some_and(|min| v < min.ceil() as i128) => self.underflow += 1,
+                _ if self.max.is_some_and(|max| v > max.floor() as i128) => self.overflow += 1,
+                _ => match &mut self.binned {
+                    Some((bins, counts)) => match bins.place_integer(v) {
+                        Ok(bin) => counts[bin] += 1,
+                        Err(Ordering::Less) => self.underflow += 1,
+                        Err(_) => self.overflow += 1,
+                    },
+                    None => {
+                        self.observed.push_integer(v);
+                        if self.keep {
+                            self.integers.push(v);
+                        }
+                    }
+                },
+            }
+        } else {
+            let v = value.as_f64();
+            match v {
+                _ if v.is_nan() => self.nan_count += 1,
+                f64::NEG_INFINITY => self.negative_infinite += 1,
+                f64::INFINITY => self.positive_infinite += 1,
+                _ if self.min.is_some_and(|min| v < min) => self.underflow += 1,
+                _ if self.max.is_some_and(|max| v > max) => self.overflow += 1,
+                _ => match &mut self.binned {
+                    Some((bins, counts)) => match bins.place_float(v) {
+                        Ok(bin) => counts[bin] += 1,
+                        Err(Ordering::Less) => self.underflow += 1,
+                        Err(_) => self.overflow += 1,
+                    },
+                    None => {
+                        self.observed.push_float(v);
+                        if self.keep {
+                            self.floats.push(v);
+                        }
+                    }
+                },
+            }
+        }
+        let kept = self.integers.len() + self.floats.len();
+        if self.keep && kept >= self.sample_size {
+            self.sampled = Some(kept);
+            self.set_bins();
+        }
+    }
+
+    /// Number of values the bounds of the bins were taken from, if they
+    /// weren't all kept.
+    pub fn sampled(&self) -> Option<usize> {
+        self.sampled
+    }
+
+    /// Sets the bins and bins the values kept so far.
+    fn set_bins(&mut self) {
+        self.binned = self.bin_kept();
+        self.integers = Vec::new();
+        self.floats = Vec::new();
+    }
+
+    /// The bins for the values kept with their counts, `None` if there are
+    /// none to bin.
+    fn bin_kept(&self) -> Option<(Bins, Vec<u64>)> {
+        let bins = self.bins()?;
+        let mut counts = vec![0u64; bins.len()];
+        for value in &self.integers {
+            counts[bins.integer_bin(*value)] += 1;
+        }
+        for value in &self.floats {
+            counts[bins.float_bin(*value)] += 1;
+        }
+        Some((bins, counts))
+    }
+
+    /// The bins for the values, `None` if there are none to bin.
+    fn bins(&self) -> Option<Bins> {
+        match self.integer {
+            true => {
+                // Integer bins start and end on whole numbers.
+                let min = match self.min {
+                    Some(min) => min.ceil() as i128,
+                    None => self.observed.integers?.0,
+                };
+                let max = match self.max {
+                    Some(max) => max.floor() as i128,
+                    None => self.observed.integers?.1,
+                };
+                (min <= max).then(|| Bins::integer(min, max, self.bins))
+            }
+            false => {
+                let min = match self.min {
+                    Some(min) => min,
+                    None => self.observed.floats?.0,
+                };
+                let max = match self.max {
+                    Some(max) => max,
+                    None => self.observed.floats?.1,
+                };
+                Some(Bins::float(min, max, self.bins))
+            }
+        }
+    }
+
+    /// Prints one line per bin with its range, count and a proportional bar,
+    /// the bars are scaled so the lines fit in `line_width` characters.
+    /// `format` formats the edges of float bins.
+    pub fn print(
+        &self,
+        out: &mut impl Write,
+        line_width: usize,
+        format: impl Fn(f64) -> String,
+    ) -> std::io::Result<()> {
+        let apart = [
+            ("nan", self.nan_count),
+            ("-inf", self.negative_infinite),
+            ("inf", self.positive_infinite),
+        ];
+        for (name, count) in apart.iter().filter(|(_, count)| *count > 0) {
+            writeln!(out, "{}: {}", name, count)?;
+        }
+        let kept = match &self.binned {
+            Some(_) => None,
+            None => self.bin_kept(),
+        };
+        let binned = self.binned.as_ref().or(kept.as_ref());
+        // Bounds set from a sample are bounds too.
+        let sampled = self.sampled.and(binned);
+        let (min, max) = match sampled {
+            Some((bins, _)) => bins.bounds(&format),
+            None => (String::new(), String::new()),
+        };
+        match self.min {
+            Some(min) => writeln!(out, "below {}: {}", format(min), self.underflow)?,
+            None if sampled.is_some() => writeln!(out, "below {}: {}", min, self.underflow)?,
+            None => (),
+        }
+        match self.max {
+            Some(max) => writeln!(out, "above {}: {}", format(max), self.overflow)?,
+            None if sampled.is_some() => writeln!(out, "above {}: {}", max, self.overflow)?,
+            None => (),
+        }
+        let (bins, counts) = match binned {
+            Some(binned) => binned,
+            None => return writeln!(out, "no values"),
+        };
+        let ranges: Vec<String> = (0..bins.len())
+            .map(|bin| bins.range(bin, &format))
+            .collect();
+        let range_width = ranges.iter().map(|range| range.len()).max().unwrap_or(0);
+        let most = counts.iter().copied().max().unwrap_or(0);
+        let count_width = most.to_string().len();
+        let bar_width = std::cmp::max(
+            MIN_BAR_WIDTH,
+            line_width.saturating_sub(range_width + count_width + 2),
+        );
+        for (range, count) in ranges.iter().zip(counts) {
+            let bar = match most {
+                0 => 0,
+                _ => (*count as f64 / most as f64 * bar_width as f64).round() as usize,
+            };
+            writeln!(
+                out,
+                "{:<range_width$} {:>count_width$} {}",
+                range,
+                count,
+                "#".repeat(bar),
+                range_width = range_width,
+                count_width = count_width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(histogram: &Histogram) -> String {
+        let mut out = Vec::new();
+        histogram
+            .print(&mut out, 0, |edge| edge.to_string())
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn non_finite_values_are_counted_apart() {
+        let mut histogram = Histogram::new(2, false, None, None);
+        for v in [
+            1.0,
+            f64::NAN,
+            3.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+        ] {
+            histogram.push(Value::F64(v));
+        }
+        let lines: Vec<String> = printed(&histogram)
+            .lines()
+            .map(|line| line.trim_end_matches('#').trim_end().to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            ["nan: 2", "-inf: 1", "inf: 1", "[1, 2) 1", "[2, 3] 1"]
+        );
+    }
+
+    #[test]
+    fn only_non_finite_values() {
+        let mut histogram = Histogram::new(4, false, None, None);
+        histogram.push(Value::F32(f32::NAN));
+        assert_eq!(printed(&histogram), "nan: 1\nno values\n");
+    }
+
+    #[test]
+    fn large_integers_are_binned_exactly() {
+        let mut histogram = Histogram::new(2, true, None, None);
+        for v in [1 << 60, (1 << 60) + 1, (1 << 60) + 3] {
+            histogram.push(Value::U64(v));
+        }
+        let bins = histogram.bins().unwrap();
+        assert_eq!(
+            bins.range(0, &|edge| edge.to_string()),
+            "[1152921504606846976, 1152921504606846977]"
+        );
+        assert_eq!(
+            bins.range(1, &|edge| edge.to_string()),
+            "[1152921504606846978, 1152921504606846979]"
+        );
+        assert_eq!(bins.integer_bin((1 << 60) + 1), 0);
+        assert_eq!(bins.integer_bin((1 << 60) + 3), 1);
+    }
+
+    #[test]
+    fn full_range_of_u64() {
+        let mut histogram = Histogram::new(4, true, None, None);
+        histogram.push(Value::U64(0));
+        histogram.push(Value::U64(u64::MAX));
+        let bins = histogram.bins().unwrap();
+        assert_eq!(bins.len(), 4);
+        assert_eq!(bins.integer_bin(u64::MAX as i128), 3);
+        assert_eq!(
+            bins.range(3, &|edge| edge.to_string()),
+            "[13835058055282163712, 18446744073709551615]"
+        );
+    }
+
+    #[test]
+    fn fewer_integer_bins_than_requested() {
+        let bins = Bins::integer(0, 2, 10);
+        assert_eq!(bins.len(), 3);
+        assert_eq!(bins.range(2, &|edge| edge.to_string()), "[2, 2]");
+    }
+
+    #[test]
+    fn widest_float_range() {
+        let bins = Bins::float(f64::MIN, f64::MAX, 2);
+        assert_eq!(bins.float_bin(f64::MIN), 0);
+        assert_eq!(bins.float_bin(-1e300), 0);
+        assert_eq!(bins.float_bin(1e300), 1);
+        assert_eq!(bins.float_bin(f64::MAX), 1);
+    }
+
+    #[test]
+    fn bins_are_set_by_both_bounds_right_away() {
+        let mut histogram = Histogram::new(2, false, Some(0.0), Some(4.0));
+        assert!(histogram.binned.is_some());
+        for v in [0.0, 1.0, 3.0, 4.0, 5.0] {
+            histogram.push(Value::F64(v));
+        }
+        assert!(histogram.floats.is_empty());
+        assert_eq!(histogram.binned.as_ref().unwrap().1, [2, 2]);
+        assert_eq!(histogram.sampled(), None);
+    }
+
+    #[test]
+    fn memory_is_bounded_by_the_sample() {
+        let mut histogram = Histogram::new(2, true, None, None);
+        histogram.sample_size = 4;
+        for v in [10, 20, 11, 19, 15, 9, 21, 22, 10] {
+            histogram.push(Value::U8(v));
+            assert!(histogram.integers.len() < 4);
+        }
+        assert_eq!(histogram.sampled(), Some(4));
+        let lines: Vec<String> = printed(&histogram)
+            .lines()
+            .map(|line| line.trim_end_matches('#').trim_end().to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            ["below 10: 1", "above 20: 2", "[10, 15] 4", "[16, 20] 2"]
+        );
+    }
+
+    #[test]
+    fn sampled_float_bounds() {
+        let mut histogram = Histogram::new(1, false, Some(0.0), None);
+        histogram.sample_size = 2;
+        for v in [-1.0, 0.5, 2.0, 3.0, 1.0, f64::NAN] {
+            histogram.push(Value::F32(v as f32));
+        }
+        let text = printed(&histogram);
+        assert!(
+            text.starts_with("nan: 1\nbelow 0: 1\nabove 2: 1\n[0, 2] 3"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn bounds_of_a_first_pass() {
+        let values = [10, 20, 11, 19, 15, 9, 21, 22, 10];
+        let mut first = Histogram::measure(2, true, None, None);
+        first.sample_size = 4;
+        for v in values {
+            first.push(Value::U8(v));
+        }
+        assert!(first.integers.is_empty());
+        let mut histogram = Histogram::new(2, true, None, None).with_bounds(first.bounds());
+        histogram.sample_size = 4;
+        for v in values {
+            histogram.push(Value::U8(v));
+        }
+        // The bins span all values, none was sampled.
+        assert_eq!(histogram.sampled(), None);
+        let lines: Vec<String> = printed(&histogram)
+            .lines()
+            .map(|line| line.trim_end_matches('#').trim_end().to_string())
+            .collect();
+        assert_eq!(lines, ["[9, 15]  5", "[16, 22] 4"]);
+        // Without values there are no bins.
+        let empty = Histogram::new(2, false, None, None).with_bounds(Bounds::default());
+        assert_eq!(printed(&empty), "no values\n");
+    }
+
+    #[test]
+    fn values_outside_the_bounds_are_only_counted() {
+        let mut histogram = Histogram::new(1, true, Some(10.0), Some(20.0));
+        for v in [5, 10, 20, 21, i64::MAX] {
+            histogram.push(Value::I64(v));
+        }
+        let text = printed(&histogram);
+        assert!(
+            text.starts_with("below 10: 1\nabove 20: 2\n[10, 20] 2"),
+            "{}",
+            text
+        );
+    }
+}
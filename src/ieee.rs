@@ -0,0 +1,128 @@
+/// Layout of an IEEE 754 binary floating point format.
+struct Format {
+    exponent_bits: u32,
+    mantissa_bits: u32,
+}
+
+const BINARY32: Format = Format {
+    exponent_bits: 8,
+    mantissa_bits: 23,
+};
+
+const BINARY64: Format = Format {
+    exponent_bits: 11,
+    mantissa_bits: 52,
+};
+
+/// Splits the raw bits into sign, biased exponent and mantissa and labels the
+/// special values, e.g. `sign=0 exp=127 mant=0x000000 (1.0)`.
+fn describe(bits: u64, format: &Format, value: f64, display: String) -> String {
+    let mantissa = bits & ((1 << format.mantissa_bits) - 1);
+    let exponent = (bits >> format.mantissa_bits) & ((1 << format.exponent_bits) - 1);
+    let sign = bits >> (format.mantissa_bits + format.exponent_bits);
+    let width = format.mantissa_bits.div_ceil(4) as usize;
+    let max_exponent = (1 << format.exponent_bits) - 1;
+    // The top mantissa bit tells quiet from signaling NaNs.
+    let quiet = mantissa >> (format.mantissa_bits - 1) == 1;
+    let label = match exponent {
+        0 if mantissa != 0 => format!("subnormal {}", display),
+        e if e == max_exponent && mantissa == 0 && value > 0.0 => "inf".to_string(),
+        e if e == max_exponent && mantissa == 0 => "-inf".to_string(),
+        e if e == max_exponent && quiet => "quiet NaN".to_string(),
+        e if e == max_exponent => "signaling NaN".to_string(),
+        _ => display,
+    };
+    format!(
+        "sign={} exp={} mant=0x{:0width$x} ({})",
+        sign,
+        exponent,
+        mantissa,
+        label,
+        width = width
+    )
+}
+
+pub fn describe_f32(v: f32) -> String {
+    describe(v.to_bits() as u64, &BINARY32, v as f64, format!("{:?}", v))
+}
+
+pub fn describe_f64(v: f64) -> String {
+    describe(v.to_bits(), &BINARY64, v, format!("{:?}", v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zeros_and_normal_values() {
+        assert_eq!(describe_f32(1.0), "sign=0 exp=127 mant=0x000000 (1.0)");
+        assert_eq!(describe_f32(-0.0), "sign=1 exp=0 mant=0x000000 (-0.0)");
+        assert_eq!(describe_f32(0.0), "sign=0 exp=0 mant=0x000000 (0.0)");
+        assert_eq!(
+            describe_f64(-0.0),
+            "sign=1 exp=0 mant=0x0000000000000 (-0.0)"
+        );
+        assert_eq!(
+            describe_f64(-1.5),
+            "sign=1 exp=1023 mant=0x8000000000000 (-1.5)"
+        );
+    }
+
+    #[test]
+    fn nan_payloads() {
+        assert_eq!(
+            describe_f32(f32::from_bits(0x7fc0_0001)),
+            "sign=0 exp=255 mant=0x400001 (quiet NaN)"
+        );
+        assert_eq!(
+            describe_f32(f32::from_bits(0xffc0_0000)),
+            "sign=1 exp=255 mant=0x400000 (quiet NaN)"
+        );
+        assert_eq!(
+            describe_f32(f32::from_bits(0x7f80_0001)),
+            "sign=0 exp=255 mant=0x000001 (signaling NaN)"
+        );
+        assert_eq!(
+            describe_f64(f64::from_bits(0x7ff8_0000_0000_beef)),
+            "sign=0 exp=2047 mant=0x800000000beef (quiet NaN)"
+        );
+        assert_eq!(
+            describe_f64(f64::from_bits(0xfff4_0000_0000_0000)),
+            "sign=1 exp=2047 mant=0x4000000000000 (signaling NaN)"
+        );
+    }
+
+    #[test]
+    fn infinities() {
+        assert_eq!(
+            describe_f32(f32::INFINITY),
+            "sign=0 exp=255 mant=0x000000 (inf)"
+        );
+        assert_eq!(
+            describe_f64(f64::NEG_INFINITY),
+            "sign=1 exp=2047 mant=0x0000000000000 (-inf)"
+        );
+    }
+
+    #[test]
+    fn subnormals() {
+        assert_eq!(
+            describe_f32(f32::from_bits(1)),
+            "sign=0 exp=0 mant=0x000001 (subnormal 1e-45)"
+        );
+        assert_eq!(
+            describe_f32(-f32::from_bits(0x007f_ffff)),
+            "sign=1 exp=0 mant=0x7fffff (subnormal -1.1754942e-38)"
+        );
+        assert_eq!(
+            describe_f64(f64::from_bits(1)),
+            "sign=0 exp=0 mant=0x0000000000001 (subnormal 5e-324)"
+        );
+        // The smallest normal value is no longer subnormal.
+        assert_eq!(
+            describe_f32(f32::MIN_POSITIVE),
+            "sign=0 exp=1 mant=0x000000 (1.1754944e-38)"
+        );
+    }
+}
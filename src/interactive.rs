@@ -0,0 +1,203 @@
+//! Commands read one per line for --interactive, to poke at a file without
+//! opening it again for every look.
+
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+
+use clap::ValueEnum;
+
+use parsebin::decode;
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+use parsebin::SizeOf;
+
+use crate::multi::MultiFileReader;
+use crate::size::parse_size;
+
+const HELP: &str = "seek OFFSET     move to OFFSET, or by it with a leading + or -
+type TYPE       read values of TYPE, e.g. u16 or f32
+endian ORDER    read them little, big or native endian
+read [N]        print the next N values, 1 by default, and move past them
+where           print the offset, type and byte order
+help            print this list
+quit            stop, as does the end of the input";
+
+/// Values printed on a line by `read`.
+const VALUES_PER_LINE: usize = 8;
+
+/// Longest LEB128 encoding of a 64 bit integer.
+const MAX_LEB128_SIZE: u64 = 10;
+
+/// Error of a command that can't be run, reported without ending the session.
+fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
+pub struct Session {
+    reader: MultiFileReader,
+    /// Size of the input, if known to check seeks against.
+    size: Option<u64>,
+    offset: u64,
+    parse_type: ParseType,
+    byte_order: ByteOrder,
+}
+
+impl Session {
+    pub fn new(
+        reader: MultiFileReader,
+        size: Option<u64>,
+        offset: u64,
+        parse_type: ParseType,
+        byte_order: ByteOrder,
+    ) -> Self {
+        Session {
+            reader,
+            size,
+            offset,
+            parse_type,
+            byte_order,
+        }
+    }
+
+    fn status(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "offset {} ({:#x}), {}, {}",
+            self.offset, self.offset, self.parse_type, self.byte_order
+        )
+    }
+
+    fn seek(&mut self, to: &str) -> std::io::Result<()> {
+        let offset = match to.as_bytes().first() {
+            Some(b'+') => self
+                .offset
+                .checked_add(parse_size(&to[1..]).map_err(invalid)?),
+            Some(b'-') => self
+                .offset
+                .checked_sub(parse_size(&to[1..]).map_err(invalid)?),
+            _ => Some(parse_size(to).map_err(invalid)?),
+        };
+        match (offset, self.size) {
+            (None, _) => Err(invalid(format!("{} is out of range", to))),
+            (Some(offset), Some(size)) if offset > size => Err(invalid(format!(
+                "offset {} is past the end of the input ({} bytes)",
+                offset, size
+            ))),
+            (Some(offset), _) => {
+                self.offset = offset;
+                Ok(())
+            }
+        }
+    }
+
+    /// Prints `count` values from the offset on and moves past them.
+    fn read(&mut self, count: u64, out: &mut impl Write) -> std::io::Result<()> {
+        let size = match self.parse_type.is_variable_length() {
+            true => MAX_LEB128_SIZE,
+            false => self.parse_type.size_of() as u64,
+        };
+        let mut bytes = Vec::new();
+        self.reader.seek_to(self.offset)?;
+        let length = count.saturating_mul(size);
+        self.reader.by_ref().take(length).read_to_end(&mut bytes)?;
+        let mut start = 0;
+        let mut values = 0;
+        while values < count && start < bytes.len() {
+            // Unless a value is cut off by the end of the input.
+            let (value, size) = match decode(&self.parse_type, &bytes[start..], &self.byte_order) {
+                Some(decoded) => decoded,
+                None => break,
+            };
+            let separator = match values as usize % VALUES_PER_LINE {
+                0 if values > 0 => format!("\n{:08x}: ", self.offset + start as u64),
+                0 => format!("{:08x}: ", self.offset + start as u64),
+                _ => " ".to_string(),
+            };
+            write!(out, "{}{}", separator, value)?;
+            start += size;
+            values += 1;
+        }
+        if values > 0 {
+            writeln!(out)?;
+        }
+        self.offset += start as u64;
+        if values < count {
+            writeln!(out, "end of input at offset {}", self.offset)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `command`, returns false to stop.
+    fn run_command(&mut self, command: &str, out: &mut impl Write) -> std::io::Result<bool> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words[..] {
+            [] => (),
+            ["seek", to] => {
+                self.seek(to)?;
+                self.status(out)?;
+            }
+            ["type", name] => {
+                self.parse_type = ParseType::from_str(name, true)
+                    .map_err(|_| invalid(format!("unknown type {}", name)))?;
+                self.status(out)?;
+            }
+            ["endian", order] => {
+                self.byte_order = match order {
+                    "little" => ByteOrder::LittleEndian,
+                    "big" => ByteOrder::BigEndian,
+                    order => ByteOrder::from_str(order, true)
+                        .map_err(|_| invalid(format!("unknown byte order {}", order)))?,
+                };
+                self.status(out)?;
+            }
+            ["read"] => self.read(1, out)?,
+            ["read", count] => {
+                let count = parse_size(count).map_err(invalid)?;
+                self.read(count, out)?;
+            }
+            ["where"] => self.status(out)?,
+            ["help"] => writeln!(out, "{}", HELP)?,
+            ["quit" | "exit"] => return Ok(false),
+            [name, ..] => {
+                return Err(invalid(format!(
+                    "unknown command or wrong arguments: {}, try help",
+                    name
+                )))
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads commands from `input` until it ends or one quits, and prints
+    /// their results to `out`. Failed commands are reported on stderr and
+    /// don't stop the session. `prompt` is printed to stderr before every
+    /// command.
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        out: &mut impl Write,
+        prompt: Option<&str>,
+    ) -> std::io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if let Some(prompt) = prompt {
+                eprint!("{}", prompt);
+            }
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            match self.run_command(&line, out) {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                    eprintln!("Error: {}", e)
+                }
+                Err(e) => return Err(e),
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use clap::ValueEnum;
+
+use crate::Value;
+
+/// Largest integer that survives a round trip through an IEEE double, as used
+/// by JavaScript numbers.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// How NaN and infinities are written, JSON has no numbers for them.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum NanAs {
+    /// Write `null`
+    Null,
+    /// Write the strings "NaN", "Infinity" and "-Infinity" like JSON5
+    String,
+}
+
+fn format_special(v: f64, nan_as: NanAs) -> String {
+    match nan_as {
+        NanAs::Null => "null".to_string(),
+        NanAs::String if v.is_nan() => "\"NaN\"".to_string(),
+        NanAs::String if v > 0.0 => "\"Infinity\"".to_string(),
+        NanAs::String => "\"-Infinity\"".to_string(),
+    }
+}
+
+/// Formats a value as a JSON number.
+///
+/// NaN and infinities are written according to `nan_as`. Integers outside the
+/// safe integer range are quoted if `bigint_as_string` is set, since many JSON
+/// parsers silently round them otherwise.
+pub fn format_value(value: Value, bigint_as_string: bool, nan_as: NanAs) -> String {
+    match value {
+        Value::F32(v) if !v.is_finite() => format_special(v as f64, nan_as),
+        Value::F64(v) if !v.is_finite() => format_special(v, nan_as),
+        Value::U64(v) if bigint_as_string && v > MAX_SAFE_INTEGER => format!("\"{}\"", v),
+        Value::I64(v) if bigint_as_string && v.unsigned_abs() > MAX_SAFE_INTEGER => {
+            format!("\"{}\"", v)
+        }
+        v => v.to_string(),
+    }
+}
@@ -0,0 +1,153 @@
+use std::io::Read;
+
+use parsebin::decode;
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+
+/// What was read of LEB128 values.
+pub struct Unpacked {
+    /// Bytes the values were read from.
+    pub packed_len: u64,
+    /// Bytes at the end of the input of a value without its last byte.
+    pub trailing: u64,
+}
+
+/// Reads at most `number` LEB128 values of `parse_type` from `reader` and
+/// widens them to u64 or i64 in `byte_order`, so they can be parsed like
+/// values of a fixed size.
+///
+/// The input is read a chunk at a time and the widened values of every chunk
+/// are passed to `emit` with their encoded lengths, a value split between
+/// chunks is carried over. Reading stops early once `emit` returns false.
+pub fn unpack(
+    reader: &mut impl Read,
+    parse_type: &ParseType,
+    byte_order: &ByteOrder,
+    number: u64,
+    mut emit: impl FnMut(&[u8], &[usize]) -> std::io::Result<bool>,
+) -> std::io::Result<Unpacked> {
+    let mut chunk = vec![0; 1 << 16];
+    // Bytes of a value not complete at the end of the last chunk.
+    let mut carried = Vec::new();
+    let mut bytes = Vec::new();
+    let mut lengths = Vec::new();
+    let mut values = 0;
+    let mut packed_len = 0;
+    let mut stopped = false;
+    while values < number && !stopped {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        carried.extend(&chunk[..n]);
+        let mut position = 0;
+        while values < number {
+            let (value, length) = match decode(parse_type, &carried[position..], byte_order) {
+                Some(decoded) => decoded,
+                None => break,
+            };
+            bytes.extend(value.to_bytes(byte_order));
+            lengths.push(length);
+            position += length;
+            values += 1;
+        }
+        carried.drain(..position);
+        packed_len += position as u64;
+        stopped = !emit(&bytes, &lengths)?;
+        bytes.clear();
+        lengths.clear();
+    }
+    let trailing = match values < number && !stopped {
+        true => carried.len() as u64,
+        false => 0,
+    };
+    Ok(Unpacked {
+        packed_len,
+        trailing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The unpacked values, their lengths and what was read.
+    fn unpack_all(
+        mut reader: impl Read,
+        parse_type: ParseType,
+        number: u64,
+    ) -> (Vec<u64>, Vec<usize>, Unpacked) {
+        let mut values = Vec::new();
+        let mut lengths = Vec::new();
+        let unpacked = unpack(
+            &mut reader,
+            &parse_type,
+            &ByteOrder::LittleEndian,
+            number,
+            |bytes, chunk_lengths| {
+                values.extend(
+                    bytes
+                        .chunks(8)
+                        .map(|value| u64::from_le_bytes(value.try_into().unwrap())),
+                );
+                lengths.extend(chunk_lengths);
+                Ok(true)
+            },
+        )
+        .unwrap();
+        (values, lengths, unpacked)
+    }
+
+    #[test]
+    fn values_split_between_reads() {
+        let packed = [0xe5, 0x8e, 0x26, 0x7f, 0x80, 0x01];
+        // Every byte is a read of its own.
+        let split = packed.iter().fold(
+            Box::new(std::io::empty()) as Box<dyn Read>,
+            |reader, byte| Box::new(reader.chain(std::slice::from_ref(byte))),
+        );
+        let (values, lengths, unpacked) = unpack_all(split, ParseType::Uleb128, u64::MAX);
+        assert_eq!(values, [624_485, 127, 128]);
+        assert_eq!(lengths, [3, 1, 2]);
+        assert_eq!((unpacked.packed_len, unpacked.trailing), (6, 0));
+    }
+
+    #[test]
+    fn signed_values() {
+        let (values, _, _) = unpack_all(&[0x7e, 0x80, 0x7f][..], ParseType::Sleb128, u64::MAX);
+        assert_eq!(values, [-2i64 as u64, -128i64 as u64]);
+    }
+
+    #[test]
+    fn value_cut_short_by_the_end() {
+        let (values, _, unpacked) =
+            unpack_all(&[0x01, 0x80, 0x80][..], ParseType::Uleb128, u64::MAX);
+        assert_eq!(values, [1]);
+        assert_eq!((unpacked.packed_len, unpacked.trailing), (1, 2));
+        // Not trailing if the values asked for were all read.
+        let (_, _, unpacked) = unpack_all(&[0x01, 0x80, 0x80][..], ParseType::Uleb128, 1);
+        assert_eq!(unpacked.trailing, 0);
+    }
+
+    #[test]
+    fn endless_input() {
+        let (values, _, unpacked) = unpack_all(std::io::repeat(0x05), ParseType::Uleb128, 3);
+        assert_eq!(values, [5, 5, 5]);
+        assert_eq!(unpacked.packed_len, 3);
+        let mut chunks = 0;
+        unpack(
+            &mut std::io::repeat(0),
+            &ParseType::Sleb128,
+            &ByteOrder::BigEndian,
+            u64::MAX,
+            |_, _| {
+                chunks += 1;
+                Ok(chunks < 3)
+            },
+        )
+        .unwrap();
+        assert_eq!(chunks, 3);
+    }
+}
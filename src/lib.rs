@@ -0,0 +1,1143 @@
+//! Decoding of binary data into primitive values, as done by the parsebin CLI.
+//!
+//! [`BinParser`] reads the values of one type, or records of several, from
+//! any reader, [`decode`] decodes a single value from a byte slice.
+//!
+//! ```no_run
+//! use parsebin::{BinParser, ByteOrder, ParseType};
+//!
+//! let file = std::fs::File::open("samples.bin")?;
+//! for value in BinParser::new(file, ParseType::F32, ByteOrder::LittleEndian, 0, Some(16)) {
+//!     println!("{}", value?);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fmt::Display;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Type of the values in the binary data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ParseType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    /// 3 byte unsigned integer, e.g. 24 bit PCM
+    U24,
+    /// 3 byte signed integer, e.g. 24 bit PCM
+    I24,
+    /// Unsigned LEB128 variable-length integer, as in protobuf, DWARF and WebAssembly
+    Uleb128,
+    /// Signed LEB128 variable-length integer
+    Sleb128,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+    /// Byte order of the machine running parsebin
+    Native,
+}
+
+impl Display for ByteOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByteOrder::LittleEndian => write!(f, "little-endian"),
+            ByteOrder::BigEndian => write!(f, "big-endian"),
+            ByteOrder::Native if cfg!(target_endian = "big") => write!(f, "native (big-endian)"),
+            ByteOrder::Native => write!(f, "native (little-endian)"),
+        }
+    }
+}
+
+pub trait SizeOf {
+    fn size_of(&self) -> i64;
+}
+
+/// The name of the type as given on the command line.
+impl Display for ParseType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ParseType::U8 => "u8",
+            ParseType::U16 => "u16",
+            ParseType::U32 => "u32",
+            ParseType::U64 => "u64",
+            ParseType::I8 => "i8",
+            ParseType::I16 => "i16",
+            ParseType::I32 => "i32",
+            ParseType::I64 => "i64",
+            ParseType::F32 => "f32",
+            ParseType::F64 => "f64",
+            ParseType::U24 => "u24",
+            ParseType::I24 => "i24",
+            ParseType::Uleb128 => "uleb128",
+            ParseType::Sleb128 => "sleb128",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl SizeOf for ParseType {
+    fn size_of(&self) -> i64 {
+        let usize = match self {
+            ParseType::U8 => std::mem::size_of::<u8>(),
+            ParseType::U16 => std::mem::size_of::<u16>(),
+            ParseType::U32 => std::mem::size_of::<u32>(),
+            ParseType::U64 => std::mem::size_of::<u64>(),
+            ParseType::I8 => std::mem::size_of::<i8>(),
+            ParseType::I16 => std::mem::size_of::<i16>(),
+            ParseType::I32 => std::mem::size_of::<i32>(),
+            ParseType::I64 => std::mem::size_of::<i64>(),
+            ParseType::F32 => std::mem::size_of::<f32>(),
+            ParseType::F64 => std::mem::size_of::<f64>(),
+            ParseType::U24 | ParseType::I24 => 3,
+            // The shortest encoding, longer ones are only known when decoding.
+            ParseType::Uleb128 | ParseType::Sleb128 => 1,
+        };
+        usize as i64
+    }
+}
+
+impl ParseType {
+    pub fn is_float(&self) -> bool {
+        matches!(self, ParseType::F32 | ParseType::F64)
+    }
+
+    /// Whether the size of a value is only known when decoding it.
+    pub fn is_variable_length(&self) -> bool {
+        matches!(self, ParseType::Uleb128 | ParseType::Sleb128)
+    }
+
+    /// Parses a value of this type from text, e.g. a --min or --max bound.
+    /// Integers may be given in hex with a `0x` prefix.
+    pub fn parse(&self, s: &str) -> Result<Value, String> {
+        let invalid = |e: &dyn Display| format!("invalid {:?} value '{}': {}", self, s, e);
+        Ok(match self {
+            ParseType::U8 => Value::U8(parse_int(s).map_err(|e| invalid(&e))?),
+            ParseType::U16 => Value::U16(parse_int(s).map_err(|e| invalid(&e))?),
+            ParseType::U32 => Value::U32(parse_int(s).map_err(|e| invalid(&e))?),
+            ParseType::U64 | ParseType::Uleb128 => {
+                Value::U64(parse_int(s).map_err(|e| invalid(&e))?)
+            }
+            ParseType::I8 => Value::I8(parse_int(s).map_err(|e| invalid(&e))?),
+            ParseType::I16 => Value::I16(parse_int(s).map_err(|e| invalid(&e))?),
+            ParseType::I32 => Value::I32(parse_int(s).map_err(|e| invalid(&e))?),
+            ParseType::I64 | ParseType::Sleb128 => {
+                Value::I64(parse_int(s).map_err(|e| invalid(&e))?)
+            }
+            ParseType::F32 => Value::F32(s.parse().map_err(|e| invalid(&e))?),
+            ParseType::F64 => Value::F64(s.parse().map_err(|e| invalid(&e))?),
+            ParseType::U24 => match parse_int::<u32>(s).map_err(|e| invalid(&e))? {
+                v if v > 0xff_ffff => return Err(invalid(&"number too large to fit in 24 bits")),
+                v => Value::U24(v),
+            },
+            ParseType::I24 => match parse_int::<i32>(s).map_err(|e| invalid(&e))? {
+                v if !(-0x80_0000..0x80_0000).contains(&v) => {
+                    return Err(invalid(&"number out of range for 24 bits"))
+                }
+                v => Value::I24(v),
+            },
+        })
+    }
+}
+
+/// Parses a decimal integer, or a hex one like `0xCAFEBABE` or `-0x10`.
+fn parse_int<T>(s: &str) -> Result<T, String>
+where
+    T: std::str::FromStr + TryFrom<i128>,
+    T::Err: Display,
+{
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let hex = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) if !hex.starts_with(['+', '-']) => hex,
+        _ => return s.parse().map_err(|e: T::Err| e.to_string()),
+    };
+    let magnitude = i128::from_str_radix(hex, 16).map_err(|e| e.to_string())?;
+    let value = match negative {
+        true => -magnitude,
+        false => magnitude,
+    };
+    T::try_from(value).map_err(|_| "number out of range for the type".to_string())
+}
+
+/// A decoded value, one variant per [`ParseType`], LEB128 values are 64 bit.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    U24(u32),
+    I24(i32),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::U8(v) => write!(f, "{}", v),
+            Value::U16(v) => write!(f, "{}", v),
+            Value::U32(v) => write!(f, "{}", v),
+            Value::U64(v) => write!(f, "{}", v),
+            Value::I8(v) => write!(f, "{}", v),
+            Value::I16(v) => write!(f, "{}", v),
+            Value::I32(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+            Value::F32(v) => write!(f, "{}", v),
+            Value::F64(v) => write!(f, "{}", v),
+            Value::U24(v) => write!(f, "{}", v),
+            Value::I24(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl Value {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::U8(v) => v as f64,
+            Value::U16(v) => v as f64,
+            Value::U32(v) => v as f64,
+            Value::U64(v) => v as f64,
+            Value::I8(v) => v as f64,
+            Value::I16(v) => v as f64,
+            Value::I32(v) => v as f64,
+            Value::I64(v) => v as f64,
+            Value::F32(v) => v as f64,
+            Value::F64(v) => v,
+            Value::U24(v) => v as f64,
+            Value::I24(v) => v as f64,
+        }
+    }
+
+    /// The value as an integer, or `None` for floats.
+    pub fn as_i128(&self) -> Option<i128> {
+        match *self {
+            Value::U8(v) => Some(v as i128),
+            Value::U16(v) => Some(v as i128),
+            Value::U32(v) => Some(v as i128),
+            Value::U64(v) => Some(v as i128),
+            Value::I8(v) => Some(v as i128),
+            Value::I16(v) => Some(v as i128),
+            Value::I32(v) => Some(v as i128),
+            Value::I64(v) => Some(v as i128),
+            Value::F32(_) | Value::F64(_) => None,
+            Value::U24(v) => Some(v as i128),
+            Value::I24(v) => Some(v as i128),
+        }
+    }
+
+    /// Encodes the value like it was parsed, the inverse of [`decode`].
+    pub fn to_bytes(self, byte_order: &ByteOrder) -> Vec<u8> {
+        macro_rules! encode {
+            ($v:expr) => {
+                match byte_order {
+                    ByteOrder::LittleEndian => $v.to_le_bytes().to_vec(),
+                    ByteOrder::BigEndian => $v.to_be_bytes().to_vec(),
+                    ByteOrder::Native => $v.to_ne_bytes().to_vec(),
+                }
+            };
+        }
+        match self {
+            Value::U8(v) => encode!(v),
+            Value::U16(v) => encode!(v),
+            Value::U32(v) => encode!(v),
+            Value::U64(v) => encode!(v),
+            Value::I8(v) => encode!(v),
+            Value::I16(v) => encode!(v),
+            Value::I32(v) => encode!(v),
+            Value::I64(v) => encode!(v),
+            Value::F32(v) => encode!(v),
+            Value::F64(v) => encode!(v),
+            Value::U24(v) => {
+                let little_endian = match byte_order {
+                    ByteOrder::LittleEndian => true,
+                    ByteOrder::BigEndian => false,
+                    ByteOrder::Native => cfg!(target_endian = "little"),
+                };
+                match little_endian {
+                    true => v.to_le_bytes()[0..3].to_vec(),
+                    false => v.to_be_bytes()[1..4].to_vec(),
+                }
+            }
+            Value::I24(v) => Value::U24(v as u32 & 0xff_ffff).to_bytes(byte_order),
+        }
+    }
+}
+
+/// Decodes the value of `parse_type` at the start of `bytes` and returns it
+/// with its size, or `None` if `bytes` end within it.
+///
+/// A LEB128 value ends with the first byte without the continuation bit, see
+/// [`leb128_len`]. Bits beyond 64 are dropped.
+pub fn decode(
+    parse_type: &ParseType,
+    bytes: &[u8],
+    byte_order: &ByteOrder,
+) -> Option<(Value, usize)> {
+    macro_rules! decode {
+        ($t:ty, $variant:ident) => {{
+            const SIZE: usize = std::mem::size_of::<$t>();
+            let array = *bytes.first_chunk::<SIZE>()?;
+            let value = match byte_order {
+                ByteOrder::LittleEndian => <$t>::from_le_bytes(array),
+                ByteOrder::BigEndian => <$t>::from_be_bytes(array),
+                ByteOrder::Native => <$t>::from_ne_bytes(array),
+            };
+            (Value::$variant(value), SIZE)
+        }};
+    }
+    Some(match parse_type {
+        ParseType::U8 => decode!(u8, U8),
+        ParseType::U16 => decode!(u16, U16),
+        ParseType::U32 => decode!(u32, U32),
+        ParseType::U64 => decode!(u64, U64),
+        ParseType::I8 => decode!(i8, I8),
+        ParseType::I16 => decode!(i16, I16),
+        ParseType::I32 => decode!(i32, I32),
+        ParseType::I64 => decode!(i64, I64),
+        ParseType::F32 => decode!(f32, F32),
+        ParseType::F64 => decode!(f64, F64),
+        ParseType::U24 | ParseType::I24 => {
+            let little_endian = match byte_order {
+                ByteOrder::LittleEndian => true,
+                ByteOrder::BigEndian => false,
+                ByteOrder::Native => cfg!(target_endian = "little"),
+            };
+            let [a, b, c] = *bytes.first_chunk::<3>()?;
+            let value = match little_endian {
+                true => u32::from_le_bytes([a, b, c, 0]),
+                false => u32::from_be_bytes([0, a, b, c]),
+            };
+            match parse_type {
+                // Shift bit 23 into the sign bit and back to sign extend.
+                ParseType::I24 => (Value::I24((value << 8) as i32 >> 8), 3),
+                _ => (Value::U24(value), 3),
+            }
+        }
+        ParseType::Uleb128 | ParseType::Sleb128 => {
+            let size = leb128_len(bytes)?;
+            let mut value = 0u64;
+            let mut shift = 0;
+            for byte in &bytes[..size] {
+                if shift < 64 {
+                    value |= ((byte & 0x7f) as u64) << shift;
+                }
+                shift += 7;
+            }
+            let negative = bytes[size - 1] & 0x40 != 0;
+            match parse_type {
+                ParseType::Sleb128 if negative && shift < 64 => {
+                    (Value::I64((value | u64::MAX << shift) as i64), size)
+                }
+                ParseType::Sleb128 => (Value::I64(value as i64), size),
+                _ => (Value::U64(value), size),
+            }
+        }
+    })
+}
+
+/// Length of the LEB128 value at the start of `bytes`, `None` if it doesn't end in `bytes`.
+pub fn leb128_len(bytes: &[u8]) -> Option<usize> {
+    bytes
+        .iter()
+        .position(|byte| byte & 0x80 == 0)
+        .map(|last| last + 1)
+}
+
+/// Shared flag used to stop a parse cooperatively.
+///
+/// [`BinParser`] checks it before every value or record, the CLI then
+/// finishes and flushes the output as if the input had ended.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a parse has got, reported to a progress callback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes read from the input, skipped ones included.
+    pub bytes: u64,
+    /// Values decoded so far.
+    pub values: u64,
+}
+
+/// Callback receiving the [`Progress`] of a parse every time its buffer is
+/// refilled.
+pub type ProgressCallback = Box<dyn FnMut(Progress) + Send>;
+
+/// Reader counting the bytes read from it.
+struct Counted<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// Iterator over the values of one type read from `R`, or over the fields of
+/// records set up with [`BinParser::with_records`].
+///
+/// The reader is buffered internally. Reading ends at the end of the input,
+/// after `limit` records or once cancelled, a value cut short by the end of
+/// the input is an `UnexpectedEof` error.
+pub struct BinParser<R: Read> {
+    reader: BufReader<Counted<R>>,
+    fields: Vec<ParseType>,
+    byte_order: ByteOrder,
+    skip_before: usize,
+    stride: u64,
+    // Size of a record without the stride, unless it has variable-length fields.
+    record_len: Option<usize>,
+    // Bytes to skip before reading on, the offset and then the stride after a record.
+    skip: u64,
+    // The record read last or being read, the bytes skipped before its fields included.
+    record: Vec<u8>,
+    // Fields of `record` read so far and the start of the next one.
+    fields_read: usize,
+    field_start: usize,
+    // Next field and its start in `record` yielded by the iterator.
+    next_field: usize,
+    next_start: usize,
+    remaining: Option<u64>,
+    cancel: Option<CancellationToken>,
+    on_progress: Option<ProgressCallback>,
+    // Last progress reported, or to report once the values end.
+    progress: Progress,
+    finished: bool,
+}
+
+impl<R: Read> BinParser<R> {
+    /// Parses `limit` values, or all if `None`, starting `offset` bytes into `reader`.
+    pub fn new(
+        reader: R,
+        parse_type: ParseType,
+        byte_order: ByteOrder,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Self {
+        BinParser {
+            reader: BufReader::new(Counted {
+                inner: reader,
+                bytes: 0,
+            }),
+            fields: vec![parse_type.clone()],
+            byte_order,
+            skip_before: 0,
+            stride: 0,
+            record_len: record_len(std::slice::from_ref(&parse_type), 0),
+            skip: offset,
+            record: Vec::new(),
+            fields_read: 0,
+            field_start: 0,
+            next_field: 1,
+            next_start: 0,
+            remaining: limit,
+            cancel: None,
+            on_progress: None,
+            progress: Progress::default(),
+            finished: false,
+        }
+    }
+
+    /// Reads records of `fields` instead of single values, `skip_before`
+    /// bytes before the fields of every record and `stride` bytes after them
+    /// are skipped. The `limit` then counts records, the iterator yields the
+    /// values of their fields in turn.
+    ///
+    /// The stride after the last record may be missing.
+    pub fn with_records(mut self, fields: Vec<ParseType>, skip_before: u64, stride: u64) -> Self {
+        assert!(!fields.is_empty(), "a record has at least one field");
+        self.next_field = fields.len();
+        self.record_len = record_len(&fields, skip_before as usize);
+        self.fields = fields;
+        self.skip_before = skip_before as usize;
+        self.field_start = self.skip_before;
+        self.stride = stride;
+        self
+    }
+
+    /// Reads `capacity` bytes of the input at a time instead of 8 KiB, to be
+    /// set before the first value is read.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.reader = BufReader::with_capacity(capacity, self.reader.into_inner());
+        self
+    }
+
+    /// Stops the values once `token` is cancelled, checked before every value.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Calls `callback` with the progress every time the buffer is refilled,
+    /// and once more when the values end.
+    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
+        self.on_progress = Some(callback);
+        self
+    }
+
+    /// How far the parse has got.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.reader.get_ref().bytes,
+            values: self.progress.values,
+        }
+    }
+
+    /// Returns the underlying reader, which may have been read ahead.
+    pub fn get_ref(&self) -> &R {
+        &self.reader.get_ref().inner
+    }
+
+    /// Returns the underlying reader, buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner().inner
+    }
+
+    /// Reads the next record, its values are then in [`BinParser::record`].
+    ///
+    /// Returns `false` at the end of the input, after `limit` records or
+    /// once cancelled. Unlike the iterator it can be called again after the
+    /// end of the input, to read on once more input is available, e.g. when
+    /// following a growing file. A record cut short stays in
+    /// [`BinParser::trailing`] until then.
+    pub fn read_record(&mut self) -> std::io::Result<bool> {
+        if self.fields_read == self.fields.len() {
+            self.record.clear();
+            self.fields_read = 0;
+            self.field_start = self.skip_before;
+        }
+        let cancelled = self
+            .cancel
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled());
+        if self.remaining == Some(0) || cancelled || !self.fill_record()? {
+            self.report(true);
+            return Ok(false);
+        }
+        self.skip = self.stride;
+        self.remaining = self.remaining.map(|n| n - 1);
+        self.progress.values += self.fields.len() as u64;
+        self.next_field = 0;
+        self.next_start = self.skip_before;
+        self.report(false);
+        Ok(true)
+    }
+
+    /// The values of the record read last by [`BinParser::read_record`],
+    /// none if it returned `false`.
+    pub fn record(&self) -> Record<'_> {
+        let fields = match self.fields_read == self.fields.len() {
+            true => &self.fields[..],
+            false => &[],
+        };
+        Record {
+            fields: fields.iter(),
+            bytes: self.record.get(self.skip_before..).unwrap_or_default(),
+            start: 0,
+            byte_order: &self.byte_order,
+        }
+    }
+
+    /// Bytes of a record cut short by the end of the input, those skipped
+    /// before its fields included.
+    pub fn trailing(&self) -> &[u8] {
+        match self.fields_read == self.fields.len() {
+            true => &[],
+            false => &self.record,
+        }
+    }
+
+    /// Reports the progress if bytes were read since the last report, or
+    /// regardless if `last`.
+    fn report(&mut self, last: bool) {
+        let progress = self.progress();
+        if let Some(callback) = &mut self.on_progress {
+            if last || progress.bytes != self.progress.bytes {
+                callback(progress);
+            }
+        }
+        self.progress = progress;
+    }
+
+    /// Reads on into the record, returns whether it is complete or else the input ended.
+    fn fill_record(&mut self) -> std::io::Result<bool> {
+        // Most records are buffered whole already and copied at once.
+        if let Some(len) = self.record_len.filter(|_| self.record.is_empty()) {
+            let buffered = self.reader.buffer();
+            let skip = self.skip as usize;
+            if self.skip <= buffered.len() as u64 && len <= buffered.len() - skip {
+                self.record.extend_from_slice(&buffered[skip..skip + len]);
+                self.reader.consume(skip + len);
+                self.skip = 0;
+                self.fields_read = self.fields.len();
+                return Ok(true);
+            }
+        }
+        while self.skip > 0 {
+            let buffer = match self.reader.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if buffer.is_empty() {
+                return Ok(false);
+            }
+            let n = std::cmp::min(buffer.len() as u64, self.skip) as usize;
+            self.reader.consume(n);
+            self.skip -= n as u64;
+        }
+        if !self.fill_to(self.skip_before)? {
+            return Ok(false);
+        }
+        while self.fields_read < self.fields.len() {
+            let field = &self.fields[self.fields_read];
+            let ended = match field.is_variable_length() {
+                true => self.fill_leb128()?,
+                false => self.fill_to(self.field_start + field.size_of() as usize)?,
+            };
+            if !ended {
+                return Ok(false);
+            }
+            self.fields_read += 1;
+            self.field_start = self.record.len();
+        }
+        Ok(true)
+    }
+
+    /// Reads into the record until it is `len` bytes long, returns false if the input ends first.
+    fn fill_to(&mut self, len: usize) -> std::io::Result<bool> {
+        while self.record.len() < len {
+            let buffer = match self.reader.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if buffer.is_empty() {
+                return Ok(false);
+            }
+            let n = std::cmp::min(buffer.len(), len - self.record.len());
+            self.record.extend_from_slice(&buffer[..n]);
+            self.reader.consume(n);
+        }
+        Ok(true)
+    }
+
+    /// Reads a LEB128 value into the record, returns false if the input ends within it.
+    fn fill_leb128(&mut self) -> std::io::Result<bool> {
+        while self.record.len() == self.field_start
+            || self.record[self.record.len() - 1] & 0x80 != 0
+        {
+            let buffer = match self.reader.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if buffer.is_empty() {
+                return Ok(false);
+            }
+            let n = leb128_len(buffer).unwrap_or(buffer.len());
+            self.record.extend_from_slice(&buffer[..n]);
+            self.reader.consume(n);
+        }
+        Ok(true)
+    }
+
+    /// Error for a value cut short by the end of the input.
+    fn cut_short(&self) -> std::io::Error {
+        let field = &self.fields[self.fields_read];
+        let read = self.record.len().saturating_sub(self.field_start);
+        let message = match field.is_variable_length() {
+            true => format!("input ends within a {} value after {} bytes", field, read),
+            false => format!(
+                "input ends within a {} value, {} of {} bytes read",
+                field,
+                read,
+                field.size_of()
+            ),
+        };
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, message)
+    }
+}
+
+impl<R: Read> Iterator for BinParser<R> {
+    type Item = std::io::Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.next_field == self.fields.len() {
+            match self.read_record() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.finished = true;
+                    return match self.record.len() > self.skip_before {
+                        true => Some(Err(self.cut_short())),
+                        false => None,
+                    };
+                }
+                Err(e) => {
+                    self.finished = true;
+                    self.report(true);
+                    return Some(Err(e));
+                }
+            }
+        }
+        let field = &self.fields[self.next_field];
+        let (value, size) = decode(field, &self.record[self.next_start..], &self.byte_order)?;
+        self.next_field += 1;
+        self.next_start += size;
+        Some(Ok(value))
+    }
+}
+
+/// Size of a record of `fields`, `None` if it is only known when decoding it.
+fn record_len(fields: &[ParseType], skip_before: usize) -> Option<usize> {
+    match fields.iter().any(ParseType::is_variable_length) {
+        true => None,
+        false => Some(skip_before + fields.iter().map(|f| f.size_of() as usize).sum::<usize>()),
+    }
+}
+
+/// Values of a record read by [`BinParser::read_record`], each with the
+/// bytes it was decoded from.
+pub struct Record<'a> {
+    fields: std::slice::Iter<'a, ParseType>,
+    bytes: &'a [u8],
+    start: usize,
+    byte_order: &'a ByteOrder,
+}
+
+impl<'a> Record<'a> {
+    /// Bytes of the fields, without those skipped around them.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for Record<'a> {
+    type Item = (Value, &'a [u8]);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let field = self.fields.next()?;
+        let (value, size) = decode(field, &self.bytes[self.start..], self.byte_order)?;
+        let bytes = &self.bytes[self.start..self.start + size];
+        self.start += size;
+        Some((value, bytes))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.fields.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Record<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_negative_i16() {
+        let cases = [
+            ([0xff, 0xff], -1),
+            ([0xfe, 0xff], -2),
+            ([0x00, 0x80], i16::MIN),
+            ([0xff, 0x7f], i16::MAX),
+        ];
+        for (bytes, expected) in cases {
+            let decoded = decode(&ParseType::I16, &bytes, &ByteOrder::LittleEndian);
+            assert_eq!(decoded, Some((Value::I16(expected), 2)));
+            let reversed = [bytes[1], bytes[0]];
+            let decoded = decode(&ParseType::I16, &reversed, &ByteOrder::BigEndian);
+            assert_eq!(decoded, Some((Value::I16(expected), 2)));
+        }
+    }
+
+    #[test]
+    fn decode_inverts_to_bytes() {
+        let values = [
+            Value::U8(0xfe),
+            Value::U16(0xfeff),
+            Value::U32(0xdead_beef),
+            Value::U64(u64::MAX - 1),
+            Value::I8(-2),
+            Value::I16(-300),
+            Value::I32(-70_000),
+            Value::I64(i64::MIN),
+            Value::F32(-1.5),
+            Value::F64(-0.25),
+            Value::U24(0xff_fffe),
+            Value::I24(-2),
+        ];
+        let types = [
+            ParseType::U8,
+            ParseType::U16,
+            ParseType::U32,
+            ParseType::U64,
+            ParseType::I8,
+            ParseType::I16,
+            ParseType::I32,
+            ParseType::I64,
+            ParseType::F32,
+            ParseType::F64,
+            ParseType::U24,
+            ParseType::I24,
+        ];
+        for byte_order in [
+            ByteOrder::LittleEndian,
+            ByteOrder::BigEndian,
+            ByteOrder::Native,
+        ] {
+            for (value, parse_type) in values.iter().zip(&types) {
+                let bytes = value.to_bytes(&byte_order);
+                assert_eq!(bytes.len() as i64, parse_type.size_of());
+                assert_eq!(
+                    decode(parse_type, &bytes, &byte_order),
+                    Some((*value, bytes.len()))
+                );
+                assert_eq!(decode(parse_type, &bytes[1..], &byte_order), None);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn type_names_are_the_ones_of_the_cli() {
+        use clap::ValueEnum;
+        for parse_type in ParseType::value_variants() {
+            let value = parse_type.to_possible_value().unwrap();
+            assert_eq!(parse_type.to_string(), value.get_name());
+        }
+    }
+
+    #[test]
+    fn decode_leb128_until_its_last_byte() {
+        let bytes = [0xe5, 0x8e, 0x26, 0x7f];
+        let decoded = decode(&ParseType::Uleb128, &bytes, &ByteOrder::LittleEndian);
+        assert_eq!(decoded, Some((Value::U64(624_485), 3)));
+        let decoded = decode(&ParseType::Sleb128, &bytes[3..], &ByteOrder::LittleEndian);
+        assert_eq!(decoded, Some((Value::I64(-1), 1)));
+        // Cut short before the byte without the continuation bit.
+        assert_eq!(
+            decode(&ParseType::Uleb128, &bytes[..2], &ByteOrder::LittleEndian),
+            None
+        );
+        assert_eq!(
+            decode(&ParseType::Sleb128, &[], &ByteOrder::LittleEndian),
+            None
+        );
+    }
+
+    /// Reader returning a single byte per read, like a slow pipe.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(first)) => {
+                    *first = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    /// The values encoded again so NaNs compare equal, or the kind of error
+    /// that ended them.
+    fn parse_all(
+        reader: impl Read,
+        parse_type: ParseType,
+        offset: u64,
+    ) -> Vec<Result<Vec<u8>, std::io::ErrorKind>> {
+        BinParser::new(reader, parse_type, ByteOrder::LittleEndian, offset, None)
+            .map(|value| {
+                value
+                    .map(|v| v.to_bytes(&ByteOrder::LittleEndian))
+                    .map_err(|e| e.kind())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn short_reads_dont_split_values() {
+        let bytes: Vec<u8> = (0..100)
+            .flat_map(|v: i32| (v * -3000).to_le_bytes())
+            .collect();
+        for parse_type in [ParseType::I16, ParseType::I24, ParseType::F64] {
+            for offset in [0, 1, 7] {
+                assert_eq!(
+                    parse_all(OneByte(&bytes), parse_type.clone(), offset),
+                    parse_all(&bytes[..], parse_type.clone(), offset),
+                    "{} from {}",
+                    parse_type,
+                    offset
+                );
+            }
+        }
+        let leb128 = [0xe5, 0x8e, 0x26, 0x7f, 0x80, 0x01];
+        assert_eq!(
+            parse_all(OneByte(&leb128), ParseType::Uleb128, 0),
+            [624_485u64, 127, 128].map(|v| Ok(v.to_le_bytes().to_vec()))
+        );
+    }
+
+    #[test]
+    fn short_read_at_the_end_is_an_error() {
+        let bytes = [1, 0, 2];
+        let mut parser = BinParser::new(
+            OneByte(&bytes),
+            ParseType::U16,
+            ByteOrder::LittleEndian,
+            0,
+            None,
+        );
+        assert_eq!(parser.next().unwrap().unwrap(), Value::U16(1));
+        let error = parser.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn cancel_an_endless_parse() {
+        let token = CancellationToken::new();
+        let mut out = std::io::BufWriter::new(Vec::new());
+        let parser = BinParser::new(
+            std::io::repeat(1),
+            ParseType::U16,
+            ByteOrder::LittleEndian,
+            0,
+            None,
+        )
+        .with_cancellation(token.clone());
+        let mut written = 0;
+        for value in parser {
+            std::io::Write::write_all(&mut out, &value.unwrap().to_bytes(&ByteOrder::LittleEndian))
+                .unwrap();
+            written += 1;
+            if written == 1000 {
+                token.cancel();
+            }
+        }
+        // Nothing is yielded after the cancellation.
+        assert_eq!(written, 1000);
+        let out = out.into_inner().unwrap();
+        assert_eq!(out, vec![1; 2000]);
+    }
+
+    #[test]
+    fn cancelled_before_the_first_value() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut parser = BinParser::new(&[1, 2][..], ParseType::U8, ByteOrder::Native, 0, None)
+            .with_cancellation(token);
+        assert!(parser.next().is_none());
+        assert_eq!(parser.progress(), Progress::default());
+    }
+
+    #[test]
+    fn progress_is_reported() {
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let bytes = vec![0; 100_000];
+        let parser = BinParser::new(
+            OneByte(&bytes),
+            ParseType::U32,
+            ByteOrder::LittleEndian,
+            4,
+            Some(10),
+        )
+        .with_progress(Box::new(move |progress| {
+            sink.lock().unwrap().push(progress)
+        }));
+        assert_eq!(parser.count(), 10);
+        let reports = reports.lock().unwrap();
+        // A reader returning one byte per read refills the buffer for every
+        // value, the end is reported once more.
+        assert_eq!(reports.len(), 11);
+        assert_eq!(
+            reports[0],
+            Progress {
+                bytes: 8,
+                values: 1
+            }
+        );
+        assert!(reports[..10]
+            .windows(2)
+            .all(|pair| pair[0].bytes < pair[1].bytes));
+        assert_eq!(
+            reports.last(),
+            Some(&Progress {
+                bytes: 44,
+                values: 10
+            })
+        );
+    }
+
+    #[test]
+    fn progress_is_reported_when_cancelled() {
+        let token = CancellationToken::new();
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let mut parser = BinParser::new(
+            std::io::repeat(0),
+            ParseType::F64,
+            ByteOrder::BigEndian,
+            0,
+            None,
+        )
+        .with_cancellation(token.clone())
+        .with_progress(Box::new(move |progress| {
+            sink.lock().unwrap().push(progress)
+        }));
+        assert_eq!(parser.by_ref().take(3).count(), 3);
+        token.cancel();
+        assert!(parser.next().is_none());
+        assert!(parser.next().is_none());
+        let reports = reports.lock().unwrap();
+        let last = *reports.last().unwrap();
+        assert_eq!(last.values, 3);
+        assert!(last.bytes >= 24);
+        // The first value fills the buffer, the end is reported once.
+        assert_eq!(reports.len(), 2);
+    }
+
+    #[test]
+    fn records_skip_around_their_fields() {
+        // A byte skipped before a u8 and an i16 and two after them, which
+        // are missing in part after the last record.
+        let bytes = [0xaa, 1, 0xfe, 0xff, 0xbb, 0xbb, 0xaa, 2, 3, 0, 0xbb];
+        let mut parser = BinParser::new(
+            OneByte(&bytes),
+            ParseType::U8,
+            ByteOrder::LittleEndian,
+            0,
+            None,
+        )
+        .with_records(vec![ParseType::U8, ParseType::I16], 1, 2);
+        let mut records = Vec::new();
+        while parser.read_record().unwrap() {
+            let record = parser.record();
+            let bytes = record.bytes().to_vec();
+            records.push((record.map(|(value, _)| value).collect::<Vec<_>>(), bytes));
+        }
+        assert_eq!(
+            records,
+            [
+                (vec![Value::U8(1), Value::I16(-2)], vec![1, 0xfe, 0xff]),
+                (vec![Value::U8(2), Value::I16(3)], vec![2, 3, 0]),
+            ]
+        );
+        assert_eq!(parser.record().count(), 0);
+        assert!(parser.trailing().is_empty());
+        assert_eq!(parser.progress().values, 4);
+    }
+
+    /// Input that bytes can be appended to while it is read, like a followed file.
+    #[derive(Clone, Default)]
+    struct Growing(std::rc::Rc<std::cell::RefCell<std::collections::VecDeque<u8>>>);
+
+    impl Growing {
+        fn append(&self, bytes: &[u8]) {
+            self.0.borrow_mut().extend(bytes);
+        }
+    }
+
+    impl Read for Growing {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().read(buf)
+        }
+    }
+
+    #[test]
+    fn read_on_after_the_end_of_the_input() {
+        let input = Growing::default();
+        input.append(&[1, 0, 0xff, 2]);
+        let mut parser = BinParser::new(
+            input.clone(),
+            ParseType::U16,
+            ByteOrder::LittleEndian,
+            0,
+            None,
+        )
+        .with_records(vec![ParseType::U16], 0, 1);
+        assert!(parser.read_record().unwrap());
+        assert!(!parser.read_record().unwrap());
+        assert_eq!(parser.trailing(), [2]);
+        // The record is completed once the input grows.
+        input.append(&[0]);
+        assert!(parser.read_record().unwrap());
+        assert_eq!(parser.record().next(), Some((Value::U16(2), &[2, 0][..])));
+        assert!(!parser.read_record().unwrap());
+        // Its stride was missing at the end and is skipped first.
+        input.append(&[0xff, 4, 0]);
+        assert!(parser.read_record().unwrap());
+        assert_eq!(parser.record().next().unwrap().0, Value::U16(4));
+    }
+
+    #[test]
+    fn cancellation_is_shared_by_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn cancellation_from_another_thread() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        std::thread::spawn(move || clone.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+    }
+}
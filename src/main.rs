@@ -1,314 +1,4111 @@
-use std::i64::MAX;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::io::IsTerminal;
 use std::io::Read;
-use std::os::windows::fs::MetadataExt;
+use std::io::Write;
 
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use clap::CommandFactory;
+use clap::FromArgMatches;
 use clap::Parser;
 use clap::ValueEnum;
 
-#[derive(ValueEnum, Debug, Clone)]
-enum ParseType {
-    U8,
-    U16,
-    U32,
-    U64,
-    I8,
-    I16,
-    I32,
-    I64,
-    F32,
-    F64,
+#[cfg(feature = "arrow")]
+mod arrow;
+mod bits;
+mod color;
+mod completions;
+mod compression;
+mod container;
+mod counts;
+mod crossing;
+mod digest;
+mod endian;
+mod entropy;
+mod format;
+mod guess;
+mod histogram;
+mod ieee;
+mod interactive;
+mod json;
+mod leb128;
+mod multi;
+mod npy;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod plot;
+mod search;
+mod serial;
+mod size;
+mod special;
+mod split;
+mod stats;
+mod wav;
+#[cfg(feature = "arrow")]
+use arrow::ArrowWriter;
+use bits::BitOrder;
+use color::ColorChoice;
+use color::ColorScale;
+use completions::Shell;
+use compression::Compression;
+use counts::ValueCounts;
+use crossing::Crossings;
+use digest::Algorithm;
+use digest::Digest;
+use endian::Endianness;
+use entropy::ByteCounts;
+use format::TextFormat;
+use format::TimeUnit;
+use histogram::Histogram;
+use interactive::Session;
+use json::NanAs;
+use multi::MultiFileReader;
+use npy::NpyHeader;
+use npy::NpyWriter;
+#[cfg(feature = "parquet")]
+use parquet::Codec;
+#[cfg(feature = "parquet")]
+use parquet::ParquetWriter;
+use parsebin::BinParser;
+use parsebin::ByteOrder;
+use parsebin::CancellationToken;
+use parsebin::ParseType;
+use parsebin::Record;
+use parsebin::SizeOf;
+use parsebin::Value;
+use plot::Plot;
+use serial::Encoding;
+use serial::SerialWriter;
+use special::Special;
+use special::SpecialCounts;
+use split::SplitWriter;
+use stats::Stats;
+use wav::WavWriter;
+
+/// Prints a warning to stderr unless --quiet is given.
+macro_rules! warn {
+    ($args:expr, $($arg:tt)*) => {
+        if !$args.quiet {
+            eprintln!("Warning: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Format of the values selected with --output-format.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// CSV records with --row-size values each, same as --csv
+    Csv,
+    /// A JSON array of values or rows, same as --json
+    Json,
+    /// One JSON array per row and line
+    Jsonl,
+    /// Rows prefixed with their index and # comments describing the input, for gnuplot
+    Gnuplot,
+    /// A sequence of MessagePack numbers, the values of all rows one after another
+    Msgpack,
+    /// A sequence of CBOR numbers, the values of all rows one after another
+    Cbor,
+}
+
+/// Base the byte offsets of --addr are printed in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+enum AddressStyle {
+    Hex,
+    Dec,
 }
 
-#[derive(ValueEnum, Debug, Clone)]
-enum ByteOrder {
-    LittleEndian,
-    BigEndian,
+/// TYPE as given, a type or auto to take it from the input.
+#[derive(Debug, Clone, PartialEq)]
+enum TypeName {
+    Auto,
+    Type(ParseType),
 }
 
-impl ToString for ByteOrder {
-    fn to_string(&self) -> String {
+impl TypeName {
+    /// The type, `None` for `Auto`.
+    fn parse_type(&self) -> Option<ParseType> {
         match self {
-            ByteOrder::LittleEndian => "little-endian".to_string(),
-            ByteOrder::BigEndian => "big-endian".to_string(),
+            TypeName::Auto => None,
+            TypeName::Type(parse_type) => Some(parse_type.clone()),
         }
     }
 }
 
-trait SizeOf {
-    fn size_of(&self) -> i64;
+/// Parser of TYPE, the names of the types and auto.
+fn type_names() -> impl clap::builder::TypedValueParser<Value = TypeName> {
+    use clap::builder::TypedValueParser;
+    let auto = clap::builder::PossibleValue::new("auto")
+        .help("Take the type from a parsebin, .npy or .wav header or --struct");
+    let names = ParseType::value_variants()
+        .iter()
+        .filter_map(|parse_type| parse_type.to_possible_value())
+        .chain([auto]);
+    clap::builder::PossibleValuesParser::new(names).map(|name: String| {
+        match ParseType::from_str(&name, false) {
+            Ok(parse_type) => TypeName::Type(parse_type),
+            Err(_) => TypeName::Auto,
+        }
+    })
+}
+
+/// Byte offset to start parsing at, negative values count from the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Offset {
+    FromStart(u64),
+    FromEnd(u64),
 }
 
-impl SizeOf for ParseType {
-    fn size_of(&self) -> i64 {
-        let usize = match self {
-            ParseType::U8 => std::mem::size_of::<u8>(),
-            ParseType::U16 => std::mem::size_of::<u16>(),
-            ParseType::U32 => std::mem::size_of::<u32>(),
-            ParseType::U64 => std::mem::size_of::<u64>(),
-            ParseType::I8 => std::mem::size_of::<i8>(),
-            ParseType::I16 => std::mem::size_of::<i16>(),
-            ParseType::I32 => std::mem::size_of::<i32>(),
-            ParseType::I64 => std::mem::size_of::<i64>(),
-            ParseType::F32 => std::mem::size_of::<f32>(),
-            ParseType::F64 => std::mem::size_of::<f64>(),
-        };
-        usize as i64
+impl Display for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Offset::FromStart(o) => write!(f, "{}", o),
+            Offset::FromEnd(o) => write!(f, "-{}", o),
+        }
+    }
+}
+
+fn parse_offset(s: &str) -> Result<Offset, String> {
+    match s.strip_prefix('-') {
+        Some(o) => size::parse_size(o).map(Offset::FromEnd),
+        None => size::parse_size(s).map(Offset::FromStart),
+    }
+}
+
+/// Format of fixed-point numbers, e.g. 1.15 for Q1.15, not counting the sign bit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FixedPoint {
+    int_bits: u32,
+    frac_bits: u32,
+}
+
+fn parse_fixed(s: &str) -> Result<FixedPoint, String> {
+    let (int_bits, frac_bits) = s
+        .split_once('.')
+        .ok_or_else(|| "expected INT_BITS.FRAC_BITS, e.g. 1.15".to_string())?;
+    let bits = |s: &str| s.parse::<u32>().map_err(|e| format!("{}: {}", s, e));
+    let fixed = FixedPoint {
+        int_bits: bits(int_bits)?,
+        frac_bits: bits(frac_bits)?,
+    };
+    match fixed.int_bits.checked_add(fixed.frac_bits) {
+        Some(1..=64) => Ok(fixed),
+        _ => Err("expected 1 to 64 bits".to_string()),
+    }
+}
+
+/// Bits HIGH down to LOW of an integer for --field, bit 0 is the least significant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BitField {
+    high: u32,
+    low: u32,
+}
+
+fn parse_bit_field(s: &str) -> Result<BitField, String> {
+    // A single bit is N:N.
+    let (high, low) = s.split_once(':').unwrap_or((s, s));
+    let bit = |s: &str| s.parse::<u32>().map_err(|e| format!("{}: {}", s, e));
+    let field = BitField {
+        high: bit(high)?,
+        low: bit(low)?,
+    };
+    match field.high < field.low {
+        true => Err(format!(
+            "expected HIGH:LOW, but {} is below {}",
+            field.high, field.low
+        )),
+        false => Ok(field),
+    }
+}
+
+/// Field types of a record, e.g. `<u32,f32,f32`, with an optional leading `<`
+/// (little-endian) or `>` (big-endian) that overrides --byte-order.
+#[derive(Debug, Clone)]
+struct Layout {
+    byte_order: Option<ByteOrder>,
+    fields: Vec<ParseType>,
+}
+
+fn parse_layout(s: &str) -> Result<Layout, String> {
+    let (byte_order, fields) = match s.chars().next() {
+        Some('<') => (Some(ByteOrder::LittleEndian), &s[1..]),
+        Some('>') => (Some(ByteOrder::BigEndian), &s[1..]),
+        _ => (None, s),
+    };
+    let fields = fields
+        .split(',')
+        .map(|token| {
+            ParseType::from_str(token.trim(), true)
+                .map_err(|_| format!("unknown type '{}'", token.trim()))
+        })
+        .collect::<Result<Vec<ParseType>, String>>()?;
+    Ok(Layout { byte_order, fields })
+}
+
+/// Options that take the names of their columns from --header.
+fn named_columns() -> clap::ArgGroup {
+    #[allow(unused_mut)]
+    let mut args = vec!["csv", "output_format"];
+    #[cfg(feature = "arrow")]
+    args.push("arrow");
+    #[cfg(feature = "parquet")]
+    args.push("parquet");
+    clap::ArgGroup::new("named_columns").args(args)
+}
+
+/// Options of the plain text rows, refused with every --output-format.
+const TEXT_ONLY: &[&str] = &[
+    "ascii",
+    "group",
+    "align",
+    "field_sep",
+    "show_bytes",
+    "labels",
+    "stats",
+    "guess",
+];
+
+/// Options of JSON, refused with the other formats.
+const JSON_ONLY: &[&str] = &["json_bigint_as_string", "nan_as"];
+
+/// Options of the plain text rows and CSV, refused with the other formats.
+const CSV_ONLY: &[&str] = &[
+    "record_sep",
+    "highlight_special",
+    "complex",
+    "bits",
+    "float_bits",
+    "float_bits_only",
+    "as_time",
+    "headers",
+    "delimiter",
+    "header",
+    "auto_header",
+    "split_columns",
+];
+
+/// Options of the formats with rows, refused with gnuplot, msgpack and cbor.
+const ROWS_ONLY: &[&str] = &["addr", "value_counts"];
+
+/// Options that need every value to start on a byte of its own, refused with --bit-width and LEB128.
+const PACKED_CONFLICTS: &[&str] = &[
+    "layout",
+    "complex",
+    "channels",
+    "stride",
+    "skip_before",
+    "skip",
+    "last",
+    "follow",
+    "mmap",
+    "with_header",
+    "addr",
+    "show_bytes",
+    "find",
+    "expect",
+];
+
+/// Parses a separator, replacing the escapes \t, \n, \r, \0 and \\.
+fn parse_separator(s: &str) -> Result<String, String> {
+    let mut separator = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            separator.push(c);
+            continue;
+        }
+        separator.push(match chars.next() {
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some(c) => return Err(format!("unknown escape '\\{}'", c)),
+            None => return Err("trailing '\\'".to_string()),
+        });
+    }
+    Ok(separator)
+}
+
+/// Stands for `--row-size auto` until the row size is picked from the terminal width.
+const AUTO_ROW_SIZE: usize = 0;
+
+/// Bytes --guess looks at.
+const GUESS_SAMPLE: u64 = 4096;
+
+/// Width of the output assumed if it isn't a terminal.
+const DEFAULT_WIDTH: usize = 80;
+
+fn parse_row_size(s: &str) -> Result<usize, String> {
+    match s {
+        "auto" => Ok(AUTO_ROW_SIZE),
+        _ => match s.parse::<usize>() {
+            Ok(0) => Err("expected at least 1 or auto".to_string()),
+            Ok(n) => Ok(n),
+            Err(e) => Err(format!("expected a number or auto: {}", e)),
+        },
+    }
+}
+
+/// Width of the terminal the output goes to, or `DEFAULT_WIDTH` if it doesn't.
+fn output_width(args: &Opt) -> usize {
+    match (&args.output, std::io::stdout().is_terminal()) {
+        (None, true) => terminal_size::terminal_size()
+            .map(|(width, _)| width.0 as usize)
+            .unwrap_or(DEFAULT_WIDTH),
+        _ => DEFAULT_WIDTH,
+    }
+}
+
+/// Picks the number of values of `parse_type` that fit on a line of the terminal.
+fn auto_row_size(parse_type: &ParseType, args: &Opt, address_width: usize) -> usize {
+    let width = output_width(args);
+    let value_width = args
+        .width
+        .unwrap_or_else(|| args.format.field_width(&output_type(parse_type, args)))
+        + 1;
+    let mut available = width;
+    if args.addr.is_some() {
+        available = available.saturating_sub(address_width + 2);
     }
+    // The ASCII gutter takes one character per value and its borders.
+    let value_width = match args.ascii {
+        true => {
+            available = available.saturating_sub(4);
+            value_width + 1
+        }
+        false => value_width,
+    };
+    std::cmp::max(1, available / value_width)
+}
+
+fn parse_number(s: &str) -> Result<i64, String> {
+    let number = size::parse_size(s)?;
+    i64::try_from(number).map_err(|_| format!("'{}' is larger than {}", s, i64::MAX))
 }
 
 #[derive(Parser, Debug)]
+#[command(after_help = size::SIZE_HELP)]
+#[command(group(clap::ArgGroup::new("pattern").args(["skip_header_until", "find_pattern", "find_str"])))]
+#[command(group(clap::ArgGroup::new("decibels").args(["db", "db_power"])))]
+#[command(group(clap::ArgGroup::new("compare").args(["find", "diff", "expect"])))]
+#[command(group(clap::ArgGroup::new("frequencies").args(["byte_freq", "value_counts"])))]
+#[command(group(clap::ArgGroup::new("find_bytes").args(["find_pattern", "find_str"])))]
+#[command(group(clap::ArgGroup::new("range").args(["min", "max", "eq"]).multiple(true)))]
+#[command(group(clap::ArgGroup::new("csv_output").args(["csv", "output_format"])))]
+#[command(group(named_columns()))]
+#[command(group(clap::ArgGroup::new("json_output").args(["json", "output_format"])))]
+#[command(group(clap::ArgGroup::new("channel_source").args(["channels", "wav"])))]
+#[command(group(clap::ArgGroup::new("output_mode").args([
+    "stats_only", "histogram", "plot", "crossings", "count_only", "count_special", "emit_binary", "reencode", "npy",
+    "wav_out", "find", "diff", "expect", "interactive", "csv", "json", "output_format",
+])))]
 struct Opt {
-    #[clap(value_name = "TYPE")]
-    parse_type: ParseType,
+    /// Type of the values, auto to take it from a parsebin, .npy or .wav header or --struct
+    #[clap(value_name = "TYPE", value_parser = type_names(), required_unless_present = "completions")]
+    type_name: Option<TypeName>,
+
+    /// TYPE unless auto
+    #[clap(skip)]
+    parse_type: Option<ParseType>,
+
+    /// Parse records of mixed types like "<u32,f32,f32" instead of TYPE, one record per row
+    #[clap(long = "struct", value_name = "LAYOUT", conflicts_with_all = ["row_size", "channels", "with_header", "ascii"], value_parser = parse_layout)]
+    layout: Option<Layout>,
+
+    /// Byte offset to start at, negative values count back from the end of the file
+    #[clap(short, long, default_value_t = Offset::FromStart(0), value_parser = parse_offset, allow_hyphen_values = true)]
+    offset: Offset,
+
+    /// Offset to start at counted in values of TYPE instead of bytes
+    #[clap(long, conflicts_with = "offset", value_parser = size::parse_size)]
+    offset_elements: Option<u64>,
 
-    #[clap(short, long, default_value_t = 0)]
-    offset: u64,
+    /// Drop the first N values after the offset, counted like --number and including --stride
+    #[clap(long, value_name = "N", conflicts_with = "last", value_parser = size::parse_size)]
+    skip: Option<u64>,
 
-    #[clap(short, long, default_value_t = MAX)]
+    /// Start parsing right after the first occurrence of these hex bytes, --offset is relative to it
+    #[clap(long, value_name = "HEX", value_parser = search::parse_hex_bytes)]
+    skip_header_until: Option<::std::vec::Vec<u8>>,
+
+    /// Start parsing at the first occurrence of these hex bytes, --offset is relative to it
+    #[clap(long, value_name = "HEX", value_parser = search::parse_hex_bytes)]
+    find_pattern: Option<::std::vec::Vec<u8>>,
+
+    /// Like --find-pattern, but searches for the bytes of this text, e.g. RIFF
+    #[clap(long, value_name = "TEXT", value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    find_str: Option<String>,
+
+    /// Start right after the pattern found by --find-pattern instead of at it
+    #[clap(long, requires = "find_bytes")]
+    after_pattern: bool,
+
+    /// Use the K-th occurrence of --find-pattern, occurrences may overlap
+    #[clap(long, value_name = "K", default_value_t = 1, requires = "find_bytes", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    nth_match: u64,
+
+    /// Print the offset, element index and value of every value equal to V instead of all values
+    ///
+    /// Integers may be given in hex, e.g. 0xCAFEBABE. Exits with 1 if there
+    /// is no match. The element index is "-" for matches between elements.
+    #[clap(long, value_name = "V", allow_hyphen_values = true, conflicts_with_all = ["layout", "complex", "skip_before", "follow", "every", "delta", "skip_nan", "range", "stats", "split_columns"])]
+    find: Option<String>,
+
+    /// Print a checksum of the bytes that would be read and their number instead of the values
+    #[clap(long, value_name = "ALGORITHM", value_enum, conflicts_with_all = ["output_mode", "bit_width", "follow", "stats", "split_columns"])]
+    checksum: Option<Algorithm>,
+
+    /// Print the Shannon entropy of the bytes that would be read in bits per byte instead of the values
+    ///
+    /// Compressed and encrypted data is close to 8, text and structured data
+    /// much lower. The bytes are counted whatever TYPE is.
+    #[clap(long, conflicts_with_all = ["output_mode", "bit_width", "follow", "stats", "split_columns"])]
+    entropy: bool,
+
+    /// Print how often every byte value occurs in the bytes that would be read instead of the values
+    ///
+    /// The most frequent first, with their share of all bytes and the totals.
+    #[clap(long, conflicts_with_all = ["output_mode", "bit_width", "follow", "stats", "split_columns"])]
+    byte_freq: bool,
+
+    /// Print how often every distinct value occurs instead of the values
+    ///
+    /// The most frequent first, per column, with their share of all values
+    /// and the totals. Floats are only counted rounded with --round.
+    #[clap(long, conflicts_with_all = ["find", "diff", "expect", "checksum", "entropy", "byte_freq", "follow", "stats", "stats_only", "histogram", "plot", "crossings", "emit_binary", "reencode", "npy", "wav_out", "count_only", "count_special", "split_columns", "complex", "as_time", "ascii", "show_bytes", "group", "labels"])]
+    value_counts: bool,
+
+    /// Round floats of --value-counts to N decimal places to count them
+    #[clap(long, value_name = "N", requires = "value_counts")]
+    round: Option<u8>,
+
+    /// Fail once --value-counts finds more than N distinct values in a column
+    /// instead of using up the memory
+    #[clap(long, value_name = "N", default_value_t = 1_000_000, requires = "value_counts", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_distinct: usize,
+
+    /// Order the table of --byte-freq or --value-counts by value instead of count
+    #[clap(long, requires = "frequencies")]
+    by_value: bool,
+
+    /// Only print the first K values of --byte-freq or --value-counts
+    #[clap(long, value_name = "K", requires = "frequencies", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    top: Option<usize>,
+
+    /// Compare the values with those of OTHER at the same offsets and print those that differ
+    ///
+    /// Every difference is printed as "index offset: value != other value",
+    /// followed by a summary. A shorter input is reported, not truncated.
+    #[clap(long, value_name = "OTHER", conflicts_with_all = ["bit_width", "complex", "follow", "delta", "cumsum", "cummean", "stats", "split_columns"])]
+    diff: Option<String>,
+
+    /// Offset in OTHER of --diff if it's not the one of the input, then both are printed
+    #[clap(long, value_name = "OFFSET", requires = "diff", value_parser = size::parse_size)]
+    diff_offset: Option<u64>,
+
+    /// Also treat floats of --diff as the same if they differ by at most REL times the larger magnitude
+    #[clap(long, value_name = "REL", requires = "diff")]
+    rel_tolerance: Option<f64>,
+
+    /// Exit with 1 if --diff finds a difference or one input is shorter
+    #[clap(long, requires = "diff")]
+    exit_code: bool,
+
+    /// Check that the values are the ones listed in GOLDEN, separated by whitespace as parsebin prints them
+    ///
+    /// Exits with 1 at the first value that differs. GOLDEN may list fewer
+    /// values than there are unless --exact-length is given.
+    #[clap(long, value_name = "GOLDEN", conflicts_with_all = ["bit_fields", "complex", "follow", "every", "skip_nan", "delta", "cumsum", "cummean", "decibels", "stats", "split_columns"])]
+    expect: Option<String>,
+
+    /// Fail --expect if GOLDEN lists fewer values than there are
+    #[clap(long, requires = "expect")]
+    exact_length: bool,
+
+    /// Also match --find values and treat --diff and --expect values as the same if they differ by at most EPS, for floats
+    #[clap(long, value_name = "EPS", requires = "compare")]
+    tolerance: Option<f64>,
+
+    /// Check --find at every byte instead of only at the start of every element
+    #[clap(long, requires = "find")]
+    unaligned: bool,
+
+    /// Stop after K matches of --find
+    #[clap(long, value_name = "K", requires = "find", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    max_matches: Option<u64>,
+
+    /// Number of bytes searched for the pattern
+    #[clap(long, requires = "pattern", value_parser = size::parse_size)]
+    scan_limit: Option<u64>,
+
+    /// Bytes to skip after every value, e.g. --stride 12 with f32 reads one float every 16 bytes
+    #[clap(long, visible_alias = "skip-after", default_value_t = 0, value_parser = size::parse_size)]
+    stride: u64,
+
+    /// Print the difference to the previous value instead of the value, unsigned types wrap around
+    ///
+    /// With --struct and --channels the difference is taken per field and channel.
+    #[clap(long, conflicts_with_all = ["complex", "as_time"])]
+    delta: bool,
+
+    /// Print the running sum of the values instead of the values, e.g. to integrate a signal
+    ///
+    /// Integers are summed as u64 or i64, so even sums of u8 and i8 only wrap
+    /// around after 2^56 values, while sums of u64 and i64 may wrap around
+    /// right away. Floats are summed as f64. With --struct and --channels
+    /// there is a sum per field and channel.
+    #[clap(long, conflicts_with_all = ["delta", "complex", "emit_binary", "reencode", "npy", "find"])]
+    cumsum: bool,
+
+    /// Print the mean of the values so far instead of the values, summed like --cumsum
+    #[clap(long, conflicts_with_all = ["cumsum", "delta", "complex", "emit_binary", "reencode", "npy", "find"])]
+    cummean: bool,
+
+    /// Don't print the first value of --delta, which has no previous value
+    #[clap(long, requires = "delta")]
+    delta_skip_first: bool,
+
+    /// Only print every N-th value, --number and --last count the printed values
+    ///
+    /// Frames of --channels and pairs of --complex count as one value.
+    #[clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    every: Option<u64>,
+
+    /// Print N values, then leave out the number of --drop and repeat, --number counts the printed values
+    ///
+    /// Frames of --channels and pairs of --complex count as one value.
+    #[clap(long, value_name = "N", requires = "drop", conflicts_with_all = ["every", "last"], value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    take: Option<u64>,
+
+    /// Number of values left out after every block of --take
+    #[clap(long, value_name = "M", requires = "take")]
+    drop: Option<u64>,
+
+    /// Bytes to skip before every value
+    #[clap(long, default_value_t = 0, value_parser = size::parse_size)]
+    skip_before: u64,
+
+    /// Parse the samples of a .wav file with the type and channels of its header,
+    /// --offset and --length are relative to the samples
+    #[clap(long, conflicts_with_all = ["layout", "pattern", "end", "bit_width"])]
+    wav: bool,
+
+    /// Number of interleaved channels, each frame is printed as a row unless --channel is given
+    #[clap(long, value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    channels: Option<u64>,
+
+    /// Only print this channel (0-based) of the interleaved frames
+    #[clap(long, requires = "channel_source")]
+    channel: Option<u64>,
+
+    /// Size of the read buffer; larger is faster
+    /// for big files, smaller is more responsive with --follow
+    #[clap(long, value_name = "SIZE", default_value = "4KiB", value_parser = size::parse_size)]
+    buffer_size: u64,
+
+    /// Print the number of bytes every uleb128 and sleb128 value was encoded in after it
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Read the input as packed fields of N bits, widened to the integer TYPE
+    #[clap(long, value_name = "N", conflicts_with_all = PACKED_CONFLICTS, value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..=64))]
+    bit_width: Option<u32>,
+
+    /// Order of the bits of --bit-width fields [default: msb for big-endian, else lsb]
+    #[clap(long, requires = "bit_width")]
+    bit_order: Option<BitOrder>,
+
+    /// Memory map the file instead of reading it through a buffer
+    #[clap(long)]
+    mmap: bool,
+
+    /// Never memory map the file, not even above --mmap-threshold
+    #[clap(long, conflicts_with = "mmap")]
+    no_mmap: bool,
+
+    /// Memory map a single regular file automatically if at least this many bytes are parsed
+    #[clap(long, value_name = "SIZE", default_value = "64MiB", value_parser = size::parse_size)]
+    mmap_threshold: u64,
+
+    /// Keep reading values appended to the file, like tail -f
+    #[clap(short, long, conflicts_with_all = ["end", "length", "last", "mmap"])]
+    follow: bool,
+
+    /// Number of values to parse
+    #[clap(short, long, default_value_t = i64::MAX, value_parser = parse_number)]
     number: i64,
 
-    #[clap(short, long, default_value_t = ByteOrder::LittleEndian)]
+    /// Byte offset to stop parsing at (exclusive)
+    #[clap(long, conflicts_with_all = ["number", "length"], value_parser = size::parse_size)]
+    end: Option<u64>,
+
+    /// Number of bytes to parse, starting at the offset
+    #[clap(long, conflicts_with = "number", value_parser = size::parse_size)]
+    length: Option<u64>,
+
+    /// Only parse the last N values before the end of the file or range
+    #[clap(long, conflicts_with = "number", value_parser = size::parse_size)]
+    last: Option<u64>,
+
+    #[clap(
+        short = 'b',
+        long = "byte-order",
+        value_name = "BYTE_ORDER",
+        default_value = "little-endian"
+    )]
+    endianness: Endianness,
+
+    /// Only print the byte order guessed from the first values and how confident the guess is
+    #[clap(long)]
+    detect_only: bool,
+
+    /// Rank the types and byte orders the first 4 KiB could have by how plausible their values look
+    ///
+    /// Prints the score of every candidate and its first values instead of
+    /// the values, TYPE is given as auto.
+    #[clap(long, conflicts_with_all = ["layout", "wav", "offset_elements", "detect_only", "find", "diff", "checksum", "entropy", "byte_freq", "stats", "stats_only", "histogram", "plot", "emit_binary", "npy", "wav_out", "count_only"])]
+    guess: bool,
+
+    /// Read commands like "seek 0x40", "type f32", "endian big" and "read 16"
+    /// from stdin and print their results, "help" lists them
+    ///
+    /// The input stays open and a cursor keeps the offset between commands.
+    /// It starts at the offset and with TYPE, u8 for auto, and the byte
+    /// order, little-endian for auto.
+    #[clap(long, conflicts_with_all = ["layout", "wav", "guess", "detect_only", "follow", "output", "value_counts", "stats", "split_columns"])]
+    interactive: bool,
+
+    /// --byte-order, guessed or given by a header
+    #[clap(skip = ByteOrder::LittleEndian)]
     byte_order: ByteOrder,
 
-    file: String,
+    /// How the values are printed, from the options of the text output
+    #[clap(skip)]
+    format: TextFormat,
+
+    /// Number of values printed per line, or auto to fill the width of the terminal
+    #[clap(short, long, default_value = "1", value_parser = parse_row_size)]
+    row_size: usize,
+
+    /// Print count, min, max, sum, mean and standard deviation after the values,
+    /// NaN and infinities are counted apart
+    #[clap(long, conflicts_with_all = ["stats_only", "emit_binary", "npy", "histogram", "count_only", "count_special", "split_columns", "complex", "plot"])]
+    stats: bool,
+
+    /// Print only the statistics of --stats instead of the values
+    #[clap(long)]
+    stats_only: bool,
+
+    /// Only print the number of values and bytes parsed and the trailing bytes ignored
+    ///
+    /// With --min, --max or --eq it prints how many values or rows match.
+    #[clap(long, conflicts_with_all = ["complex", "addr", "ascii", "group", "headers"])]
+    count_only: bool,
+
+    /// Combine each pair of values into a complex number printed as (re+imj), --number counts pairs
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "split_columns", "layout", "channels", "ascii", "range"])]
+    complex: bool,
+
+    /// Print magnitude and phase of --complex values instead
+    #[clap(long, requires = "complex")]
+    polar: bool,
+
+    /// Print the --polar phase in degrees instead of radians
+    #[clap(long, requires = "polar")]
+    degrees: bool,
+
+    /// Print a histogram with this many bins between the minimum and maximum instead of the values
+    ///
+    /// The minimum and maximum are found in a first pass over the input. For pipes and --follow
+    /// they are those of the first 1048576 values, later values outside them are counted as below
+    /// or above the bins. NaN and infinities are counted apart.
+    #[clap(long, value_name = "BINS", conflicts_with_all = ["split_columns", "row_size"], value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    histogram: Option<usize>,
+
+    /// Print a sparkline of the values, one per column, instead of the values,
+    /// in ASCII unless printing to a terminal
+    #[clap(long, conflicts_with_all = ["complex", "split_columns"])]
+    plot: bool,
+
+    /// Number of lines of the --plot, every line adds eight steps
+    #[clap(long, value_name = "LINES", default_value_t = 1, requires = "plot", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    plot_height: usize,
+
+    /// Print the index, offset, direction and the values around every crossing of THRESHOLD
+    /// instead of the values, and then their number
+    #[clap(long, value_name = "THRESHOLD", allow_hyphen_values = true, conflicts_with_all = ["stats", "complex", "split_columns", "any_column", "all_columns"])]
+    crossings: Option<f64>,
+
+    /// Only count --crossings once the values are H apart, half of it on each side of the threshold
+    #[clap(long, value_name = "H", default_value_t = 0.0, requires = "crossings")]
+    hysteresis: f64,
+
+    /// Lower bound of the --histogram bins, smaller values are only counted
+    #[clap(
+        long,
+        value_name = "VALUE",
+        requires = "histogram",
+        allow_hyphen_values = true
+    )]
+    hist_min: Option<f64>,
+
+    /// Upper bound of the --histogram bins, larger values are only counted
+    #[clap(
+        long,
+        value_name = "VALUE",
+        requires = "histogram",
+        allow_hyphen_values = true
+    )]
+    hist_max: Option<f64>,
+
+    /// Write the values as raw binary in the selected byte order instead of text
+    #[clap(long)]
+    emit_binary: bool,
+
+    /// Like --emit-binary, but write the values in this byte order, e.g. to swap it
+    #[clap(long, value_name = "BYTE_ORDER")]
+    reencode: Option<ByteOrder>,
+
+    /// Write the values to a NumPy .npy file instead of printing them
+    ///
+    /// The array has a row per row of --row-size, or one element per record of
+    /// --struct with a field per value. 24 bit integers are widened to 32 bits.
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["complex", "split_columns", "range", "show_bytes"])]
+    npy: Option<String>,
+
+    /// Write the values to a .wav file instead of printing them, a frame per row of --channels
+    ///
+    /// Integers are stored as PCM and f32 and f64 as IEEE float samples, the
+    /// signedness of 8 bit and longer integers is changed to that of WAV.
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["complex", "split_columns", "range", "show_bytes", "layout"])]
+    wav_out: Option<String>,
+
+    /// Write the values to an Apache Arrow IPC file, also known as Feather v2, instead of printing
+    /// them
+    ///
+    /// There is a column per value of a row of --row-size, or per field of --struct, named by
+    /// --header or col0, col1 and so on. 24 bit integers are widened to 32 bits.
+    #[cfg(feature = "arrow")]
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["output_mode", "value_counts", "complex", "split_columns", "range", "show_bytes", "checksum", "entropy", "byte_freq"])]
+    arrow: Option<String>,
+
+    /// Rows of every record batch of the --arrow file, only a batch is kept in memory
+    #[cfg(feature = "arrow")]
+    #[clap(long, value_name = "ROWS", default_value_t = 65536, requires = "arrow", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    arrow_batch_size: usize,
+
+    /// Write the values to an Apache Parquet file instead of printing them
+    ///
+    /// There is a column per value of a row of --row-size, or per field of --struct, named by
+    /// --header or col0, col1 and so on. 8, 16 and 24 bit integers are widened to INT32 and
+    /// unsigned ones are annotated as such.
+    #[cfg(feature = "parquet")]
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["output_mode", "value_counts", "complex", "split_columns", "range", "show_bytes", "checksum", "entropy", "byte_freq"])]
+    #[cfg_attr(feature = "arrow", clap(conflicts_with = "arrow"))]
+    parquet: Option<String>,
+
+    /// Rows of every row group of the --parquet file, only a row group is kept in memory
+    #[cfg(feature = "parquet")]
+    #[clap(long, value_name = "ROWS", default_value_t = 1 << 20, requires = "parquet", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=1 << 24))]
+    row_group_size: usize,
+
+    /// Compression of the pages of the --parquet file
+    #[cfg(feature = "parquet")]
+    #[clap(long, value_name = "CODEC", value_enum, default_value_t = Codec::None, requires = "parquet")]
+    parquet_compression: Codec,
+
+    /// Sample rate of --wav-out in Hz [default: the rate of --wav input or 44100]
+    #[clap(long, value_name = "HZ", requires = "wav_out", value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..))]
+    sample_rate: Option<u32>,
+
+    /// Prefix the binary output with a parsebin header describing the values
+    #[clap(long, requires = "emit_binary")]
+    with_header: bool,
+
+    /// Print rows as CSV records, using --row-size as the column count
+    #[clap(long, conflicts_with_all = TEXT_ONLY, conflicts_with_all = JSON_ONLY)]
+    csv: bool,
+
+    /// Print floats with N digits after the decimal point
+    #[clap(long, value_name = "N", conflicts_with = "emit_binary")]
+    precision: Option<usize>,
+
+    /// Print floats in scientific notation
+    #[clap(long, conflicts_with = "emit_binary")]
+    sci: bool,
+
+    /// Print the sign, biased exponent and mantissa bits of floats next to their value
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex", "precision", "sci"])]
+    bits: bool,
+
+    /// Print the bit pattern of floats in hex after their value, e.g. -0 (0x80000000)
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex", "bits"])]
+    float_bits: bool,
+
+    /// Print only the bit pattern of floats in hex instead of their value
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex", "bits", "float_bits"])]
+    float_bits_only: bool,
+
+    /// Right-align the values in columns as wide as the widest value of their type
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex", "count_only"])]
+    align: bool,
+
+    /// Width of the columns of --align instead of the width of the type
+    #[clap(long, value_name = "N", requires = "align")]
+    width: Option<usize>,
+
+    /// Print the bytes of the values of a row in file order before the values
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex", "count_only", "count_special", "group"])]
+    show_bytes: bool,
+
+    /// Mark NaN, infinities and subnormal floats, in color on a terminal or else as [NaN]
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex", "count_only"])]
+    highlight_special: bool,
+
+    /// Only print how many NaN, infinities and subnormal floats there are and the offsets of the first K
+    #[clap(long, value_name = "K", num_args = 0..=1, require_equals = true, default_missing_value = "10", conflicts_with_all = ["complex", "highlight_special", "split_columns", "align"])]
+    count_special: Option<usize>,
+
+    /// Leave out NaN and infinite floats, the values after them in a row move up
+    #[clap(long, conflicts_with_all = ["highlight_special", "count_special", "complex"])]
+    skip_nan: bool,
+
+    /// Color the values on a gradient from --color-min to --color-max, NaN and infinities in red
+    #[clap(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Value at the low end of the --color gradient [default: the minimum of the input]
+    ///
+    /// Without --color-min or --color-max they are found in a first pass over
+    /// the input, or for pipes and --follow from the values printed so far.
+    #[clap(long, value_name = "V", allow_hyphen_values = true)]
+    color_min: Option<f64>,
+
+    /// Value at the high end of the --color gradient [default: the maximum of the input]
+    #[clap(long, value_name = "V", allow_hyphen_values = true)]
+    color_max: Option<f64>,
+
+    /// Strip trailing zeros of floats after rounding to --precision
+    #[clap(long, requires = "precision")]
+    trim_zeros: bool,
+
+    /// Print integers as RFC 3339 UTC timestamps counted from the Unix epoch
+    ///
+    /// Values outside of the range of the supported dates are printed as plain
+    /// numbers, with a warning. Floats are printed as usual.
+    #[clap(long, conflicts_with_all = ["stats_only", "histogram", "emit_binary", "complex"])]
+    as_time: bool,
+
+    /// Read integers as fixed-point numbers with this many integer and fraction bits, e.g. 1.15
+    ///
+    /// Values are divided by 2^FRAC_BITS and then treated as f64. The bits
+    /// and the sign bit of signed types must add up to the width of the type.
+    #[clap(long, value_name = "INT.FRAC", value_parser = parse_fixed, conflicts_with_all = ["as_time", "emit_binary", "reencode", "npy", "complex"])]
+    fixed: Option<FixedPoint>,
+
+    /// Print amplitudes in decibels, 20*log10(|v|/REF) with the reference of --db-ref
+    #[clap(long, conflicts_with_all = ["db_power", "as_time", "emit_binary", "reencode", "npy", "complex", "find", "diff"])]
+    db: bool,
+
+    /// Print powers in decibels, 10*log10(v/REF) with the reference of --db-ref
+    #[clap(long, conflicts_with_all = ["as_time", "emit_binary", "reencode", "npy", "complex", "find", "diff"])]
+    db_power: bool,
+
+    /// Reference value of 0 dB for --db and --db-power
+    #[clap(long, value_name = "REF", default_value_t = 1.0, requires = "decibels")]
+    db_ref: f64,
+
+    /// Lowest decibels of --db and --db-power, 0 and values below are clamped to it
+    #[clap(long, value_name = "DB", default_value_t = -120.0, allow_hyphen_values = true, requires = "decibels")]
+    db_floor: f64,
+
+    /// Print bits HIGH to LOW of every integer instead, or the single bit N; may be repeated
+    ///
+    /// The fields of a value are printed as unsigned integers in the order
+    /// given, as columns of the row, e.g. --field 31:24 --field 7:0 prints
+    /// the top and the bottom byte of u32 values.
+    #[clap(long = "field", value_name = "HIGH:LOW", value_parser = parse_bit_field, conflicts_with_all = ["fixed", "as_time", "complex", "ascii", "show_bytes", "emit_binary", "reencode", "npy", "find", "diff"])]
+    bit_fields: Vec<BitField>,
+
+    /// Unit of the integers printed with --as-time
+    #[clap(long, value_enum, default_value_t = TimeUnit::S, requires = "as_time")]
+    time_unit: TimeUnit,
+
+    /// Only show values >= this bound, parsed as TYPE
+    #[clap(long, value_name = "V", allow_hyphen_values = true)]
+    min: Option<String>,
+
+    /// Only show values <= this bound, parsed as TYPE
+    #[clap(long, value_name = "V", allow_hyphen_values = true)]
+    max: Option<String>,
+
+    /// Only show values equal to this value, parsed as TYPE
+    #[clap(long, value_name = "V", allow_hyphen_values = true, conflicts_with_all = ["min", "max"])]
+    eq: Option<String>,
+
+    /// Only show rows with a value passing --min, --max or --eq, whole and with all values
+    #[clap(long, requires = "range", conflicts_with = "complex")]
+    any_column: bool,
+
+    /// Like --any-column, but only show rows of which all values pass
+    #[clap(long, requires = "range", conflicts_with_all = ["complex", "any_column"])]
+    all_columns: bool,
+
+    /// Only show values outside of --min and --max instead
+    ///
+    /// Hidden values are dropped if there is one value per row. With several
+    /// values per row they keep their column and are shown as "-", an empty CSV
+    /// field or null in JSON, and are left out of --stats.
+    #[clap(long, requires = "range")]
+    invert: bool,
+
+    /// Format of the values instead of plain text rows
+    #[clap(long, value_enum)]
+    output_format: Option<OutputFormat>,
+
+    /// Multiply the row index of --output-format gnuplot by FACTOR, e.g. the sample period
+    #[clap(long, value_name = "FACTOR", allow_hyphen_values = true)]
+    x_scale: Option<f64>,
+
+    /// Add OFF to the scaled row index of --output-format gnuplot
+    #[clap(long, value_name = "OFF", allow_hyphen_values = true)]
+    x_offset: Option<f64>,
+
+    /// Write --output-format msgpack or cbor as one array instead of a sequence
+    #[clap(long)]
+    as_array: bool,
+
+    /// Write --output-format msgpack or cbor even if stdout is a terminal
+    #[clap(long)]
+    force: bool,
+
+    /// Field delimiter used by --csv
+    #[clap(long, default_value_t = ',', requires = "csv_output")]
+    delimiter: char,
+
+    /// Separator between the values of a row, accepts \t, \n, \r, \0 and \\ escapes [default: " "]
+    #[clap(long, value_name = "STR", value_parser = parse_separator, conflicts_with_all = ["stats_only", "emit_binary"])]
+    field_sep: Option<String>,
+
+    /// Terminator of every row including the last, accepts the escapes of --field-sep [default: "\n"]
+    #[clap(long, value_name = "STR", value_parser = parse_separator, conflicts_with_all = ["stats_only", "emit_binary"])]
+    record_sep: Option<String>,
+
+    /// Comma separated column names printed as the first CSV record
+    #[clap(long, value_delimiter = ',', requires = "named_columns")]
+    header: Vec<String>,
+
+    /// Comma separated column names printed as a line above the values, implies --align
+    #[clap(long, value_delimiter = ',', conflicts_with_all = ["stats_only", "histogram", "plot", "crossings", "emit_binary", "reencode", "npy", "complex", "count_only", "show_bytes", "split_columns"])]
+    labels: Vec<String>,
+
+    /// Print the --labels line again after the empty line of every --group
+    #[clap(long, requires_all = ["labels", "group"])]
+    repeat_labels: bool,
+
+    /// Print col0,col1,... as the first CSV record
+    #[clap(long, requires = "csv_output", conflicts_with_all = ["header", "split_columns"])]
+    auto_header: bool,
+
+    /// Write the output to this file instead of stdout, it's created or truncated
+    #[clap(short = 'O', long, visible_alias = "raw-out", value_name = "PATH")]
+    output: Option<String>,
+
+    /// Spread the CSV columns over several files with this many value columns each
+    #[clap(long, requires_all = ["csv_output", "output_template"], conflicts_with = "header", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    split_columns: Option<usize>,
+
+    /// Path of the files written by --split-columns, `{}` is replaced by the part number
+    #[clap(long, requires = "split_columns")]
+    output_template: Option<String>,
+
+    /// Print the values as a JSON array, or an array of rows if --row-size > 1
+    #[clap(long, conflicts_with_all = TEXT_ONLY, conflicts_with_all = CSV_ONLY)]
+    json: bool,
+
+    /// Quote 64 bit integers outside the JavaScript safe integer range in JSON output
+    #[clap(long, requires = "json_output")]
+    json_bigint_as_string: bool,
+
+    /// How NaN and infinities are written in JSON output [default: null]
+    #[clap(long, value_enum, requires = "json_output")]
+    nan_as: Option<NanAs>,
+
+    /// Prefix every row with the byte offset of its first value, in hex unless --addr=dec
+    ///
+    /// The offset is the position in the input, including --offset and skipped
+    /// headers, padded to the width of the largest offset of the file. CSV rows
+    /// get an offset column, with a 0x prefix for hex, and JSON rows become
+    /// objects with a decimal "offset" key.
+    #[clap(long, visible_alias = "show-offset", value_name = "BASE", num_args = 0..=1, require_equals = true, default_missing_value = "hex", conflicts_with_all = ["stats_only", "emit_binary", "split_columns"])]
+    addr: Option<AddressStyle>,
+
+    /// Append the ASCII representation of each row, only for u8 and i8
+    #[clap(long, conflicts_with_all = ["stats_only", "emit_binary"])]
+    ascii: bool,
+
+    /// Print an empty line after every N rows
+    #[clap(long, conflicts_with_all = ["stats_only", "emit_binary"], value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    group: Option<u64>,
+
+    /// Decompress the files, auto detects gzip and zstd by extension and magic by the magic bytes too
+    #[clap(long, value_enum, default_value_t = Compression::Auto)]
+    compression: Compression,
+
+    /// Print a ==> FILE <== line before the values of each file when given several
+    #[clap(long, conflicts_with_all = ["stats_only", "emit_binary", "split_columns"])]
+    headers: bool,
+
+    /// Don't print warnings, e.g. about ignored trailing bytes
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// Print a completion script for SHELL and exit
+    #[clap(long, value_name = "SHELL", value_enum, hide = true)]
+    completions: Option<Shell>,
+
+    /// Files to parse, several files are parsed as one stream in the given order
+    #[clap(required_unless_present = "completions")]
+    files: Vec<String>,
 }
 
-fn main() {
-    let args = Opt::parse();
+struct OutputState {
+    current_row: usize,
+    stats: Option<Vec<Stats>>,
+    histograms: Option<Vec<Histogram>>,
+    value_counts: Option<Vec<ValueCounts>>,
+    plots: Option<Vec<Plot>>,
+    crossings: Option<Crossings>,
+    split: Option<SplitWriter>,
+    npy: Option<NpyWriter>,
+    #[cfg(feature = "arrow")]
+    arrow: Option<ArrowWriter>,
+    #[cfg(feature = "parquet")]
+    parquet: Option<ParquetWriter>,
+    wav: Option<WavWriter>,
+    serial: Option<SerialWriter>,
+    json_started: bool,
+    // Real part of a --complex value waiting for its imaginary part.
+    complex_re: Option<Value>,
+    address: u64,
+    address_width: usize,
+    element_size: u64,
+    // Bounds of --min and --max for every field, empty without a filter.
+    filter: Vec<(Option<Value>, Option<Value>)>,
+    ascii: String,
+    // Width of every column of a row with --align, empty otherwise.
+    widths: Vec<usize>,
+    color: Option<ColorScale>,
+    // Whether --highlight-special marks values in color instead of brackets.
+    highlight_color: bool,
+    special: Option<SpecialCounts>,
+    // Address of the first LEB128 value and the encoded length of every one for --verbose.
+    leb128_lengths: Option<(u64, Vec<usize>)>,
+    // Offset of the field that is output within its record.
+    field_start: u64,
+    // Records parsed so far, for --every and --take.
+    records: u64,
+    // Previous value of every field and channel for --delta, empty otherwise.
+    previous: Vec<Option<Value>>,
+    delta_column: usize,
+    // Sum and count of the values of every field and channel so far for
+    // --cumsum and --cummean, empty otherwise.
+    sums: Vec<Option<(Value, u64)>>,
+    sum_column: usize,
+    // Hex bytes and text of the values of the current row for --show-bytes.
+    raw: String,
+    line: Vec<u8>,
+    // Width of the hex bytes of a complete row for --show-bytes.
+    raw_width: usize,
+    row_address: u64,
+    completed_rows: u64,
+    // Values decoded for --count-only.
+    values: u64,
+    // Values or rows passing the filter for --count-only.
+    matches: u64,
+    // Values with their bytes, address and field offset of the current row of
+    // --any-column and --all-columns, output once it is known to pass.
+    pending: Vec<(Value, Vec<u8>, u64, u64)>,
+    // NaN and infinite values left out by --skip-nan.
+    skipped_nan: u64,
+    // Values clamped to --db-floor.
+    clamped_db: u64,
+}
 
-    let file_size = match std::fs::metadata(&args.file) {
-        Ok(meta) => {
-            if meta.is_dir() {
-                eprintln!("File is a directory: {}", args.file);
-                std::process::exit(1);
-            } else {
-                meta.file_size()
-            }
-        }
+fn record_sep(args: &Opt) -> &str {
+    args.record_sep.as_deref().unwrap_or("\n")
+}
+
+fn finish_row(out: &mut impl Write, args: &Opt, state: &mut OutputState) -> std::io::Result<()> {
+    if args.show_bytes {
+        write_address(out, args, state, state.row_address)?;
+        write!(out, "{:<width$} -> ", state.raw, width = state.raw_width)?;
+        out.write_all(&state.line)?;
+        state.raw.clear();
+        state.line.clear();
+    }
+    if args.ascii {
+        write!(out, "  |{}|", state.ascii)?;
+        state.ascii.clear();
+    }
+    write!(out, "{}", record_sep(args))?;
+    state.completed_rows += 1;
+    // Followed output is read while it's written, so don't hold rows back.
+    if args.follow {
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// JSON lines put every row on a line of its own instead of into one array.
+fn json_lines(args: &Opt) -> bool {
+    args.output_format == Some(OutputFormat::Jsonl)
+}
+
+/// Encoding of --output-format msgpack and cbor.
+fn serial_encoding(args: &Opt) -> Option<Encoding> {
+    match args.output_format {
+        Some(OutputFormat::Msgpack) => Some(Encoding::Msgpack),
+        Some(OutputFormat::Cbor) => Some(Encoding::Cbor),
+        _ => None,
+    }
+}
+
+/// Gnuplot data starts every row with its index.
+fn gnuplot(args: &Opt) -> bool {
+    args.output_format == Some(OutputFormat::Gnuplot)
+}
+
+/// Rows are arrays unless the output is an array of single values.
+fn json_row_is_array(args: &Opt) -> bool {
+    args.row_size > 1 || json_lines(args)
+}
+
+fn finish_json_row(out: &mut impl Write, args: &Opt) -> std::io::Result<()> {
+    if json_row_is_array(args) {
+        write!(out, "]")?;
+    }
+    if args.addr.is_some() {
+        write!(out, "}}")?;
+    }
+    if json_lines(args) {
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Unwraps the result of writing the output or exits with the error.
+///
+/// A closed pipe, e.g. `parsebin f32 big.bin | head`, ends the program quietly
+/// with status 0 like other command line tools.
+fn exit_on_error<T>(result: std::io::Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => std::process::exit(0),
         Err(e) => {
             eprintln!("Error: {}", e);
             std::process::exit(1);
         }
-    };
-    if args.offset >= file_size {
-        eprintln!("Offset is out of range: {} >= {}", args.offset, file_size);
-        std::process::exit(1);
     }
+}
 
-    let buffered_file_stream = std::fs::File::open(&args.file).unwrap();
-    let mut file_stream = std::io::BufReader::new(buffered_file_stream);
-    // If offset is different from 0, seek to the offset.
-    if args.offset > 0 {
-        match file_stream.seek_relative(args.offset as i64) {
-            Ok(_) => (),
-            Err(e) => {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
+/// Quotes a CSV field as in RFC 4180 if it contains the delimiter, a quote or a line break.
+fn csv_field(field: &str, delimiter: char) -> String {
+    match field.contains([delimiter, '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Writes what precedes a field of text output, the empty line between groups
+/// of rows and the address at the start of a row or else the delimiter.
+fn start_field(
+    out: &mut impl Write,
+    args: &Opt,
+    state: &OutputState,
+    address: u64,
+) -> std::io::Result<()> {
+    if let Some(group) = args.group {
+        if state.current_row == 0
+            && state.completed_rows > 0
+            && state.completed_rows.is_multiple_of(group)
+        {
+            write!(out, "{}", record_sep(args))?;
+            if args.repeat_labels {
+                write_labels(out, args, state)?;
             }
         }
     }
-    // While number is not 0, read the file and parse the data according to the type.
-    let bytes_to_read = match args.number {
-        MAX => (file_size - args.offset) as i64,
-        a => std::cmp::min(a * args.parse_type.size_of(), file_size as i64),
-    };
-    // Read the file until the end of the file or the number of bytes to read.
-    let mut buffer = vec![0; 4096 as usize];
-    let mut previous_unread = 0;
-    let mut bytes_read = 0;
-    while bytes_read < bytes_to_read {
-        let bytes_to_read_now =
-            std::cmp::min(buffer.len() as i64, bytes_to_read - bytes_read) as usize;
-        match file_stream.read(&mut buffer[previous_unread..bytes_to_read_now]) {
-            Ok(n) => {
-                previous_unread = 0;
-                if n == 0 {
-                    break;
-                }
-                bytes_read += n as i64;
-                let mut i = 0;
-                while i < n {
-                    let size = args.parse_type.size_of() as usize;
-                    if i + size > n {
-                        for j in 0..(n - i) {
-                            buffer[j] = buffer[i + j];
-                        }
-                        previous_unread = n - i;
-                        break;
-                    }
-                    match args.parse_type {
-                        ParseType::U8 => {
-                            let value = buffer[i] as u8;
-                            println!("{}", value);
-                            i += 1;
-                        }
-                        ParseType::U16 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => {
-                                    u16::from_le_bytes([buffer[i], buffer[i + 1]])
-                                }
-                                ByteOrder::BigEndian => {
-                                    u16::from_be_bytes([buffer[i], buffer[i + 1]])
-                                }
-                            };
-                            println!("{}", value);
-                            i += 2;
-                        }
-                        ParseType::U32 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => u32::from_le_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                ]),
-                                ByteOrder::BigEndian => u32::from_be_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                ]),
-                            };
-                            println!("{}", value);
-                            i += 4;
-                        }
-                        ParseType::U64 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => u64::from_le_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                    buffer[i + 4],
-                                    buffer[i + 5],
-                                    buffer[i + 6],
-                                    buffer[i + 7],
-                                ]),
-                                ByteOrder::BigEndian => u64::from_be_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                    buffer[i + 4],
-                                    buffer[i + 5],
-                                    buffer[i + 6],
-                                    buffer[i + 7],
-                                ]),
-                            };
-                            println!("{}", value);
-                            i += 8;
-                        }
-                        ParseType::I8 => {
-                            let value = buffer[i] as i8;
-                            println!("{}", value);
-                            i += 1;
-                        }
-                        ParseType::I16 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => {
-                                    i16::from_le_bytes([buffer[i], buffer[i + 1]])
-                                }
-                                ByteOrder::BigEndian => {
-                                    i16::from_be_bytes([buffer[i], buffer[i + 1]])
-                                }
-                            };
-                            println!("{}", value);
-                        }
-                        ParseType::I32 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => i32::from_le_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                ]),
-                                ByteOrder::BigEndian => i32::from_be_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                ]),
-                            };
-                            println!("{}", value);
-                            i += 4;
-                        }
-                        ParseType::I64 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => i64::from_le_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                    buffer[i + 4],
-                                    buffer[i + 5],
-                                    buffer[i + 6],
-                                    buffer[i + 7],
-                                ]),
-                                ByteOrder::BigEndian => i64::from_be_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                    buffer[i + 4],
-                                    buffer[i + 5],
-                                    buffer[i + 6],
-                                    buffer[i + 7],
-                                ]),
-                            };
-                            println!("{}", value);
-                            i += 8;
-                        }
-                        ParseType::F32 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => f32::from_le_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                ]),
-                                ByteOrder::BigEndian => f32::from_be_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                ]),
-                            };
-                            println!("{}", value);
-                            i += 4;
-                        }
-                        ParseType::F64 => {
-                            let value = match args.byte_order {
-                                ByteOrder::LittleEndian => f64::from_le_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                    buffer[i + 4],
-                                    buffer[i + 5],
-                                    buffer[i + 6],
-                                    buffer[i + 7],
-                                ]),
-                                ByteOrder::BigEndian => f64::from_be_bytes([
-                                    buffer[i],
-                                    buffer[i + 1],
-                                    buffer[i + 2],
-                                    buffer[i + 3],
-                                    buffer[i + 4],
-                                    buffer[i + 5],
-                                    buffer[i + 6],
-                                    buffer[i + 7],
-                                ]),
-                            };
-                            println!("{}", value);
-                            i += 8;
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
+    if state.current_row > 0 {
+        match args.csv {
+            true => write!(out, "{}", args.delimiter),
+            false => write!(out, "{}", args.field_sep.as_deref().unwrap_or(" ")),
+        }
+    } else {
+        write_address(out, args, state, address)
+    }
+}
+
+/// Writes the line of --labels, each right-aligned to its column.
+fn write_labels(out: &mut impl Write, args: &Opt, state: &OutputState) -> std::io::Result<()> {
+    // As wide as the offset of --addr and its ": ".
+    if args.addr.is_some() {
+        write!(out, "{:width$}", "", width = state.address_width + 2)?;
+    }
+    for (column, label) in args.labels.iter().enumerate() {
+        if column > 0 {
+            write!(out, "{}", args.field_sep.as_deref().unwrap_or(" "))?;
+        }
+        let width = state.widths.get(column).copied().unwrap_or(0);
+        write!(out, "{:>width$}", label, width = width)?;
+    }
+    write!(out, "{}", record_sep(args))
+}
+
+/// Writes the offset of --addr at the start of a row.
+fn write_address(
+    out: &mut impl Write,
+    args: &Opt,
+    state: &OutputState,
+    address: u64,
+) -> std::io::Result<()> {
+    match (args.addr, args.csv) {
+        (Some(AddressStyle::Hex), false) => {
+            write!(out, "{:0width$x}: ", address, width = state.address_width)
+        }
+        (Some(AddressStyle::Dec), false) => {
+            write!(out, "{:0width$}: ", address, width = state.address_width)
+        }
+        (Some(AddressStyle::Hex), true) => write!(out, "0x{:x}{}", address, args.delimiter),
+        (Some(AddressStyle::Dec), true) => write!(out, "{}{}", address, args.delimiter),
+        (None, _) if gnuplot(args) => {
+            let index = state.completed_rows as f64;
+            match (args.x_scale, args.x_offset) {
+                (None, None) => write!(out, "{} ", state.completed_rows),
+                (scale, offset) => write!(
+                    out,
+                    "{} ",
+                    index * scale.unwrap_or(1.0) + offset.unwrap_or(0.0)
+                ),
             }
         }
+        (None, _) => Ok(()),
+    }
+}
+
+/// Formats a --complex value as (re+imj), or as magnitude and phase with --polar.
+fn format_complex(re: Value, im: Value, args: &Opt) -> String {
+    if args.polar {
+        let (re, im) = (re.as_f64(), im.as_f64());
+        let phase = match args.degrees {
+            true => im.atan2(re).to_degrees(),
+            false => im.atan2(re),
+        };
+        let separator = match args.csv {
+            true => args.delimiter,
+            false => ' ',
+        };
+        return format!(
+            "{}{}{}",
+            args.format.float(re.hypot(im)),
+            separator,
+            args.format.float(phase)
+        );
+    }
+    let im = args.format.value(im);
+    match im.starts_with('-') {
+        true => format!("({}{}j)", args.format.value(re), im),
+        false => format!("({}+{}j)", args.format.value(re), im),
+    }
+}
+
+fn passes_filter(value: Value, column: usize, args: &Opt, state: &OutputState) -> bool {
+    if state.filter.is_empty() {
+        return true;
+    }
+    let (min, max) = &state.filter[column % state.filter.len()];
+    let inside = min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max);
+    inside != args.invert
+}
+
+fn output(
+    value: Value,
+    raw: &[u8],
+    out: &mut impl Write,
+    args: &Opt,
+    state: &mut OutputState,
+) -> std::io::Result<()> {
+    let shown = passes_filter(value, state.current_row, args, state);
+    if args.count_only {
+        state.values += 1;
+        state.matches += shown as u64;
+        return Ok(());
+    }
+    if !shown && (args.row_size == 1 || args.emit_binary || state.serial.is_some()) {
+        return Ok(());
+    }
+    if args.emit_binary {
+        let byte_order = args.reencode.as_ref().unwrap_or(&args.byte_order);
+        return out.write_all(&value.to_bytes(byte_order));
+    }
+    if let Some(serial) = &mut state.serial {
+        return serial.push(value, out);
+    }
+    if let Some(npy) = &mut state.npy {
+        let value = match value {
+            Value::U24(v) => Value::U32(v),
+            Value::I24(v) => Value::I32(v),
+            v => v,
+        };
+        if let Err(e) = npy.write(&value.to_bytes(&args.byte_order)) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    #[cfg(feature = "arrow")]
+    if let Some(arrow) = &mut state.arrow {
+        if let Err(e) = arrow.push(value) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(parquet) = &mut state.parquet {
+        if let Err(e) = parquet.push(value) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if let Some(wav) = &mut state.wav {
+        if let Err(e) = wav.write(value) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(split) = &mut state.split {
+        let formatted = match shown {
+            true => args.format.value(value),
+            false => String::new(),
+        };
+        if let Err(e) = split.push(state.current_row, formatted) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if let Some(special) = &mut state.special {
+        if shown {
+            special.push(value, state.address + state.field_start);
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if let Some(crossings) = &mut state.crossings {
+        if let Some((index, direction, previous)) = crossings.push(value) {
+            let address = state.address + state.field_start;
+            writeln!(
+                out,
+                "{} {} {} {} {}",
+                index,
+                address,
+                direction,
+                args.format.value(previous),
+                args.format.value(value)
+            )?;
+        }
+        return Ok(());
+    }
+    if let Some(plots) = &mut state.plots {
+        if shown {
+            plots[state.current_row].push(value.as_f64());
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if let Some(histograms) = &mut state.histograms {
+        if shown {
+            histograms[state.current_row].push(value);
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if let Some(counts) = &mut state.value_counts {
+        if shown {
+            let value = match (args.round, value) {
+                (Some(places), Value::F32(_) | Value::F64(_)) => {
+                    let scale = 10f64.powi(places as i32);
+                    Value::F64((value.as_f64() * scale).round() / scale)
+                }
+                _ => value,
+            };
+            if let Err(max) = counts[state.current_row].push(value) {
+                eprintln!(
+                    "Error: more than {} distinct values in column {}, raise --max-distinct",
+                    max, state.current_row
+                );
+                std::process::exit(1);
+            }
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if args.complex {
+        let re = match state.complex_re.take() {
+            Some(re) => re,
+            None => {
+                state.complex_re = Some(value);
+                return Ok(());
+            }
+        };
+        // The address of the sample is the one of its real part.
+        start_field(out, args, state, state.address - state.element_size)?;
+        write!(out, "{}", format_complex(re, value, args))?;
+        if state.current_row + 1 == args.row_size {
+            finish_row(out, args, state)?;
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if args.json {
+        if state.current_row == 0 {
+            if state.json_started && !json_lines(args) {
+                writeln!(out, ",")?;
+            }
+            state.json_started = true;
+            if args.addr.is_some() {
+                let key = match json_row_is_array(args) {
+                    true => "values",
+                    false => "value",
+                };
+                write!(out, "{{\"offset\":{},\"{}\":", state.address, key)?;
+            }
+            if json_row_is_array(args) {
+                write!(out, "[")?;
+            }
+        } else {
+            write!(out, ",")?;
+        }
+        match shown {
+            true => write!(
+                out,
+                "{}",
+                json::format_value(
+                    value,
+                    args.json_bigint_as_string,
+                    args.nan_as.unwrap_or(NanAs::Null)
+                )
+            )?,
+            false => write!(out, "null")?,
+        }
+        if state.current_row + 1 == args.row_size {
+            finish_json_row(out, args)?;
+        }
+        state.current_row = (state.current_row + 1) % args.row_size;
+        return Ok(());
+    }
+    if let Some(stats) = &mut state.stats {
+        if shown {
+            stats[state.current_row].push(value);
+        }
+    }
+    match args.stats_only {
+        true => (),
+        false if args.show_bytes => {
+            if state.current_row == 0 {
+                state.row_address = state.address;
+            } else {
+                state.raw.push_str("  ");
+            }
+            let hex: Vec<String> = raw.iter().map(|byte| format!("{:02x}", byte)).collect();
+            state.raw.push_str(&hex.join(" "));
+            // The values go after the bytes, so they are kept until the row is complete.
+            let mut line = std::mem::take(&mut state.line);
+            if state.current_row > 0 {
+                start_field(&mut line, args, state, state.address)?;
+            }
+            write_value(&mut line, value, shown, args, state)?;
+            state.line = line;
+            finish_value(value, shown, out, args, state)?;
+        }
+        false => {
+            start_field(out, args, state, state.address)?;
+            write_value(out, value, shown, args, state)?;
+            if let Some((start, lengths)) = &state.leb128_lengths {
+                // Values are widened to 8 bytes, which gives the index of the value.
+                let index = (state.address - start) / 8;
+                match lengths[index as usize] {
+                    1 => write!(out, " (1 byte)")?,
+                    length => write!(out, " ({} bytes)", length)?,
+                }
+            }
+            finish_value(value, shown, out, args, state)?;
+        }
+    }
+    state.current_row = (state.current_row + 1) % args.row_size;
+    Ok(())
+}
+
+/// Writes a value of text output, or a placeholder if it's hidden by a filter.
+fn write_value(
+    out: &mut impl Write,
+    value: Value,
+    shown: bool,
+    args: &Opt,
+    state: &mut OutputState,
+) -> std::io::Result<()> {
+    match (shown, args.format.is_plain()) {
+        _ if args.align || state.color.is_some() || args.highlight_special => {
+            let text = match shown {
+                true => args.format.value(value),
+                false if args.csv => String::new(),
+                false => "-".to_string(),
+            };
+            let width = state.widths.get(state.current_row).copied().unwrap_or(0);
+            let special = match (args.highlight_special, shown) {
+                (true, true) => special::classify(value),
+                _ => None,
+            };
+            match (&mut state.color, special) {
+                (_, Some(special)) if state.highlight_color => write!(
+                    out,
+                    "{}{}{}",
+                    match special {
+                        Special::Subnormal => color::SUBNORMAL,
+                        _ => color::SPECIAL,
+                    },
+                    format::align(&text, width, false),
+                    color::RESET
+                )?,
+                (_, Some(_)) => write!(out, "{}", format::align(&text, width, true))?,
+                (Some(color), None) if shown => write!(
+                    out,
+                    "{}{}{}",
+                    color.escape(value.as_f64()),
+                    format::align(&text, width, false),
+                    color::RESET
+                )?,
+                (_, None) => write!(out, "{}", format::align(&text, width, false))?,
+            }
+        }
+        (false, _) if args.csv => (),
+        (false, _) => write!(out, "-")?,
+        (true, false) => write!(out, "{}", args.format.value(value))?,
+        (true, true) => write!(out, "{}", value)?,
+    }
+    Ok(())
+}
+
+/// Adds the value to the ASCII gutter and ends the row after its last value.
+fn finish_value(
+    value: Value,
+    shown: bool,
+    out: &mut impl Write,
+    args: &Opt,
+    state: &mut OutputState,
+) -> std::io::Result<()> {
+    if args.ascii {
+        let byte = match value {
+            Value::U8(v) => v,
+            Value::I8(v) => v as u8,
+            _ => unreachable!("--ascii is only allowed for u8 and i8"),
+        };
+        state.ascii.push(match byte {
+            _ if !shown => ' ',
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        });
+    }
+    if state.current_row + 1 == args.row_size {
+        finish_row(out, args, state)?;
+    }
+    Ok(())
+}
+
+/// Formats a value computed from values of `field`, like a histogram bin edge.
+fn format_summary(value: f64, field: &ParseType, args: &Opt) -> String {
+    match output_type(field, args) {
+        ParseType::F32 => args.format.value(Value::F32(value as f32)),
+        ParseType::F64 => args.format.value(Value::F64(value)),
+        _ => value.to_string(),
+    }
+}
+
+/// Bytes to skip of `available` ones so --last values of `element_span`
+/// bytes are left, keeping whole frames of channels and complex values.
+fn skipped_before_last(
+    available: u64,
+    last: u64,
+    element_span: i64,
+    frame_stride: i64,
+    args: &Opt,
+) -> u64 {
+    let mut elements = (available + frame_stride as u64) / element_span as u64;
+    if let (Some(channels), None) = (args.channels, args.channel) {
+        elements -= elements % channels;
+    }
+    if args.complex {
+        elements -= elements % 2;
+    }
+    match last < elements {
+        true => (elements - last) * element_span as u64,
+        false => 0,
+    }
+}
+
+/// Reads up to `length` bytes of `reader` into a ring buffer of the last
+/// `keep`, for --last on input whose size is only known at its end. Returns
+/// them with the number of bytes read.
+fn read_tail(
+    reader: &mut impl Read,
+    length: u64,
+    keep: usize,
+    cancel: &CancellationToken,
+) -> std::io::Result<(Vec<u8>, u64)> {
+    let mut ring = std::collections::VecDeque::new();
+    let mut chunk = vec![0; 1 << 16];
+    let mut total = 0;
+    while total < length && !cancel.is_cancelled() {
+        let wanted = std::cmp::min(chunk.len() as u64, length - total) as usize;
+        let n = match reader.read(&mut chunk[..wanted]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        total += n as u64;
+        ring.extend(&chunk[..n]);
+        if ring.len() > keep {
+            ring.drain(..ring.len() - keep);
+        }
+    }
+    Ok((ring.into(), total))
+}
+
+/// Prints the distinct values of every column of --value-counts with their
+/// counts, as a table or as CSV or JSON rows.
+fn print_value_counts(
+    out: &mut impl Write,
+    counts: &[ValueCounts],
+    args: &Opt,
+) -> std::io::Result<()> {
+    let columns = counts.len() > 1;
+    if args.csv {
+        match columns {
+            true => writeln!(out, "column,value,count")?,
+            false => writeln!(out, "value,count")?,
+        }
+    } else if args.json && !json_lines(args) {
+        writeln!(out, "[")?;
+    }
+    let mut first = true;
+    for (column, counts) in counts.iter().enumerate() {
+        let sorted = counts.sorted(args.by_value);
+        let shown = &sorted[..args.top.unwrap_or(usize::MAX).min(sorted.len())];
+        if args.csv {
+            for (value, count) in shown {
+                if columns {
+                    write!(out, "{},", column)?;
+                }
+                writeln!(out, "{},{}", args.format.value(*value), count)?;
+            }
+            continue;
+        }
+        if args.json {
+            for (value, count) in shown {
+                let value = json::format_value(
+                    *value,
+                    args.json_bigint_as_string,
+                    args.nan_as.unwrap_or(NanAs::Null),
+                );
+                if !first && !json_lines(args) {
+                    writeln!(out, ",")?;
+                }
+                if columns {
+                    write!(out, "{{\"column\":{},", column)?;
+                } else {
+                    write!(out, "{{")?;
+                }
+                write!(out, "\"value\":{},\"count\":{}}}", value, count)?;
+                if json_lines(args) {
+                    writeln!(out)?;
+                }
+                first = false;
+            }
+            continue;
+        }
+        if columns {
+            writeln!(out, "column {}:", column)?;
+        }
+        let values: Vec<String> = shown
+            .iter()
+            .map(|(value, _)| args.format.value(*value))
+            .collect();
+        let width = values.iter().map(|v| v.len()).max().unwrap_or(0).max(5);
+        let count_width = counts.total().to_string().len().max(5);
+        writeln!(out, "{:>width$} {:>count_width$} percent", "value", "count")?;
+        for (value, (_, count)) in values.iter().zip(shown) {
+            let percent = *count as f64 * 100.0 / counts.total() as f64;
+            writeln!(
+                out,
+                "{:>width$} {:>count_width$} {:>6.2}%",
+                value, count, percent
+            )?;
+        }
+        writeln!(
+            out,
+            "total {} values, {} distinct",
+            counts.total(),
+            counts.distinct()
+        )?;
+    }
+    if args.json && !json_lines(args) {
+        if !first {
+            writeln!(out)?;
+        }
+        writeln!(out, "]")?;
+    }
+    Ok(())
+}
+
+/// Prints the statistics of every column, labeled if there are several.
+fn print_stats(
+    out: &mut impl Write,
+    stats: &[Stats],
+    fields: &[ParseType],
+    args: &Opt,
+) -> std::io::Result<()> {
+    for (column, column_stats) in stats.iter().enumerate() {
+        if args.row_size > 1 {
+            writeln!(out, "column {}:", column)?;
+        }
+        let float = output_type(&fields[column % fields.len()], args).is_float();
+        column_stats.print(out, float)?;
+    }
+    Ok(())
+}
+
+/// Finds the minimum and maximum finite value in `reader`, laid out like the
+/// values that are printed, or `None` if there are none.
+fn value_range(
+    reader: impl Read,
+    fields: &[ParseType],
+    args: &Opt,
+) -> std::io::Result<Option<(f64, f64)>> {
+    let mut parser = record_parser(reader, fields, args);
+    let mut range: Option<(f64, f64)> = None;
+    while parser.read_record()? {
+        for (value, _) in parser.record() {
+            let value = fixed_point(value, args).as_f64();
+            if value.is_finite() {
+                range = match range {
+                    Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+                    None => Some((value, value)),
+                };
+            }
+        }
+    }
+    Ok(range)
+}
+
+/// Parses the values of `reader` into `state` like the pass that prints
+/// them, but without output, for the bounds of the bins of --histogram.
+fn measure(
+    reader: &mut impl Read,
+    fields: &[ParseType],
+    leb128: &Option<ParseType>,
+    args: &Opt,
+    state: &mut OutputState,
+    buffer_len: usize,
+    cancel: &CancellationToken,
+) -> std::io::Result<()> {
+    let mut out = std::io::sink();
+    let mut parse = |bytes: &[u8]| {
+        let mut parser = record_parser(bytes, fields, args);
+        parse_records(&mut parser, &mut out, args, state)?;
+        Ok(!cancel.is_cancelled())
+    };
+    match (args.bit_width, leb128) {
+        (Some(width), _) => {
+            let bit_order = args
+                .bit_order
+                .unwrap_or_else(|| BitOrder::of(&args.byte_order));
+            let parse_type = &fields[0];
+            let number = args.number as u64;
+            bits::unpack(
+                reader,
+                width,
+                bit_order,
+                parse_type,
+                &args.byte_order,
+                number,
+                parse,
+            )?;
+        }
+        (None, Some(parse_type)) => {
+            let number = args.number as u64;
+            leb128::unpack(reader, parse_type, &args.byte_order, number, |bytes, _| {
+                parse(bytes)
+            })?;
+        }
+        (None, None) => {
+            let mut parser = record_parser(reader, fields, args)
+                .with_capacity(buffer_len)
+                .with_cancellation(cancel.clone());
+            parse_records(&mut parser, &mut out, args, state)?;
+        }
+    }
+    finish_filtered_row(&mut out, args, state)
+}
+
+/// Parser of the records of `fields` in `reader`, with the bytes of
+/// --skip-before and --stride around each.
+fn record_parser<R: Read>(reader: R, fields: &[ParseType], args: &Opt) -> BinParser<R> {
+    BinParser::new(reader, fields[0].clone(), args.byte_order.clone(), 0, None).with_records(
+        fields.to_vec(),
+        args.skip_before,
+        args.stride,
+    )
+}
+
+/// Result of --diff.
+#[derive(Debug, Default)]
+struct DiffSummary {
+    differences: u64,
+    values: u64,
+    /// Offset of the first difference.
+    first: Option<u64>,
+    /// Largest absolute difference of values that are numbers.
+    max_difference: Option<f64>,
+    /// Whether `ours` ended first, if one input ended before the other.
+    ours_shorter: Option<bool>,
+}
+
+/// Whether two values are the same for --diff, NaN is equal to NaN and
+/// floats may differ by --tolerance or relatively by --rel-tolerance.
+fn same_value(a: Value, b: Value, args: &Opt) -> bool {
+    if a == b || (a.as_f64().is_nan() && b.as_f64().is_nan()) {
+        return true;
+    }
+    let (a, b) = (a.as_f64(), b.as_f64());
+    let difference = (a - b).abs();
+    args.tolerance
+        .is_some_and(|tolerance| difference <= tolerance)
+        || args
+            .rel_tolerance
+            .is_some_and(|tolerance| difference <= tolerance * a.abs().max(b.abs()))
+}
+
+/// Prints the values of `ours` that differ from the ones of `theirs` for
+/// --diff with the index of their element and offset, `address` is the
+/// offset of the first element of `ours` and `their_address` of `theirs`.
+fn diff_values(
+    ours: impl Read,
+    theirs: impl Read,
+    fields: &[ParseType],
+    args: &Opt,
+    (mut address, mut their_address): (u64, u64),
+    out: &mut impl Write,
+) -> std::io::Result<DiffSummary> {
+    let size: u64 = fields.iter().map(|field| field.size_of() as u64).sum();
+    let span = args.skip_before + size + args.stride;
+    let mut ours = record_parser(ours, fields, args);
+    let mut theirs = record_parser(theirs, fields, args);
+    let mut summary = DiffSummary::default();
+    let mut index = 0;
+    let shifted = their_address != address;
+    loop {
+        match (ours.read_record()?, theirs.read_record()?) {
+            (true, true) => (),
+            (false, false) => return Ok(summary),
+            (ours_complete, _) => {
+                summary.ours_shorter = Some(!ours_complete);
+                return Ok(summary);
+            }
+        }
+        let mut start = args.skip_before;
+        for ((our_value, bytes), (their_value, _)) in ours.record().zip(theirs.record()) {
+            let (our_value, their_value) =
+                (fixed_point(our_value, args), fixed_point(their_value, args));
+            if !same_value(our_value, their_value, args) {
+                let offset = address + start;
+                match shifted {
+                    // Both offsets if they differ by --diff-offset.
+                    true => write!(out, "{} {} {}: ", index, offset, their_address + start)?,
+                    false => write!(out, "{} {}: ", index, offset)?,
+                }
+                writeln!(
+                    out,
+                    "{} != {}",
+                    args.format.value(our_value),
+                    args.format.value(their_value)
+                )?;
+                let difference = match (our_value.as_i128(), their_value.as_i128()) {
+                    (Some(a), Some(b)) => (a - b).unsigned_abs() as f64,
+                    _ => (our_value.as_f64() - their_value.as_f64()).abs(),
+                };
+                if !difference.is_nan() {
+                    summary.max_difference = Some(
+                        summary
+                            .max_difference
+                            .map_or(difference, |max| max.max(difference)),
+                    );
+                }
+                summary.first = summary.first.or(Some(offset));
+                summary.differences += 1;
+            }
+            start += bytes.len() as u64;
+            summary.values += 1;
+        }
+        address += span;
+        their_address += span;
+        index += 1;
+    }
+}
+
+/// Outcome of --expect.
+enum Expectation {
+    /// All values listed match, how many and whether there are more values.
+    Met(u64, bool),
+    Failed(String),
+}
+
+/// Checks the values of `ours` against the values listed in `golden` for
+/// --expect, `address` is the offset of the first element.
+fn expect_values(
+    ours: impl Read,
+    golden: &str,
+    fields: &[ParseType],
+    args: &Opt,
+    mut address: u64,
+) -> std::io::Result<Expectation> {
+    let size: u64 = fields.iter().map(|field| field.size_of() as u64).sum();
+    let span = args.skip_before + size + args.stride;
+    let mut ours = record_parser(ours, fields, args);
+    let mut expected = golden.split_whitespace();
+    let mut index = 0;
+    loop {
+        if !ours.read_record()? {
+            return Ok(match expected.next() {
+                None => Expectation::Met(index, false),
+                Some(_) => Expectation::Failed(format!(
+                    "the values end after {}, but {} are expected",
+                    index,
+                    index + 1 + expected.count() as u64
+                )),
+            });
+        }
+        let mut start = args.skip_before;
+        for (field, (value, bytes)) in fields.iter().zip(ours.record()) {
+            let value = fixed_point(value, args);
+            let text = match expected.next() {
+                Some(text) => text,
+                None => return Ok(Expectation::Met(index, true)),
+            };
+            let matches = match output_type(field, args).parse(text) {
+                Ok(expected) => same_value(value, expected, args),
+                Err(e) => return Ok(Expectation::Failed(format!("value {}: {}", index, e))),
+            };
+            if !matches {
+                return Ok(Expectation::Failed(format!(
+                    "value {} at offset {} is {}, but {} is expected",
+                    index,
+                    address + start,
+                    args.format.value(value),
+                    text
+                )));
+            }
+            start += bytes.len() as u64;
+            index += 1;
+        }
+        address += span;
+    }
+}
+
+/// Difference of two values of the same type, integers wrap around.
+fn delta(value: Value, previous: Value) -> Value {
+    match (value, previous) {
+        (Value::U8(a), Value::U8(b)) => Value::U8(a.wrapping_sub(b)),
+        (Value::U16(a), Value::U16(b)) => Value::U16(a.wrapping_sub(b)),
+        (Value::U32(a), Value::U32(b)) => Value::U32(a.wrapping_sub(b)),
+        (Value::U64(a), Value::U64(b)) => Value::U64(a.wrapping_sub(b)),
+        (Value::I8(a), Value::I8(b)) => Value::I8(a.wrapping_sub(b)),
+        (Value::I16(a), Value::I16(b)) => Value::I16(a.wrapping_sub(b)),
+        (Value::I32(a), Value::I32(b)) => Value::I32(a.wrapping_sub(b)),
+        (Value::I64(a), Value::I64(b)) => Value::I64(a.wrapping_sub(b)),
+        (Value::F32(a), Value::F32(b)) => Value::F32(a - b),
+        (Value::F64(a), Value::F64(b)) => Value::F64(a - b),
+        (Value::U24(a), Value::U24(b)) => Value::U24(a.wrapping_sub(b) & 0xff_ffff),
+        // Shift bit 23 into the sign bit and back to sign extend.
+        (Value::I24(a), Value::I24(b)) => Value::I24(a.wrapping_sub(b) << 8 >> 8),
+        _ => unreachable!("the values of a field have the same type"),
+    }
+}
+
+/// Type of the values output for values of `field`, which differs from it
+/// for --field, --fixed, --db, --db-power, --cumsum and --cummean.
+fn output_type(field: &ParseType, args: &Opt) -> ParseType {
+    let field = match field {
+        _ if args.bit_fields.is_empty() => field,
+        ParseType::I8 => &ParseType::U8,
+        ParseType::I16 => &ParseType::U16,
+        ParseType::I24 => &ParseType::U24,
+        ParseType::I32 => &ParseType::U32,
+        ParseType::I64 => &ParseType::U64,
+        field => field,
+    };
+    match field {
+        _ if args.fixed.is_some() || args.db || args.db_power || args.cummean => ParseType::F64,
+        ParseType::U8 | ParseType::U16 | ParseType::U24 | ParseType::U32 if args.cumsum => {
+            ParseType::U64
+        }
+        ParseType::I8 | ParseType::I16 | ParseType::I24 | ParseType::I32 if args.cumsum => {
+            ParseType::I64
+        }
+        field => field.clone(),
+    }
+}
+
+/// Widens a value to the type of a --cumsum sum, u64 or i64 for integers
+/// and f64 for floats.
+fn widen(value: Value) -> Value {
+    match value {
+        Value::F32(v) => Value::F64(v as f64),
+        Value::F64(v) => Value::F64(v),
+        Value::U8(_) | Value::U16(_) | Value::U24(_) | Value::U32(_) | Value::U64(_) => {
+            Value::U64(value.as_i128().unwrap() as u64)
+        }
+        _ => Value::I64(value.as_i128().unwrap() as i64),
+    }
+}
+
+/// Adds `value` to a running sum of `widen`ed values, 64 bit integers wrap around.
+fn accumulate(sum: Value, value: Value) -> Value {
+    match (sum, widen(value)) {
+        (Value::U64(a), Value::U64(b)) => Value::U64(a.wrapping_add(b)),
+        (Value::I64(a), Value::I64(b)) => Value::I64(a.wrapping_add(b)),
+        (Value::F64(a), Value::F64(b)) => Value::F64(a + b),
+        _ => unreachable!("the values of a field have the same type"),
+    }
+}
+
+/// Scales an integer to the real number it stands for with --fixed.
+fn fixed_point(value: Value, args: &Opt) -> Value {
+    match (args.fixed, value.as_i128()) {
+        (Some(fixed), Some(v)) => Value::F64(v as f64 / 2f64.powi(fixed.frac_bits as i32)),
+        _ => value,
+    }
+}
+
+/// The value in decibels for --db and --db-power and whether it's clamped to
+/// --db-floor, like 0 and for powers negative values. NaN stays NaN.
+fn decibels(value: Value, args: &Opt) -> (Value, bool) {
+    let ratio = value.as_f64() / args.db_ref;
+    let db = match args.db_power {
+        true => 10.0 * ratio.log10(),
+        false => 20.0 * ratio.abs().log10(),
+    };
+    match db {
+        _ if ratio.is_nan() => (Value::F64(f64::NAN), false),
+        db if db.is_nan() || db < args.db_floor => (Value::F64(args.db_floor), true),
+        db => (Value::F64(db), false),
+    }
+}
+
+/// Bits of `field` of the integer `value` as the unsigned integer of its width.
+fn extract_bits(value: Value, field: BitField) -> Value {
+    let bits = value.as_i128().expect("integer value") as u128 >> field.low;
+    let bits = (bits & (u128::MAX >> (127 - (field.high - field.low)))) as u64;
+    match value {
+        Value::U8(_) | Value::I8(_) => Value::U8(bits as u8),
+        Value::U16(_) | Value::I16(_) => Value::U16(bits as u16),
+        Value::U24(_) | Value::I24(_) => Value::U24(bits as u32),
+        Value::U32(_) | Value::I32(_) => Value::U32(bits as u32),
+        _ => Value::U64(bits),
+    }
+}
+
+/// Number of records that count as one value for --every and --take, values
+/// that belong together are kept or dropped together.
+fn value_unit(args: &Opt) -> u64 {
+    match (args.channels, args.channel, args.complex) {
+        (Some(channels), None, _) => channels,
+        (_, _, true) => 2,
+        _ => 1,
+    }
+}
+
+/// Parses the fields of one record, which is a single value unless --struct is given.
+fn parse_record(
+    record: Record<'_>,
+    out: &mut impl Write,
+    args: &Opt,
+    state: &mut OutputState,
+) -> std::io::Result<()> {
+    if let Some(every) = args.every {
+        let index = state.records / value_unit(args);
+        state.records += 1;
+        if !index.is_multiple_of(every) {
+            state.address += state.element_size;
+            return Ok(());
+        }
+    }
+    if let (Some(take), Some(drop)) = (args.take, args.drop) {
+        let index = state.records / value_unit(args);
+        state.records += 1;
+        if index % take.saturating_add(drop) >= take {
+            state.address += state.element_size;
+            return Ok(());
+        }
+    }
+    if args.count_only && state.filter.is_empty() {
+        let bit_fields = std::cmp::max(1, args.bit_fields.len());
+        state.values += (record.len() * bit_fields) as u64;
+        state.address += state.element_size;
+        return Ok(());
+    }
+    let mut start = 0;
+    for (value, raw) in record {
+        state.field_start = start;
+        start += raw.len() as u64;
+        // Only floats have an `as_i128` of `None`.
+        if args.skip_nan && value.as_i128().is_none() && !value.as_f64().is_finite() {
+            state.skipped_nan += 1;
+            continue;
+        }
+        // Without --field the value itself is the only field.
+        for index in 0..std::cmp::max(1, args.bit_fields.len()) {
+            let value = match args.bit_fields.get(index) {
+                Some(bit_field) => extract_bits(value, *bit_field),
+                None => value,
+            };
+            let value = match state.previous.is_empty() {
+                true => value,
+                false => {
+                    let column = state.delta_column;
+                    state.delta_column = (column + 1) % state.previous.len();
+                    match state.previous[column].replace(value) {
+                        Some(previous) => delta(value, previous),
+                        None if args.delta_skip_first => continue,
+                        None => value,
+                    }
+                }
+            };
+            let value = fixed_point(value, args);
+            let value = match args.db || args.db_power {
+                true => {
+                    let (value, clamped) = decibels(value, args);
+                    state.clamped_db += clamped as u64;
+                    value
+                }
+                false => value,
+            };
+            let value = match state.sums.is_empty() {
+                true => value,
+                false => {
+                    let column = state.sum_column;
+                    state.sum_column = (column + 1) % state.sums.len();
+                    let (sum, count) = match state.sums[column] {
+                        Some((sum, count)) => (accumulate(sum, value), count + 1),
+                        None => (widen(value), 1),
+                    };
+                    state.sums[column] = Some((sum, count));
+                    match (args.cummean, value) {
+                        (true, _) => Value::F64(sum.as_f64() / count as f64),
+                        (false, Value::F32(_)) => Value::F32(sum.as_f64() as f32),
+                        (false, _) => sum,
+                    }
+                }
+            };
+            if args.any_column || args.all_columns {
+                let (address, field_start) = (state.address, state.field_start);
+                state
+                    .pending
+                    .push((value, raw.to_vec(), address, field_start));
+                if state.pending.len() == args.row_size {
+                    finish_filtered_row(out, args, state)?;
+                }
+                continue;
+            }
+            output(value, raw, out, args, state)?;
+        }
+    }
+    state.address += state.element_size;
+    Ok(())
+}
+
+/// Outputs the row of --any-column or --all-columns collected so far if it
+/// passes the filter, or only counts it with --count-only.
+fn finish_filtered_row(
+    out: &mut impl Write,
+    args: &Opt,
+    state: &mut OutputState,
+) -> std::io::Result<()> {
+    if state.pending.is_empty() {
+        return Ok(());
+    }
+    let mut pending = std::mem::take(&mut state.pending);
+    let mut passing = pending
+        .iter()
+        .enumerate()
+        .map(|(column, (value, ..))| passes_filter(*value, column, args, state));
+    let passes = match args.all_columns {
+        true => passing.all(|passes| passes),
+        false => passing.any(|passes| passes),
+    };
+    if args.count_only {
+        state.values += pending.len() as u64;
+        state.matches += passes as u64;
+    } else if passes {
+        let (address, field_start) = (state.address, state.field_start);
+        // The whole row is shown, so its values bypass the filter.
+        let filter = std::mem::take(&mut state.filter);
+        for (value, raw, value_address, value_start) in &pending {
+            state.address = *value_address;
+            state.field_start = *value_start;
+            output(*value, raw, out, args, state)?;
+        }
+        state.filter = filter;
+        state.address = address;
+        state.field_start = field_start;
+    }
+    pending.clear();
+    state.pending = pending;
+    Ok(())
+}
+
+/// Parses the records read by `parser` until it returns none.
+fn parse_records(
+    parser: &mut BinParser<impl Read>,
+    out: &mut impl Write,
+    args: &Opt,
+    state: &mut OutputState,
+) -> std::io::Result<()> {
+    while parser.read_record()? {
+        parse_record(parser.record(), out, args, state)?;
+    }
+    Ok(())
+}
+
+/// The first of `ids` given on the command line, by its long name.
+fn given(matches: &ArgMatches, ids: &[&str]) -> Option<String> {
+    let id = ids
+        .iter()
+        .find(|id| matches.value_source(id) == Some(ValueSource::CommandLine))?;
+    let command = Opt::command();
+    let arg = command.get_arguments().find(|arg| arg.get_id() == id)?;
+    Some(format!("--{}", arg.get_long()?))
+}
+
+/// Rejects options that don't go with `format`, the ones clap rejects with --csv and --json.
+fn check_output_format(format: OutputFormat, matches: &ArgMatches) {
+    let conflicts = match format {
+        OutputFormat::Csv => [TEXT_ONLY, JSON_ONLY].concat(),
+        OutputFormat::Json | OutputFormat::Jsonl => [TEXT_ONLY, CSV_ONLY].concat(),
+        // Like gnuplot, binary formats are only the values and not printed rows.
+        OutputFormat::Gnuplot | OutputFormat::Msgpack | OutputFormat::Cbor => {
+            [TEXT_ONLY, JSON_ONLY, CSV_ONLY, ROWS_ONLY].concat()
+        }
+    };
+    if let Some(name) = given(matches, &conflicts) {
+        let format = format.to_possible_value().unwrap();
+        eprintln!(
+            "--output-format {} can't be combined with {}",
+            format.get_name(),
+            name
+        );
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let matches = Opt::command().get_matches();
+    let mut args = Opt::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(shell) = args.completions {
+        print!("{}", completions::generate(shell, &Opt::command()));
+        return;
+    }
+    args.parse_type = args.type_name.as_ref().and_then(TypeName::parse_type);
+    if args.parse_type.is_some() && (args.layout.is_some() || args.guess) {
+        let option = if args.guess { "--guess" } else { "--struct" };
+        eprintln!("{} replaces TYPE, give it as auto", option);
+        std::process::exit(1);
+    }
+    // Given by a header or guessed from the values once they can be read otherwise.
+    let mut guess_byte_order = args.endianness == Endianness::Auto || args.detect_only;
+    args.byte_order = args
+        .endianness
+        .byte_order()
+        .unwrap_or(ByteOrder::LittleEndian);
+    args.format = TextFormat {
+        precision: args.precision,
+        sci: args.sci,
+        trim_zeros: args.trim_zeros,
+        bits: args.bits,
+        float_bits: args.float_bits,
+        float_bits_only: args.float_bits_only,
+        as_time: args.as_time.then_some(args.time_unit),
+        quiet: args.quiet,
+    };
+    if let Some(format) = args.output_format {
+        check_output_format(format, &matches);
+        match format {
+            OutputFormat::Csv => args.csv = true,
+            OutputFormat::Json | OutputFormat::Jsonl => args.json = true,
+            OutputFormat::Gnuplot | OutputFormat::Msgpack | OutputFormat::Cbor => (),
+        }
+    }
+    if let Some(encoding) = serial_encoding(&args) {
+        if args.output.is_none() && std::io::stdout().is_terminal() && !args.force {
+            let name = match encoding {
+                Encoding::Msgpack => "MessagePack",
+                Encoding::Cbor => "CBOR",
+            };
+            eprintln!(
+                "Refusing to write {} to a terminal, redirect stdout or use --output or --force",
+                name
+            );
+            std::process::exit(1);
+        }
+    } else if args.as_array || args.force {
+        eprintln!("--as-array and --force need --output-format msgpack or cbor");
+        std::process::exit(1);
+    }
+    if args.reencode.is_some() {
+        args.emit_binary = true;
+    }
+    if (args.x_scale.is_some() || args.x_offset.is_some()) && !gnuplot(&args) {
+        eprintln!("--x-scale and --x-offset need --output-format gnuplot");
+        std::process::exit(1);
+    }
+    if args.db_ref <= 0.0 || args.db_ref.is_nan() {
+        eprintln!("--db-ref must be positive: {}", args.db_ref);
+        std::process::exit(1);
+    }
+    if args.hysteresis < 0.0 {
+        eprintln!("--hysteresis is negative: {}", args.hysteresis);
+        std::process::exit(1);
+    }
+    if let (Some(min), Some(max)) = (args.hist_min, args.hist_max) {
+        if max < min {
+            eprintln!("--hist-max is below --hist-min: {} < {}", max, min);
+            std::process::exit(1);
+        }
+    }
+
+    // Ctrl-C stops reading but still finishes the output, e.g. closes the JSON array.
+    let cancel = CancellationToken::new();
+    let sigint_cancel = cancel.clone();
+    if let Err(e) = ctrlc::set_handler(move || sigint_cancel.cancel()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    let mut file_sizes = Vec::with_capacity(args.files.len());
+    // Pipes, devices and pseudo files like /proc/cpuinfo report no useful size.
+    let mut regular_files = true;
+    let mut sized_files = true;
+    for file in &args.files {
+        match std::fs::metadata(file) {
+            Ok(meta) => {
+                if meta.is_dir() {
+                    eprintln!("File is a directory: {}", file);
+                    std::process::exit(1);
+                } else {
+                    regular_files &= meta.is_file();
+                    sized_files &= meta.is_file() && (meta.len() > 0 || args.follow);
+                    file_sizes.push(meta.len())
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", file, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut compressions = Vec::with_capacity(args.files.len());
+    for file in &args.files {
+        match args.compression.detect(file, regular_files) {
+            Ok(compression) => compressions.push(compression),
+            Err(e) => {
+                eprintln!("Error: {}: {}", file, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // Without a known size the input is streamed, so checks against the size never fail.
+    let compressed = compressions.iter().any(|c| *c != Compression::None);
+    let size_known = sized_files && !compressed;
+    let file_size: u64 = match size_known {
+        true => file_sizes.iter().sum(),
+        false => i64::MAX as u64,
+    };
+    let seek_sizes = match size_known {
+        true => Some(&file_sizes[..]),
+        false => None,
+    };
+    if !size_known {
+        let unsupported = [
+            (
+                matches!(args.offset, Offset::FromEnd(_)),
+                "a negative offset",
+            ),
+            (args.follow, "--follow"),
+            (args.with_header, "--with-header"),
+        ];
+        for (given, option) in unsupported {
+            if given {
+                eprintln!(
+                    "Input of unknown size, e.g. compressed or a pipe, can't be combined with {}",
+                    option
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut offset = match args.offset {
+        Offset::FromStart(o) => o,
+        Offset::FromEnd(o) if o > file_size => {
+            eprintln!(
+                "Offset is out of range: -{} reaches before the start of the file ({} bytes)",
+                o, file_size
+            );
+            std::process::exit(1);
+        }
+        Offset::FromEnd(o) => file_size - o,
+    };
+
+    let find = args
+        .find_pattern
+        .clone()
+        .or_else(|| args.find_str.as_ref().map(|text| text.as_bytes().to_vec()));
+    let pattern_search = match (&args.skip_header_until, &find) {
+        (Some(pattern), _) => Some((pattern, 1, true)),
+        (_, Some(pattern)) => Some((pattern, args.nth_match, args.after_pattern)),
+        (None, None) => None,
+    };
+    if let Some((pattern, nth, after)) = pattern_search {
+        if let Offset::FromEnd(_) = args.offset {
+            eprintln!("A negative offset can't be combined with a pattern search");
+            std::process::exit(1);
+        }
+        // The search reads the input once more, which would consume a pipe.
+        if !regular_files {
+            eprintln!("A pattern search needs regular files, not pipes or devices");
+            std::process::exit(1);
+        }
+        let found = MultiFileReader::open(&args.files, seek_sizes, &compressions)
+            .and_then(|mut files| search::find_pattern(&mut files, pattern, nth, args.scan_limit));
+        match found {
+            Ok(Some(position)) => {
+                offset += position;
+                if after {
+                    offset += pattern.len() as u64;
+                }
+                if !args.quiet {
+                    eprintln!("Found pattern at {}, parsing from {}", position, offset);
+                }
+            }
+            Ok(None) => {
+                let hex: String = pattern.iter().map(|b| format!("{:02x}", b)).collect();
+                match args.scan_limit {
+                    Some(limit) => eprintln!(
+                        "Pattern {} (occurrence {}) not found in the first {} bytes",
+                        hex, nth, limit
+                    ),
+                    None => eprintln!("Pattern {} (occurrence {}) not found", hex, nth),
+                }
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut file_stream = match MultiFileReader::open(&args.files, seek_sizes, &compressions) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if args.wav {
+        if let Offset::FromEnd(_) = args.offset {
+            eprintln!("A negative offset can't be combined with --wav");
+            std::process::exit(1);
+        }
+        let wav = match wav::read_header(&mut file_stream) {
+            Ok(wav) => wav,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        eprintln!(
+            "Found WAV header: {} {} bit, {} channel{}, {} Hz, samples at {}{}",
+            wav::format_name(wav.format),
+            wav.bits,
+            wav.channels,
+            if wav.channels == 1 { "" } else { "s" },
+            wav.sample_rate,
+            wav.data_offset,
+            match wav.data_len {
+                Some(len) => format!(" ({} bytes)", len),
+                None => String::new(),
+            }
+        );
+        if let Some(parse_type) = &args.parse_type {
+            if *parse_type != wav.parse_type {
+                eprintln!(
+                    "Type mismatch: {:?} given but header says {:?}",
+                    parse_type, wav.parse_type
+                );
+                std::process::exit(1);
+            }
+        }
+        args.parse_type = Some(wav.parse_type.clone());
+        args.byte_order = ByteOrder::LittleEndian;
+        guess_byte_order = false;
+        args.channels = Some(wav.channels as u64);
+        args.sample_rate = args.sample_rate.or(Some(wav.sample_rate));
+        let start = offset;
+        offset += wav.data_offset;
+        // A truncated capture has fewer samples than its header says.
+        let available = match size_known {
+            true => Some(file_size.saturating_sub(offset)),
+            false => None,
+        };
+        let remaining = wav.data_len.map(|len| len.saturating_sub(start));
+        let remaining = match (remaining, available) {
+            (Some(remaining), Some(available)) => Some(std::cmp::min(remaining, available)),
+            (remaining, None) => remaining,
+            (None, available) => available,
+        };
+        // --number conflicts with --length, so it's capped to the frames instead.
+        let frame_size = wav.parse_type.size_of() as u64 * wav.channels as u64;
+        match remaining {
+            Some(remaining) if args.number != i64::MAX => {
+                let frames = (remaining / frame_size) as i64;
+                args.number = std::cmp::min(args.number, frames);
+            }
+            Some(remaining) => {
+                args.length = Some(
+                    args.length
+                        .map_or(remaining, |length| std::cmp::min(length, remaining)),
+                );
+            }
+            None => (),
+        }
+    }
+    // A parsebin or .npy header at the start of the file configures type, byte order and length.
+    if args.offset == Offset::FromStart(0)
+        && pattern_search.is_none()
+        && args.layout.is_none()
+        && !args.wav
+    {
+        let (header, npy_header) = match file_stream.fill_buf() {
+            Ok(bytes) => (
+                container::Header::from_bytes(bytes),
+                NpyHeader::from_bytes(bytes),
+            ),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        match npy_header {
+            Some(Ok(header)) => {
+                let shape: Vec<String> = header.shape.iter().map(|axis| axis.to_string()).collect();
+                // Written like Python tuples, where one element needs a trailing comma.
+                let shape = match shape.len() {
+                    1 => format!("({},)", shape[0]),
+                    _ => format!("({})", shape.join(", ")),
+                };
+                eprintln!(
+                    "Found NumPy header: version {}.{}, '{}', shape {}{}",
+                    header.version.0,
+                    header.version.1,
+                    header.descr,
+                    shape,
+                    if header.fortran_order {
+                        ", Fortran order"
+                    } else {
+                        ""
+                    }
+                );
+                if let Some(parse_type) = &args.parse_type {
+                    if *parse_type != header.parse_type {
+                        eprintln!(
+                            "Type mismatch: {:?} given but header says {:?}",
+                            parse_type, header.parse_type
+                        );
+                        std::process::exit(1);
+                    }
+                }
+                // Rows are the last axis, or with Fortran order the first one,
+                // which is what is contiguous in the file.
+                let axis = match header.fortran_order {
+                    true => header.shape.first(),
+                    false => header.shape.last(),
+                };
+                if let (Some(axis), true, 1) = (axis, header.shape.len() > 1, args.row_size) {
+                    args.row_size = std::cmp::max(1, *axis as usize);
+                }
+                args.number = std::cmp::min(args.number, header.count() as i64);
+                args.parse_type = Some(header.parse_type);
+                args.byte_order = header.byte_order;
+                guess_byte_order = false;
+                offset = header.header_len;
+            }
+            Some(Err(e)) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            None => (),
+        }
+        match header {
+            Some(Ok(header)) => {
+                eprintln!(
+                    "Found parsebin header: version {}, {:?}, {}, {} elements",
+                    header.version, header.parse_type, header.byte_order, header.count
+                );
+                if let Some(parse_type) = &args.parse_type {
+                    if *parse_type != header.parse_type {
+                        eprintln!(
+                            "Type mismatch: {:?} given but header says {:?}",
+                            parse_type, header.parse_type
+                        );
+                        std::process::exit(1);
+                    }
+                }
+                args.parse_type = Some(header.parse_type);
+                args.byte_order = header.byte_order;
+                guess_byte_order = false;
+                args.number = std::cmp::min(args.number, header.count as i64);
+                offset = header.header_len as u64;
+            }
+            Some(Err(e)) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            None => (),
+        }
+    }
+    if args.guess {
+        let length = std::cmp::min(args.length.unwrap_or(GUESS_SAMPLE), GUESS_SAMPLE);
+        let mut sample = Vec::new();
+        let read = file_stream
+            .seek_to(offset)
+            .and_then(|_| file_stream.by_ref().take(length).read_to_end(&mut sample));
+        if let Err(e) = read {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        let mut out = std::io::stdout().lock();
+        exit_on_error(writeln!(out, "score type byte order    first values"));
+        for candidate in guess::rank(&sample) {
+            let preview: Vec<String> = candidate.preview.iter().map(|v| v.to_string()).collect();
+            exit_on_error(writeln!(
+                out,
+                "{:.3} {:<4} {:<13} {}",
+                candidate.score,
+                candidate.parse_type,
+                candidate.byte_order,
+                preview.join(" ")
+            ));
+        }
+        return;
+    }
+    if args.interactive {
+        // Commands come from stdin, so the input can't be a pipe as well.
+        if !regular_files {
+            eprintln!("--interactive needs regular files, not pipes or devices");
+            std::process::exit(1);
+        }
+        let parse_type = args.parse_type.clone().unwrap_or(ParseType::U8);
+        let size = size_known.then_some(file_size);
+        let mut session = Session::new(file_stream, size, offset, parse_type, args.byte_order);
+        let stdin = std::io::stdin();
+        let prompt = stdin.is_terminal().then_some("> ");
+        exit_on_error(session.run(stdin.lock(), &mut std::io::stdout().lock(), prompt));
+        return;
+    }
+    let fields = match (&args.parse_type, &args.layout) {
+        (_, Some(layout)) => {
+            if let Some(byte_order) = &layout.byte_order {
+                args.byte_order = byte_order.clone();
+                guess_byte_order = false;
+            }
+            args.row_size = layout.fields.len();
+            layout.fields.clone()
+        }
+        (Some(parse_type), None) => vec![parse_type.clone()],
+        (None, None) => {
+            eprintln!("TYPE auto needs a parsebin, .npy or .wav header or --struct");
+            std::process::exit(1);
+        }
+    };
+    // LEB128 values are decoded up front and parsed as the 64 bit integers they are widened to.
+    let leb128 = fields
+        .iter()
+        .find(|field| field.is_variable_length())
+        .cloned();
+    if let Some(parse_type) = &leb128 {
+        if let Some(name) = given(&matches, &[&["bit_width"], PACKED_CONFLICTS].concat()) {
+            eprintln!("{} can't be combined with {}", parse_type, name);
+            std::process::exit(1);
+        }
+    }
+    let fields = match leb128 {
+        Some(ParseType::Sleb128) => vec![ParseType::I64],
+        Some(_) => vec![ParseType::U64],
+        None => fields,
+    };
+    let record_size: i64 = fields.iter().map(|field| field.size_of()).sum();
+    if let Some(width) = args.bit_width {
+        if fields[0].is_float() || width as i64 > record_size * 8 {
+            eprintln!(
+                "--bit-width {} needs an integer type of at least {} bits",
+                width, width
+            );
+            std::process::exit(1);
+        }
+    }
+    if args.buffer_size < record_size as u64 {
+        eprintln!(
+            "--buffer-size must be at least the size of one value: {} < {}",
+            args.buffer_size, record_size
+        );
+        std::process::exit(1);
+    }
+    // Wide enough for every offset of the file, so the rows of --addr line up.
+    let address_width = match (size_known, args.addr) {
+        (true, Some(AddressStyle::Dec)) => std::cmp::max(8, file_size.to_string().len()),
+        (true, _) => std::cmp::max(8, format!("{:x}", file_size).len()),
+        (false, _) => 8,
+    };
+    // Every field of --field is a column of its own.
+    let bit_fields = std::cmp::max(1, args.bit_fields.len());
+    let columns: Vec<ParseType> = fields
+        .iter()
+        .flat_map(|field| std::iter::repeat_n(field.clone(), bit_fields))
+        .collect();
+    if args.row_size == AUTO_ROW_SIZE {
+        let values = auto_row_size(&fields[0], &args, address_width);
+        args.row_size = std::cmp::max(bit_fields, values - values % bit_fields);
+    } else {
+        args.row_size *= bit_fields;
+    }
+    if args.auto_header {
+        args.header = (0..args.row_size)
+            .map(|column| format!("col{}", column))
+            .collect();
+    }
+    if !args.header.is_empty() && args.header.len() != args.row_size {
+        eprintln!(
+            "Header has {} columns but row size is {}",
+            args.header.len(),
+            args.row_size
+        );
+        std::process::exit(1);
+    }
+    if !args.labels.is_empty() {
+        if args.labels.len() != args.row_size {
+            eprintln!(
+                "--labels has {} names but row size is {}",
+                args.labels.len(),
+                args.row_size
+            );
+            std::process::exit(1);
+        }
+        args.align = true;
+    }
+    if args.ascii && !matches!(fields[0], ParseType::U8 | ParseType::I8) {
+        eprintln!("--ascii is only supported for u8 and i8");
+        std::process::exit(1);
+    }
+    if (args.highlight_special || args.count_special.is_some() || args.skip_nan)
+        && !fields.iter().any(|field| field.is_float())
+    {
+        eprintln!(
+            "--highlight-special, --count-special and --skip-nan are only supported for f32 and f64"
+        );
+        std::process::exit(1);
+    }
+    if (args.bits || args.float_bits || args.float_bits_only)
+        && !fields.iter().any(|field| field.is_float())
+    {
+        eprintln!("--bits, --float-bits and --float-bits-only are only supported for f32 and f64");
+        std::process::exit(1);
+    }
+    if let Some(fixed) = args.fixed {
+        for field in &fields {
+            if field.is_float() {
+                eprintln!("--fixed is only supported for integer types");
+                std::process::exit(1);
+            }
+            let signed = matches!(
+                field,
+                ParseType::I8
+                    | ParseType::I16
+                    | ParseType::I24
+                    | ParseType::I32
+                    | ParseType::I64
+                    | ParseType::Sleb128
+            );
+            let width = args.bit_width.unwrap_or(field.size_of() as u32 * 8);
+            let bits = fixed.int_bits + fixed.frac_bits + signed as u32;
+            if bits != width {
+                eprintln!(
+                    "--fixed {}.{} has {} bits{}, but {} values have {}",
+                    fixed.int_bits,
+                    fixed.frac_bits,
+                    bits,
+                    if signed { " with the sign bit" } else { "" },
+                    field,
+                    width
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if !args.bit_fields.is_empty() {
+        for field in &fields {
+            if field.is_float() {
+                eprintln!("--field is only supported for integer types");
+                std::process::exit(1);
+            }
+            let width = args.bit_width.unwrap_or(field.size_of() as u32 * 8);
+            if let Some(bit_field) = args.bit_fields.iter().find(|f| f.high >= width) {
+                eprintln!(
+                    "--field {}:{} is out of range for {} values, which have bits {}:0",
+                    bit_field.high,
+                    bit_field.low,
+                    field,
+                    width - 1
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if args.value_counts
+        && args.round.is_none()
+        && fields
+            .iter()
+            .any(|field| output_type(field, &args).is_float())
+    {
+        eprintln!("--value-counts needs --round for floats");
+        std::process::exit(1);
+    }
+    if args.as_time
+        && fields
+            .iter()
+            .all(|field| matches!(field, ParseType::F32 | ParseType::F64))
+    {
+        eprintln!("--as-time is only supported for integer types");
+        std::process::exit(1);
+    }
+    if let Some(elements) = args.offset_elements {
+        offset = match elements
+            .checked_mul(record_size as u64)
+            .and_then(|bytes| bytes.checked_add(offset))
+        {
+            Some(o) => o,
+            None => {
+                eprintln!(
+                    "Offset is out of range: element {} overflows a byte offset",
+                    elements
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+    // Without a known size only offsets past the largest supported one can be rejected up front.
+    if !size_known && offset > file_size {
+        eprintln!(
+            "Offset is out of range: {} is larger than the supported maximum of {}",
+            offset, file_size
+        );
+        std::process::exit(1);
+    }
+    // The end of the file is an empty range.
+    if offset > file_size {
+        match args.offset_elements {
+            Some(elements) => eprintln!(
+                "Offset is out of range: element {} (byte offset {}) > {}",
+                elements, offset, file_size
+            ),
+            None => eprintln!("Offset is out of range: {} > {}", offset, file_size),
+        }
+        std::process::exit(1);
+    }
+    // A single channel is read by skipping the other channels of each frame.
+    let value_span = args.skip_before as i64 + record_size + args.stride as i64;
+    let frame_stride = args.stride as i64;
+    if let Some(channels) = args.channels {
+        match args.channel {
+            Some(channel) if channel >= channels => {
+                eprintln!("Channel is out of range: {} >= {}", channel, channels);
+                std::process::exit(1);
+            }
+            Some(channel) => {
+                args.skip_before += channel * value_span as u64;
+                args.stride += (channels - channel - 1) * value_span as u64;
+            }
+            None => {
+                args.row_size = channels as usize * bit_fields;
+                if args.number != i64::MAX {
+                    args.number = args.number.saturating_mul(channels as i64);
+                }
+                args.last = args.last.map(|last| last.saturating_mul(channels));
+            }
+        }
+    }
+    if args.crossings.is_some() && (args.row_size > 1 || fields.len() > 1) {
+        eprintln!("--crossings needs one value per row, e.g. a single --channel");
+        std::process::exit(1);
+    }
+    if args.complex {
+        if args.number != i64::MAX {
+            args.number = args.number.saturating_mul(2);
+        }
+        args.last = args.last.map(|last| last.saturating_mul(2));
+    }
+    if let Some(every) = args.every {
+        if args.number != i64::MAX {
+            args.number = args.number.saturating_mul(every as i64);
+        }
+        args.last = args.last.map(|last| last.saturating_mul(every));
+    }
+    if let (Some(take), Some(drop), true) = (args.take, args.drop, args.number != i64::MAX) {
+        // Whole blocks of --take and --drop and the start of the last one.
+        let unit = value_unit(&args) as i64;
+        let values = args.number / unit;
+        let (blocks, rest) = (values / take as i64, values % take as i64);
+        let block = take.saturating_add(drop) as i64;
+        args.number = blocks
+            .saturating_mul(block)
+            .saturating_add(rest)
+            .saturating_mul(unit);
+    }
+    // Every value takes up its own size plus the bytes skipped before and after it.
+    let element_span = args.skip_before as i64 + record_size + args.stride as i64;
+    // While number is not 0, read the file and parse the data according to the type.
+    let range_end = match (args.end, args.length) {
+        (Some(end), _) if end < offset => {
+            eprintln!("End is before the offset: {} < {}", end, offset);
+            std::process::exit(1);
+        }
+        (Some(end), _) => Some(end),
+        (_, Some(length)) => Some(offset.saturating_add(length)),
+        (None, None) => None,
+    };
+    if let Some(skip) = args.skip {
+        // Like --number, frames of several channels and complex pairs count as one.
+        let values = match (args.channels, args.channel) {
+            (Some(channels), None) => skip.saturating_mul(channels),
+            _ => skip,
+        };
+        let values = match args.complex {
+            true => values.saturating_mul(2),
+            false => values,
+        };
+        let skipped = values.saturating_mul(element_span as u64);
+        offset = std::cmp::min(
+            offset.saturating_add(skipped),
+            range_end.unwrap_or(file_size),
+        );
+    }
+    // Input of unknown size keeps the last values while reading it instead.
+    if let (Some(last), true) = (args.last, size_known) {
+        let available = range_end.unwrap_or(file_size).saturating_sub(offset);
+        offset += skipped_before_last(available, last, element_span, frame_stride, &args);
+    }
+    // Bytes in the range that are left out since they don't make up a whole value.
+    let mut dropped = 0;
+    let bytes_to_read = match (range_end, args.number) {
+        (Some(end), _) if end > file_size => {
+            eprintln!(
+                "Range end is past the end of the file: {} > {}",
+                end, file_size
+            );
+            std::process::exit(1);
+        }
+        // Packed fields are unpacked from the whole range, --number is applied there.
+        (end, _) if args.bit_width.is_some() || leb128.is_some() => {
+            (end.unwrap_or(file_size) - offset) as i64
+        }
+        (Some(end), _) => {
+            let length = (end - offset) as i64;
+            let elements = (length + frame_stride) / element_span;
+            let trailing = std::cmp::max(0, length - elements * element_span);
+            dropped += trailing;
+            if trailing > 0 {
+                warn!(
+                    args,
+                    "range is not a multiple of {} bytes, ignoring {} trailing bytes",
+                    element_span,
+                    trailing
+                );
+            }
+            length - trailing
+        }
+        (None, i64::MAX) => {
+            let length = (file_size - offset) as i64;
+            // Bytes of an incomplete record after the stride of the last complete one,
+            // input of unknown size has no end to check.
+            let trailing = match size_known {
+                true => (length + frame_stride) % element_span - frame_stride,
+                false => 0,
+            };
+            if args.layout.is_some() && trailing > 0 {
+                eprintln!(
+                    "File size is not a multiple of the record size: {} bytes after {} records of {} bytes",
+                    trailing,
+                    (length + frame_stride) / element_span,
+                    element_span
+                );
+                std::process::exit(1);
+            }
+            length
+        }
+        (None, a) => std::cmp::min(a.saturating_mul(element_span), (file_size - offset) as i64),
+    };
+    // Drop an incomplete trailing frame, input of unknown size has no end to drop it from yet.
+    let bytes_to_read = match args.channels {
+        Some(channels) if size_known || range_end.is_some() => {
+            let frame_span = value_span * channels as i64;
+            let frames = (bytes_to_read + frame_stride) / frame_span;
+            let values = (bytes_to_read + frame_stride - frames * frame_span) / value_span;
+            if values > 0 {
+                warn!(
+                    args,
+                    "dropping incomplete trailing frame with {} of {} channels", values, channels
+                );
+            }
+            dropped += std::cmp::max(0, bytes_to_read - frames * frame_span);
+            std::cmp::min(bytes_to_read, frames * frame_span)
+        }
+        _ => bytes_to_read,
+    };
+    // Following is only bounded by --number, not by the current file size.
+    let bytes_to_read = match (args.follow, args.number) {
+        (true, number) => number.saturating_mul(element_span),
+        (false, _) => bytes_to_read,
+    };
+    // If offset is different from 0, seek to the offset.
+    if offset > 0 {
+        match file_stream.seek_to(offset) {
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if guess_byte_order {
+        // Only what is buffered already, which doesn't consume anything from pipes.
+        let sample = match file_stream.fill_buf() {
+            Ok(bytes) if leb128.is_none() => {
+                &bytes[..std::cmp::min(bytes.len(), bytes_to_read as usize)]
+            }
+            Ok(_) => &[],
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let guess = endian::guess(
+            sample,
+            &fields,
+            args.skip_before as usize,
+            element_span as usize,
+        );
+        if args.detect_only {
+            println!(
+                "{}, confidence {:.0}% from {} values",
+                guess.byte_order,
+                guess.confidence * 100.0,
+                guess.values
+            );
+            return;
+        }
+        if !args.quiet {
+            eprintln!(
+                "Guessed {} byte order, confidence {:.0}% from {} values",
+                guess.byte_order,
+                guess.confidence * 100.0,
+                guess.values
+            );
+        }
+        args.byte_order = guess.byte_order;
+    }
+    // Colors are only for people looking at the values in a terminal.
+    let colored = match args.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            args.output.is_none()
+                && std::io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none()
+        }
+    };
+    let text_output = !(args.csv
+        || args.json
+        || args.stats_only
+        || args.histogram.is_some()
+        || args.value_counts
+        || args.plot
+        || args.crossings.is_some()
+        || args.emit_binary
+        || serial_encoding(&args).is_some()
+        || args.count_only
+        || args.count_special.is_some()
+        || args.complex
+        || args.split_columns.is_some());
+    let color = match (colored && text_output, args.color_min, args.color_max) {
+        (false, _, _) => None,
+        (true, Some(min), Some(max)) => Some(ColorScale::new(min, max)),
+        // Another reader finds the range as only regular files can be read twice.
+        (true, min, max) if regular_files && !args.follow => {
+            let range = MultiFileReader::open(&args.files, seek_sizes, &compressions).and_then(
+                |mut reader| {
+                    reader.seek_to(offset)?;
+                    value_range(reader.take(bytes_to_read as u64), &fields, &args)
+                },
+            );
+            match range {
+                Ok(Some((low, high))) => {
+                    Some(ColorScale::new(min.unwrap_or(low), max.unwrap_or(high)))
+                }
+                Ok(None) => Some(ColorScale::growing()),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        (true, _, _) => Some(ColorScale::growing()),
+    };
+    // All output goes through one buffer, flushed at the end or when waiting for data.
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => match std::fs::File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut out = std::io::BufWriter::new(out);
+    // Analyses of the raw bytes of the range, whatever their type.
+    if args.checksum.is_some() || args.entropy || args.byte_freq {
+        let mut digest = args.checksum.map(Digest::new);
+        let mut counts = (args.entropy || args.byte_freq).then(ByteCounts::new);
+        let mut reader = file_stream.take(bytes_to_read as u64);
+        let mut bytes = 0;
+        let mut buffer = vec![0; 65536];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if let Some(digest) = &mut digest {
+                        digest.update(&buffer[..n]);
+                    }
+                    if let Some(counts) = &mut counts {
+                        counts.push(&buffer[..n]);
+                    }
+                    bytes += n as u64;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if let Some(counts) = counts.as_ref().filter(|_| args.byte_freq) {
+            exit_on_error(counts.print(args.by_value, args.top, &mut out));
+        }
+        if let Some(counts) = counts.as_ref().filter(|_| args.entropy) {
+            let entropy = counts.entropy();
+            exit_on_error(writeln!(
+                out,
+                "entropy: {:.4} bits/byte, {} bytes",
+                entropy, bytes
+            ));
+        }
+        if let Some(digest) = digest {
+            exit_on_error(writeln!(out, "{}  {} bytes", digest.finish(), bytes));
+        }
+        exit_on_error(out.flush());
+        return;
+    }
+    if let Some(text) = &args.find {
+        let field = &output_type(&fields[0], &args);
+        let target = match field.parse(text) {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if args.tolerance.is_some() && !field.is_float() {
+            eprintln!("--tolerance is only supported for f32 and f64");
+            std::process::exit(1);
+        }
+        let matches = |value: Value| match (fixed_point(value, &args), args.tolerance) {
+            (value, Some(tolerance)) => (value.as_f64() - target.as_f64()).abs() <= tolerance,
+            // NaN is never equal to itself, but is looked for anyway.
+            (value, None) if target.as_f64().is_nan() => value.as_f64().is_nan(),
+            (value, None) => value == target,
+        };
+        let step = match args.unaligned {
+            true => 1,
+            false => element_span as usize,
+        };
+        let mut count = 0;
+        let found = |position: u64, value: Value| {
+            let index = match position % element_span as u64 {
+                0 => (position / element_span as u64).to_string(),
+                _ => "-".to_string(),
+            };
+            let text = args.format.value(fixed_point(value, &args));
+            exit_on_error(writeln!(out, "{} {} {}", offset + position, index, text));
+            count += 1;
+            args.max_matches.is_none_or(|max| count < max)
+        };
+        let mut reader = file_stream.take(bytes_to_read as u64);
+        exit_on_error(search::find_values(
+            &mut reader,
+            &fields[0],
+            &args.byte_order,
+            step,
+            matches,
+            found,
+        ));
+        exit_on_error(out.flush());
+        std::process::exit(if count == 0 { 1 } else { 0 });
+    }
+    if let Some(path) = &args.diff {
+        let field = &output_type(&fields[0], &args);
+        if (args.tolerance.is_some() || args.rel_tolerance.is_some()) && !field.is_float() {
+            eprintln!("--tolerance and --rel-tolerance are only supported for f32 and f64");
+            std::process::exit(1);
+        }
+        let their_offset = args.diff_offset.unwrap_or(offset);
+        let other = args
+            .compression
+            .detect(path, regular_files)
+            .and_then(|compression| {
+                let size = std::fs::metadata(path)?.len();
+                // Like the input, only uncompressed files can seek.
+                let sizes = [size];
+                let sizes = (compression == Compression::None).then_some(&sizes[..]);
+                let mut other =
+                    MultiFileReader::open(std::slice::from_ref(path), sizes, &[compression])?;
+                other.seek_to(their_offset)?;
+                Ok(other)
+            });
+        let other = match other {
+            Ok(other) => other,
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let ours = file_stream.take(bytes_to_read as u64);
+        // Without a range OTHER is read to its end to find out if it's longer.
+        let their_bytes = match (range_end, args.number, args.last) {
+            (None, i64::MAX, None) => u64::MAX,
+            _ => bytes_to_read as u64,
+        };
+        let theirs = other.take(their_bytes);
+        let offsets = (offset, their_offset);
+        let summary = match diff_values(ours, theirs, &fields, &args, offsets, &mut out) {
+            Ok(summary) => summary,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        match (summary.first, summary.max_difference) {
+            (None, _) => {
+                exit_on_error(writeln!(out, "no differences in {} values", summary.values))
+            }
+            (Some(first), max) => exit_on_error(writeln!(
+                out,
+                "{} of {} values differ, the first at {}{}",
+                summary.differences,
+                summary.values,
+                first,
+                // Differences from NaN have no size.
+                max.map_or(String::new(), |max| format!(", by at most {}", max))
+            )),
+        }
+        exit_on_error(out.flush());
+        if let Some(ours_shorter) = summary.ours_shorter {
+            let name = match (ours_shorter, &args.files[..]) {
+                (false, _) => path.as_str(),
+                (true, [file]) => file.as_str(),
+                (true, _) => "the input",
+            };
+            eprintln!("{} ends after {} values", name, summary.values);
+        }
+        let same = summary.differences == 0 && summary.ours_shorter.is_none();
+        std::process::exit(if same || !args.exit_code { 0 } else { 1 });
+    }
+    if let Some(path) = &args.expect {
+        let field = &output_type(&fields[0], &args);
+        if args.tolerance.is_some() && !field.is_float() {
+            eprintln!("--tolerance is only supported for f32 and f64");
+            std::process::exit(1);
+        }
+        let golden = match std::fs::read_to_string(path) {
+            Ok(golden) => golden,
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let ours = file_stream.take(bytes_to_read as u64);
+        match expect_values(ours, &golden, &fields, &args, offset) {
+            Ok(Expectation::Met(values, true)) if args.exact_length => {
+                eprintln!("{} lists {} values, but there are more", path, values);
+                std::process::exit(1);
+            }
+            Ok(Expectation::Met(values, more)) => {
+                if more && !args.quiet {
+                    eprintln!("{} lists only the first {} values", path, values);
+                }
+                exit_on_error(writeln!(out, "{} values match {}", values, path));
+            }
+            Ok(Expectation::Failed(message)) => {
+                exit_on_error(out.flush());
+                eprintln!("{}: {}", path, message);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        exit_on_error(out.flush());
+        return;
+    }
+    if args.with_header {
+        let count = (std::cmp::min(bytes_to_read, (file_size - offset) as i64)
+            + args.stride as i64)
+            / element_span;
+        let header =
+            container::Header::new(fields[0].clone(), args.byte_order.clone(), count as u64);
+        exit_on_error(out.write_all(&header.to_bytes()));
+    }
+    if args.eq.is_some() {
+        args.min = args.eq.clone();
+        args.max = args.eq.clone();
+    }
+    let mut filter = Vec::new();
+    if args.min.is_some() || args.max.is_some() {
+        let parse_bound = |field: &ParseType, bound: &Option<String>| match bound {
+            Some(bound) => match field.parse(bound) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            None => None,
+        };
+        filter = columns
+            .iter()
+            // The bounds are compared with the values as they are output.
+            .map(|field| output_type(field, &args))
+            .map(|field| {
+                (
+                    parse_bound(&field, &args.min),
+                    parse_bound(&field, &args.max),
+                )
+            })
+            .collect();
+    }
+    // No need for a buffer larger than what is going to be read.
+    let buffer_size = match size_known && !args.follow {
+        true => std::cmp::min(args.buffer_size, bytes_to_read as u64),
+        false => args.buffer_size,
+    };
+    let buffer_len = std::cmp::max(1, usize::try_from(buffer_size).unwrap_or(usize::MAX));
+    // The parser would abort if it couldn't allocate its buffer.
+    if Vec::<u8>::new().try_reserve_exact(buffer_len).is_err() {
+        eprintln!("Can't allocate a buffer of {} bytes", buffer_len);
+        std::process::exit(1);
+    }
+    // Read the file until the end of the file or the number of bytes to read.
+    let new_state = |measure: bool| OutputState {
+        current_row: 0,
+        stats: match args.stats || args.stats_only {
+            true => Some(vec![Stats::default(); args.row_size]),
+            false => None,
+        },
+        histograms: args.histogram.map(|bins| {
+            (0..args.row_size)
+                .map(|column| {
+                    let field = output_type(&columns[column % columns.len()], &args);
+                    let integer = !field.is_float();
+                    match measure {
+                        true => Histogram::measure(bins, integer, args.hist_min, args.hist_max),
+                        false => Histogram::new(bins, integer, args.hist_min, args.hist_max),
+                    }
+                })
+                .collect()
+        }),
+        value_counts: match args.value_counts {
+            true => Some(
+                (0..args.row_size)
+                    .map(|_| ValueCounts::new(args.max_distinct))
+                    .collect(),
+            ),
+            false => None,
+        },
+        plots: match args.plot {
+            true => Some(vec![Plot::default(); args.row_size]),
+            false => None,
+        },
+        crossings: args
+            .crossings
+            .map(|threshold| Crossings::new(threshold, args.hysteresis)),
+        split: None,
+        npy: None,
+        #[cfg(feature = "arrow")]
+        arrow: None,
+        #[cfg(feature = "parquet")]
+        parquet: None,
+        wav: None,
+        serial: serial_encoding(&args).map(|encoding| SerialWriter::new(encoding, args.as_array)),
+        json_started: false,
+        complex_re: None,
+        address: offset + args.skip_before,
+        address_width,
+        element_size: element_span as u64,
+        filter: filter.clone(),
+        ascii: String::new(),
+        color: color.clone(),
+        highlight_color: colored && !args.csv,
+        special: args.count_special.map(SpecialCounts::new),
+        leb128_lengths: None,
+        field_start: 0,
+        records: 0,
+        previous: match (args.delta, args.channels, args.channel) {
+            (false, _, _) => Vec::new(),
+            (true, Some(channels), None) => vec![None; columns.len() * channels as usize],
+            (true, _, _) => vec![None; columns.len()],
+        },
+        delta_column: 0,
+        sums: match (args.cumsum || args.cummean, args.channels, args.channel) {
+            (false, _, _) => Vec::new(),
+            (true, Some(channels), None) => vec![None; columns.len() * channels as usize],
+            (true, _, _) => vec![None; columns.len()],
+        },
+        sum_column: 0,
+        raw: String::new(),
+        line: Vec::new(),
+        raw_width: (0..args.row_size)
+            .map(|column| fields[column % fields.len()].size_of() as usize * 3 + 1)
+            .sum::<usize>()
+            .saturating_sub(2),
+        row_address: 0,
+        widths: match args.align {
+            true => (0..args.row_size)
+                .map(|column| {
+                    let width = args.width.unwrap_or_else(|| {
+                        args.format
+                            .field_width(&output_type(&columns[column % columns.len()], &args))
+                    });
+                    // Wide enough for the label, too.
+                    let label = args
+                        .labels
+                        .get(column)
+                        .map_or(0, |label| label.chars().count());
+                    std::cmp::max(width, label)
+                })
+                .collect(),
+            false => Vec::new(),
+        },
+        completed_rows: 0,
+        values: 0,
+        skipped_nan: 0,
+        clamped_db: 0,
+        matches: 0,
+        pending: Vec::new(),
+    };
+    let mut state = new_state(false);
+    // Where the input can be read twice the bins of --histogram span all values, found in a first pass.
+    let bounds_known = args.hist_min.is_some() && args.hist_max.is_some();
+    if args.histogram.is_some() && !bounds_known && regular_files && !args.follow {
+        let mut first = new_state(true);
+        let measured =
+            MultiFileReader::open(&args.files, seek_sizes, &compressions).and_then(|mut reader| {
+                reader.seek_to(offset)?;
+                let mut reader = reader.take(bytes_to_read as u64);
+                measure(
+                    &mut reader,
+                    &fields,
+                    &leb128,
+                    &args,
+                    &mut first,
+                    buffer_len,
+                    &cancel,
+                )
+            });
+        if let Err(e) = measured {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        if let (Some(histograms), Some(measured)) = (&mut state.histograms, first.histograms) {
+            for (histogram, measured) in histograms.iter_mut().zip(measured) {
+                *histogram = histogram.clone().with_bounds(measured.bounds());
+            }
+        }
+    }
+    if let (Some(columns), Some(template)) = (args.split_columns, &args.output_template) {
+        if !template.contains("{}") {
+            eprintln!("Output template must contain {{}}: {}", template);
+            std::process::exit(1);
+        }
+        let row_bytes = args.row_size as u64 * element_span as u64;
+        match SplitWriter::new(
+            template,
+            columns,
+            args.row_size,
+            args.delimiter,
+            offset + args.skip_before,
+            row_bytes,
+        ) {
+            Ok(split) => state.split = Some(split),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &args.npy {
+        let columns = match (args.layout.is_some(), args.row_size) {
+            (false, row_size) if row_size > 1 => Some(row_size as u64),
+            _ => None,
+        };
+        match NpyWriter::create(path, &fields, &args.byte_order, columns) {
+            Ok(npy) => state.npy = Some(npy),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    #[cfg(feature = "arrow")]
+    if let Some(path) = args.arrow.clone() {
+        let count = match args.layout.is_some() {
+            true => columns.len(),
+            false => args.row_size,
+        };
+        let types = (0..count)
+            .map(|column| arrow::column_type(&output_type(&columns[column % columns.len()], &args)))
+            .collect();
+        // The names go to the file instead of a header line.
+        let names = match std::mem::take(&mut args.header) {
+            header if header.is_empty() => {
+                (0..count).map(|column| format!("col{}", column)).collect()
+            }
+            header => header,
+        };
+        match ArrowWriter::create(&path, names, types, args.arrow_batch_size) {
+            Ok(arrow) => state.arrow = Some(arrow),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(path) = args.parquet.clone() {
+        let count = match args.layout.is_some() {
+            true => columns.len(),
+            false => args.row_size,
+        };
+        let types = (0..count)
+            .map(|column| output_type(&columns[column % columns.len()], &args))
+            .collect();
+        // The names go to the file instead of a header line.
+        let names = match std::mem::take(&mut args.header) {
+            header if header.is_empty() => {
+                (0..count).map(|column| format!("col{}", column)).collect()
+            }
+            header => header,
+        };
+        let codec = args.parquet_compression;
+        match ParquetWriter::create(&path, names, types, codec, args.row_group_size) {
+            Ok(parquet) => state.parquet = Some(parquet),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &args.wav_out {
+        let parse_type = output_type(&fields[0], &args);
+        if wav::sample_format(&parse_type).is_none() {
+            eprintln!(
+                "--wav-out doesn't support {} samples, only 8 to 32 bit integers, f32 and f64",
+                parse_type
+            );
+            std::process::exit(1);
+        }
+        let channels = match args.channel {
+            Some(_) => 1,
+            None => args.row_size,
+        };
+        let channels = match u16::try_from(channels) {
+            Ok(channels) => channels,
+            Err(_) => {
+                eprintln!("--wav-out supports at most {} channels", u16::MAX);
+                std::process::exit(1);
+            }
+        };
+        let sample_rate = args.sample_rate.unwrap_or(44100);
+        match WavWriter::create(path, &parse_type, channels, sample_rate) {
+            Ok(wav) => state.wav = Some(wav),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if gnuplot(&args) {
+        let types: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        exit_on_error(write!(
+            out,
+            "# type: {}\n# byte order: {}\n# offset: {}\n",
+            types.join(","),
+            args.byte_order,
+            offset
+        ));
+    }
+    if !args.labels.is_empty() {
+        exit_on_error(write_labels(&mut out, &args, &state));
+    }
+    if !args.header.is_empty() {
+        let names: Vec<String> = args
+            .addr
+            .map(|_| "offset".to_string())
+            .iter()
+            .chain(&args.header)
+            .map(|name| csv_field(name, args.delimiter))
+            .collect();
+        exit_on_error(write!(
+            out,
+            "{}{}",
+            names.join(&args.delimiter.to_string()),
+            record_sep(&args)
+        ));
+    }
+    // The array of --value-counts is printed with the counts.
+    if args.json && !json_lines(&args) && !args.value_counts {
+        exit_on_error(writeln!(out, "["));
+    }
+    // Large files are mapped without asking, only an explicit --mmap warns if it can't be.
+    let auto_mmap = !args.no_mmap
+        && args.bit_width.is_none()
+        && leb128.is_none()
+        && !args.follow
+        && size_known
+        && args.files.len() == 1
+        && bytes_to_read as u64 >= args.mmap_threshold;
+    let mapped = match args.mmap || auto_mmap {
+        true if args.files.len() > 1 => {
+            warn!(args, "can't memory map several files, reading them instead");
+            None
+        }
+        true if !size_known => {
+            warn!(
+                args,
+                "can't memory map input of unknown size, reading it instead"
+            );
+            None
+        }
+        // SAFETY: the mapping is only read, if the file is truncated concurrently the
+        // process may receive SIGBUS, just like other tools that map their input.
+        true => match std::fs::File::open(&args.files[0])
+            .and_then(|file| unsafe { memmap2::Mmap::map(&file) })
+        {
+            Ok(map) => Some(map),
+            Err(e) => {
+                warn!(args, "can't memory map the file, reading it instead: {}", e);
+                None
+            }
+        },
+        false => None,
+    };
+    // Bytes of a value cut short by the end of the input.
+    let mut trailing = Vec::new();
+    let mut bytes_read = 0;
+    // Packed input is unpacked and parsed a chunk at a time, this is the bytes it was read from.
+    let unpacked = match (args.bit_width, &leb128) {
+        (Some(width), _) => {
+            let bit_order = args
+                .bit_order
+                .unwrap_or_else(|| BitOrder::of(&args.byte_order));
+            let unpacked = exit_on_error(bits::unpack(
+                &mut (&mut file_stream).take(bytes_to_read as u64),
+                width,
+                bit_order,
+                &fields[0],
+                &args.byte_order,
+                args.number as u64,
+                |bytes| {
+                    let mut parser = record_parser(bytes, &fields, &args);
+                    parse_records(&mut parser, &mut out, &args, &mut state)?;
+                    Ok(!cancel.is_cancelled())
+                },
+            ));
+            if unpacked.trailing_bits > 0 {
+                warn!(
+                    args,
+                    "input is not a multiple of {} bits, ignoring {} trailing bits",
+                    width,
+                    unpacked.trailing_bits
+                );
+            }
+            Some(unpacked.packed_len)
+        }
+        (None, Some(parse_type)) => {
+            let unpacked = exit_on_error(leb128::unpack(
+                &mut (&mut file_stream).take(bytes_to_read as u64),
+                parse_type,
+                &args.byte_order,
+                args.number as u64,
+                |bytes, lengths| {
+                    if args.verbose {
+                        state.leb128_lengths = Some((state.address, lengths.to_vec()));
+                    }
+                    let mut parser = record_parser(bytes, &fields, &args);
+                    parse_records(&mut parser, &mut out, &args, &mut state)?;
+                    Ok(!cancel.is_cancelled())
+                },
+            ));
+            dropped += unpacked.trailing as i64;
+            if unpacked.trailing > 0 {
+                warn!(
+                    args,
+                    "input ends within a {} value, ignoring {} trailing bytes",
+                    parse_type,
+                    unpacked.trailing
+                );
+            }
+            Some(unpacked.packed_len)
+        }
+        (None, None) => None,
+    };
+    let tail = match (args.last, size_known, &unpacked) {
+        (Some(last), false, None) => {
+            let span = element_span as u64;
+            let keep = last.saturating_add(1).saturating_mul(span);
+            let (mut tail, total) = exit_on_error(read_tail(
+                &mut file_stream,
+                bytes_to_read as u64,
+                usize::try_from(keep).unwrap_or(usize::MAX),
+                &cancel,
+            ));
+            let skipped = skipped_before_last(total, last, element_span, frame_stride, &args);
+            // The ring buffer starts this many bytes into the input.
+            let start = total - tail.len() as u64;
+            tail.drain(..(skipped - start) as usize);
+            state.address += skipped;
+            Some(tail)
+        }
+        _ => None,
+    };
+    let data = match (&mapped, &tail) {
+        (Some(map), _) => {
+            let end = std::cmp::min(map.len(), offset as usize + bytes_to_read as usize);
+            Some(&map[offset as usize..end])
+        }
+        (None, Some(tail)) => Some(&tail[..]),
+        (None, None) => None,
+    };
+    if let Some(data) = data {
+        let mut parser = record_parser(data, &fields, &args)
+            .with_capacity(buffer_len)
+            .with_cancellation(cancel.clone());
+        exit_on_error(parse_records(&mut parser, &mut out, &args, &mut state));
+        bytes_read = parser.progress().bytes as i64;
+        trailing = parser.trailing().to_vec();
+    }
+    // Count what was read of the packed input rather than the unpacked values.
+    if let Some(packed_len) = unpacked {
+        bytes_read = packed_len as i64;
+    }
+    if data.is_none() && unpacked.is_none() {
+        let mut parser = record_parser(
+            (&mut file_stream).take(bytes_to_read as u64),
+            &fields,
+            &args,
+        )
+        .with_capacity(buffer_len)
+        .with_cancellation(cancel.clone());
+        // Index of the file whose --headers line was printed last.
+        let mut printed_header = None;
+        loop {
+            if exit_on_error(parser.read_record()) {
+                let index = parser.get_ref().get_ref().index();
+                if args.headers && args.files.len() > 1 && printed_header != Some(index) {
+                    if state.current_row != 0 {
+                        exit_on_error(finish_row(&mut out, &args, &mut state));
+                        state.current_row = 0;
+                    }
+                    if printed_header.is_some() {
+                        exit_on_error(writeln!(out));
+                    }
+                    exit_on_error(writeln!(out, "==> {} <==", args.files[index]));
+                    printed_header = Some(index);
+                }
+                exit_on_error(parse_record(parser.record(), &mut out, &args, &mut state));
+                continue;
+            }
+            let read = parser.progress().bytes;
+            if !args.follow || cancel.is_cancelled() || read >= bytes_to_read as u64 {
+                break;
+            }
+            exit_on_error(out.flush());
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            // Only the last file is followed, the others are not expected to change.
+            let last_start = file_size - file_sizes[file_sizes.len() - 1];
+            let truncated = match std::fs::metadata(&args.files[args.files.len() - 1]) {
+                Ok(meta) => last_start + meta.len() < offset + read,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if truncated {
+                eprintln!("File truncated, parsing again from offset {}", offset);
+                let stream = parser.into_inner().into_inner();
+                if let Err(e) = stream.seek_to(offset) {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                parser = record_parser(stream.take(bytes_to_read as u64), &fields, &args)
+                    .with_capacity(buffer_len)
+                    .with_cancellation(cancel.clone());
+            }
+        }
+        bytes_read = parser.progress().bytes as i64;
+        trailing = parser.trailing().to_vec();
+    }
+
+    // A last row shorter than the others.
+    exit_on_error(finish_filtered_row(&mut out, &args, &mut state));
+    let stats = state.stats.take();
+    match (&stats, &state.histograms) {
+        (_, Some(histograms)) => {
+            for (column, histogram) in histograms.iter().enumerate() {
+                if args.row_size > 1 {
+                    exit_on_error(writeln!(out, "column {}:", column));
+                }
+                let field = &columns[column % columns.len()];
+                let format = |edge: f64| format_summary(edge, field, &args);
+                let width = output_width(&args);
+                exit_on_error(histogram.print(&mut out, width, format));
+                if let Some(values) = histogram.sampled() {
+                    warn!(
+                        args,
+                        "the histogram bins span the first {} values, give --hist-min and --hist-max to set them",
+                        values
+                    );
+                }
+            }
+        }
+        (_, None) if state.value_counts.is_some() => {
+            let counts = state.value_counts.as_ref().unwrap();
+            exit_on_error(print_value_counts(&mut out, counts, &args));
+        }
+        (_, None) if state.crossings.is_some() => {
+            let crossings = state.crossings.as_ref().unwrap();
+            exit_on_error(crossings.print_summary(&mut out));
+        }
+        (_, None) if state.plots.is_some() => {
+            let width = output_width(&args);
+            // Block characters may not survive a file or a pipe.
+            let ascii = args.output.is_some() || !std::io::stdout().is_terminal();
+            for (column, plot) in state.plots.iter().flatten().enumerate() {
+                if args.row_size > 1 {
+                    exit_on_error(writeln!(out, "column {}:", column));
+                }
+                let field = &columns[column % columns.len()];
+                let format = |value: f64| format_summary(value, field, &args);
+                exit_on_error(plot.print(&mut out, width, args.plot_height, ascii, format));
+            }
+        }
+        (Some(stats), None) if args.stats_only => {
+            exit_on_error(print_stats(&mut out, stats, &columns, &args));
+        }
+        (_, None) => {
+            #[cfg(feature = "arrow")]
+            if let Some(arrow) = state.arrow.take() {
+                match arrow.finish() {
+                    Ok(0) => (),
+                    Ok(dropped) => warn!(
+                        args,
+                        "dropping {} values of the incomplete last row from the Arrow file",
+                        dropped
+                    ),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                // There is no row to end on the output.
+                state.current_row = 0;
+            }
+            #[cfg(feature = "parquet")]
+            if let Some(parquet) = state.parquet.take() {
+                match parquet.finish() {
+                    Ok(0) => (),
+                    Ok(dropped) => warn!(
+                        args,
+                        "dropping {} values of the incomplete last row from the Parquet file",
+                        dropped
+                    ),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                state.current_row = 0;
+            }
+            if let Some(split) = &mut state.split {
+                if let Err(e) = split.finish() {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            } else if let Some(serial) = state.serial.take() {
+                exit_on_error(serial.finish(&mut out));
+            } else if let Some(wav) = state.wav.take() {
+                match wav.finish() {
+                    Ok(0) => (),
+                    Ok(dropped) => warn!(
+                        args,
+                        "dropping {} bytes of the incomplete last frame from the .wav file",
+                        dropped
+                    ),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else if let Some(npy) = state.npy.take() {
+                match npy.finish() {
+                    Ok(0) => (),
+                    Ok(dropped) => warn!(
+                        args,
+                        "dropping {} values of the incomplete last row from the .npy file", dropped
+                    ),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else if args.json {
+                if state.current_row != 0 {
+                    exit_on_error(finish_json_row(&mut out, &args));
+                }
+                if !json_lines(&args) {
+                    if state.json_started {
+                        exit_on_error(writeln!(out));
+                    }
+                    exit_on_error(writeln!(out, "]"));
+                }
+            } else if let Some(special) = &state.special {
+                exit_on_error(special.print(&mut out));
+            } else if args.count_only {
+                let bytes = bytes_read - trailing.len() as i64;
+                exit_on_error(
+                    match (state.filter.is_empty(), args.any_column || args.all_columns) {
+                        (true, _) => write!(out, "{} values, {} bytes", state.values, bytes),
+                        (false, false) => write!(
+                            out,
+                            "{} of {} values match, {} bytes",
+                            state.matches, state.values, bytes
+                        ),
+                        (false, true) => write!(
+                            out,
+                            "{} of {} rows match, {} bytes",
+                            state.matches,
+                            state.values.div_ceil(args.row_size as u64),
+                            bytes
+                        ),
+                    },
+                );
+                let ignored = trailing.len() as i64 + dropped;
+                if ignored > 0 {
+                    exit_on_error(write!(out, ", {} trailing bytes ignored", ignored));
+                }
+                exit_on_error(writeln!(out));
+            } else if state.current_row != 0 {
+                exit_on_error(finish_row(&mut out, &args, &mut state));
+            }
+            if let Some(stats) = &stats {
+                exit_on_error(writeln!(out));
+                exit_on_error(print_stats(&mut out, stats, &columns, &args));
+            }
+        }
+    }
+    // A value cut short by the end of the input, a range that isn't a whole
+    // number of values has already been warned about.
+    if !trailing.is_empty() && !cancel.is_cancelled() && args.reencode.is_some() {
+        exit_on_error(out.write_all(&trailing));
+        warn!(
+            args,
+            "input is not a multiple of {} bytes, copying {} trailing bytes unchanged",
+            element_span,
+            trailing.len()
+        );
+    } else if !trailing.is_empty() && !cancel.is_cancelled() {
+        warn!(
+            args,
+            "input is not a multiple of {} bytes, ignoring {} trailing bytes",
+            element_span,
+            trailing.len()
+        );
+    }
+    if state.skipped_nan > 0 && !args.quiet {
+        eprintln!("Skipped {} NaN and infinite values", state.skipped_nan);
+    }
+    if state.clamped_db > 0 && !args.quiet {
+        eprintln!(
+            "Clamped {} values to the floor of {} dB",
+            state.clamped_db, args.db_floor
+        );
+    }
+    if state.complex_re.is_some() {
+        warn!(
+            args,
+            "ignoring the real part of an incomplete complex value at the end"
+        );
+    }
+    exit_on_error(out.flush());
+    if cancel.is_cancelled() {
+        std::process::exit(130);
     }
 }
@@ -0,0 +1,114 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Seek;
+
+use crate::compression;
+use crate::compression::Compression;
+
+/// Reads several files one after another as one logical stream.
+///
+/// The next file is opened once the current one is exhausted, so values that
+/// straddle a file boundary are simply split over two reads. The last file is
+/// never left, it can be read again after data has been appended to it.
+/// Compressed files are decompressed while reading. Without known sizes, e.g.
+/// for compressed files or pipes, seeking reads and discards bytes instead.
+pub struct MultiFileReader {
+    paths: Vec<String>,
+    sizes: Option<Vec<u64>>,
+    compressions: Vec<Compression>,
+    index: usize,
+    reader: BufReader<Box<dyn Read>>,
+    // Bytes read so far, used to seek forward by discarding.
+    position: u64,
+}
+
+impl MultiFileReader {
+    /// Opens the first of `paths`, `sizes` are their sizes used for seeking if
+    /// known and `compressions` the already detected compression of each file.
+    pub fn open(
+        paths: &[String],
+        sizes: Option<&[u64]>,
+        compressions: &[Compression],
+    ) -> std::io::Result<Self> {
+        Ok(MultiFileReader {
+            paths: paths.to_vec(),
+            sizes: sizes.map(|sizes| sizes.to_vec()),
+            compressions: compressions.to_vec(),
+            index: 0,
+            reader: BufReader::new(compression::open(&paths[0], compressions[0])?),
+            position: 0,
+        })
+    }
+
+    /// Index of the file the last read came from.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Moves to `position` in the logical stream.
+    pub fn seek_to(&mut self, position: u64) -> std::io::Result<()> {
+        let sizes = match &self.sizes {
+            Some(sizes) => sizes,
+            None => {
+                if position < self.position {
+                    self.open_file(0)?;
+                    self.position = 0;
+                }
+                let skip = position - self.position;
+                std::io::copy(&mut self.by_ref().take(skip), &mut std::io::sink())?;
+                return Ok(());
+            }
+        };
+        let mut index = 0;
+        let mut start = 0;
+        while index + 1 < self.paths.len() && position >= start + sizes[index] {
+            start += sizes[index];
+            index += 1;
+        }
+        let mut file = std::fs::File::open(&self.paths[index])?;
+        // Some special files have a size but can't seek, skip by reading instead.
+        if file
+            .seek(std::io::SeekFrom::Start(position - start))
+            .is_err()
+        {
+            let skip = position - start;
+            std::io::copy(&mut file.by_ref().take(skip), &mut std::io::sink())?;
+        }
+        self.reader = BufReader::new(Box::new(file));
+        self.index = index;
+        self.position = position;
+        Ok(())
+    }
+
+    fn open_file(&mut self, index: usize) -> std::io::Result<()> {
+        let file = compression::open(&self.paths[index], self.compressions[index])?;
+        self.reader = BufReader::new(file);
+        self.index = index;
+        Ok(())
+    }
+}
+
+impl Read for MultiFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let n = self.reader.read(buf)?;
+            self.position += n as u64;
+            if n > 0 || buf.is_empty() || self.index + 1 == self.paths.len() {
+                return Ok(n);
+            }
+            self.open_file(self.index + 1)?;
+        }
+    }
+}
+
+impl BufRead for MultiFileReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt as u64;
+        self.reader.consume(amt)
+    }
+}
@@ -0,0 +1,370 @@
+//! Reader and writer of NumPy `.npy` files, written as format version 1.0.
+//!
+//! The file starts with the magic `\x93NUMPY`, the version bytes 1 and 0, the
+//! little-endian u16 length of the header and the header itself, a Python dict
+//! literal with the dtype, the memory order and the shape, padded with spaces
+//! and a newline so the data starts at a multiple of 64 bytes. Versions 2.0
+//! and 3.0 only differ in a u32 header length.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Seek;
+use std::io::Write;
+
+use crate::{ByteOrder, ParseType};
+
+const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+/// Start of every .npy file, followed by the version.
+const MAGIC_PREFIX: &[u8] = b"\x93NUMPY";
+
+/// Room for the shape, which is only known once all values are written.
+const SHAPE_WIDTH: usize = 48;
+
+/// NumPy type string of `parse_type`, e.g. `<f4`. 24 bit integers have no
+/// NumPy type and are widened to 32 bits.
+pub fn dtype(parse_type: &ParseType, byte_order: &ByteOrder) -> String {
+    let order = match byte_order {
+        ByteOrder::LittleEndian => '<',
+        ByteOrder::BigEndian => '>',
+        ByteOrder::Native if cfg!(target_endian = "big") => '>',
+        ByteOrder::Native => '<',
+    };
+    let (kind, size) = match parse_type {
+        ParseType::U8 => return "|u1".to_string(),
+        ParseType::I8 => return "|i1".to_string(),
+        ParseType::U16 => ('u', 2),
+        ParseType::U32 | ParseType::U24 => ('u', 4),
+        ParseType::U64 | ParseType::Uleb128 => ('u', 8),
+        ParseType::I16 => ('i', 2),
+        ParseType::I32 | ParseType::I24 => ('i', 4),
+        ParseType::I64 | ParseType::Sleb128 => ('i', 8),
+        ParseType::F32 => ('f', 4),
+        ParseType::F64 => ('f', 8),
+    };
+    format!("{}{}{}", order, kind, size)
+}
+
+/// Header of a .npy file with a dtype parsebin can parse.
+#[derive(Debug)]
+pub struct NpyHeader {
+    pub version: (u8, u8),
+    pub descr: String,
+    pub parse_type: ParseType,
+    pub byte_order: ByteOrder,
+    pub fortran_order: bool,
+    pub shape: Vec<u64>,
+    /// Bytes up to the start of the data.
+    pub header_len: u64,
+}
+
+/// Value of `key` in the header dict, everything after the colon.
+fn dict_value<'a>(dict: &'a str, key: &str) -> Option<&'a str> {
+    let start = dict
+        .find(&format!("'{}':", key))
+        .or_else(|| dict.find(&format!("\"{}\":", key)))?;
+    Some(dict[start + key.len() + 3..].trim_start())
+}
+
+impl NpyHeader {
+    /// Parses a header from the start of `bytes`.
+    ///
+    /// Returns `None` if the bytes don't start with the magic, and an error if
+    /// they do but the header is truncated or its dtype unsupported.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Result<NpyHeader, String>> {
+        if !bytes.starts_with(MAGIC_PREFIX) {
+            return None;
+        }
+        let truncated = || Err("Truncated NumPy header".to_string());
+        let (version, rest) = match bytes.get(6..) {
+            Some([major, minor, rest @ ..]) => ((*major, *minor), rest),
+            _ => return Some(truncated()),
+        };
+        let (len, start) = match (version.0, rest) {
+            (1, [a, b, ..]) => (u16::from_le_bytes([*a, *b]) as usize, 10),
+            (2 | 3, [a, b, c, d, ..]) => (u32::from_le_bytes([*a, *b, *c, *d]) as usize, 12),
+            (1..=3, _) => return Some(truncated()),
+            _ => {
+                return Some(Err(format!(
+                    "Unsupported NumPy format version: {}.{}",
+                    version.0, version.1
+                )))
+            }
+        };
+        let dict = match bytes.get(start..start + len) {
+            Some(dict) => String::from_utf8_lossy(dict),
+            None => return Some(truncated()),
+        };
+        let descr = match dict_value(&dict, "descr") {
+            Some(value) if value.starts_with('\'') || value.starts_with('"') => {
+                let quote = value.as_bytes()[0] as char;
+                value[1..].split(quote).next().unwrap_or("").to_string()
+            }
+            Some(value) if value.starts_with('[') => {
+                return Some(Err("Structured NumPy dtypes are not supported".to_string()))
+            }
+            _ => return Some(Err("NumPy header has no dtype".to_string())),
+        };
+        let (order, kind) = match descr.chars().next() {
+            Some(order @ ('<' | '>' | '|' | '=')) => (order, &descr[1..]),
+            _ => ('=', &descr[..]),
+        };
+        let parse_type = match kind {
+            "u1" | "b1" => ParseType::U8,
+            "i1" => ParseType::I8,
+            "u2" => ParseType::U16,
+            "i2" => ParseType::I16,
+            "u4" => ParseType::U32,
+            "i4" => ParseType::I32,
+            "u8" => ParseType::U64,
+            "i8" => ParseType::I64,
+            "f4" => ParseType::F32,
+            "f8" => ParseType::F64,
+            _ => return Some(Err(format!("Unsupported NumPy dtype '{}'", descr))),
+        };
+        let byte_order = match order {
+            '<' => ByteOrder::LittleEndian,
+            '>' => ByteOrder::BigEndian,
+            _ => ByteOrder::Native,
+        };
+        let fortran_order =
+            dict_value(&dict, "fortran_order").is_some_and(|value| value.starts_with("True"));
+        let shape = match dict_value(&dict, "shape").and_then(|value| value.strip_prefix('(')) {
+            Some(value) => value
+                .split(')')
+                .next()
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|axis| !axis.is_empty())
+                .map(|axis| axis.parse())
+                .collect::<Result<Vec<u64>, _>>(),
+            None => return Some(Err("NumPy header has no shape".to_string())),
+        };
+        let shape = match shape {
+            Ok(shape) => shape,
+            Err(e) => return Some(Err(format!("Invalid shape in NumPy header: {}", e))),
+        };
+        Some(Ok(NpyHeader {
+            version,
+            descr,
+            parse_type,
+            byte_order,
+            fortran_order,
+            shape,
+            header_len: (start + len) as u64,
+        }))
+    }
+
+    /// Number of values in the array.
+    pub fn count(&self) -> u64 {
+        self.shape.iter().product()
+    }
+}
+
+/// Writes the values of one type, or records of several fields as a structured
+/// array, and fills in the shape when finished.
+pub struct NpyWriter {
+    file: BufWriter<File>,
+    descr: String,
+    // Values per row of a 2D array, `None` for a 1D array.
+    columns: Option<u64>,
+    item_size: u64,
+    bytes: u64,
+}
+
+impl NpyWriter {
+    /// Creates `path` for items of `fields`, a record of several fields is one
+    /// item. `columns` gives a 2D array with that many items per row.
+    pub fn create(
+        path: &str,
+        fields: &[ParseType],
+        byte_order: &ByteOrder,
+        columns: Option<u64>,
+    ) -> std::io::Result<Self> {
+        let descr = match fields {
+            [field] => format!("'{}'", dtype(field, byte_order)),
+            _ => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| format!("('f{}', '{}')", i, dtype(field, byte_order)))
+                    .collect();
+                format!("[{}]", fields.join(", "))
+            }
+        };
+        let item_size = fields
+            .iter()
+            .map(|field| match field {
+                ParseType::U24 | ParseType::I24 => 4,
+                ParseType::U8 | ParseType::I8 => 1,
+                ParseType::U16 | ParseType::I16 => 2,
+                ParseType::U32 | ParseType::I32 | ParseType::F32 => 4,
+                ParseType::U64 | ParseType::I64 | ParseType::F64 => 8,
+                ParseType::Uleb128 | ParseType::Sleb128 => 8,
+            })
+            .sum();
+        let mut writer = NpyWriter {
+            file: BufWriter::new(File::create(path)?),
+            descr,
+            columns,
+            item_size,
+            bytes: 0,
+        };
+        let header = writer.header("()");
+        writer.file.write_all(&header)?;
+        Ok(writer)
+    }
+
+    fn header(&self, shape: &str) -> Vec<u8> {
+        let dict = format!(
+            "{{'descr': {}, 'fortran_order': False, 'shape': {:<width$}}}",
+            self.descr,
+            shape,
+            width = SHAPE_WIDTH
+        );
+        // Magic, header length, dict and the newline are padded to 64 bytes.
+        let unpadded = MAGIC.len() + 2 + dict.len() + 1;
+        let padding = (64 - unpadded % 64) % 64;
+        let len = (dict.len() + padding + 1) as u16;
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&len.to_le_bytes());
+        header.extend_from_slice(dict.as_bytes());
+        header.extend(std::iter::repeat_n(b' ', padding));
+        header.push(b'\n');
+        header
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.bytes += bytes.len() as u64;
+        self.file.write_all(bytes)
+    }
+
+    /// Writes the shape into the header and returns the number of items of an
+    /// incomplete last row which are dropped.
+    pub fn finish(self) -> std::io::Result<u64> {
+        let items = self.bytes / self.item_size;
+        let (shape, dropped) = match self.columns {
+            None => (format!("({},)", items), 0),
+            Some(columns) => (
+                format!("({}, {})", items / columns, columns),
+                items % columns,
+            ),
+        };
+        let header = self.header(&shape);
+        let mut file = self.file.into_inner().map_err(|e| e.into_error())?;
+        file.set_len(header.len() as u64 + (items - dropped) * self.item_size)?;
+        file.seek(std::io::SeekFrom::Start(0))?;
+        file.write_all(&header)?;
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("parsebin-npy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name).to_string_lossy().into_owned()
+    }
+
+    /// The dict of a version 1.0 file after checking the magic, the version
+    /// and the padding, and the data after it.
+    fn split_header(bytes: &[u8]) -> (String, &[u8]) {
+        assert_eq!(&bytes[..6], b"\x93NUMPY");
+        assert_eq!(&bytes[6..8], [1, 0]);
+        let len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + len) % 64, 0, "header of {} bytes", len);
+        assert_eq!(bytes[10 + len - 1], b'\n');
+        let dict = std::str::from_utf8(&bytes[10..10 + len - 1]).unwrap();
+        // The shape is padded inside of the dict, so it can be filled in later.
+        let dict = dict.trim_end_matches(' ').strip_suffix('}').unwrap();
+        (format!("{}}}", dict.trim_end()), &bytes[10 + len..])
+    }
+
+    #[test]
+    fn header_of_a_written_array() {
+        let path = temp_path("values.npy");
+        let mut writer =
+            NpyWriter::create(&path, &[ParseType::F32], &ByteOrder::BigEndian, None).unwrap();
+        for v in [1.5f32, -2.0, 3.25] {
+            writer.write(&v.to_be_bytes()).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 0);
+        let bytes = std::fs::read(&path).unwrap();
+        let (dict, data) = split_header(&bytes);
+        assert_eq!(
+            dict,
+            "{'descr': '>f4', 'fortran_order': False, 'shape': (3,)}"
+        );
+        let values: Vec<f32> = data
+            .chunks(4)
+            .map(|v| f32::from_be_bytes(v.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, [1.5, -2.0, 3.25]);
+        // Read back like any other .npy file.
+        let header = NpyHeader::from_bytes(&bytes).unwrap().unwrap();
+        assert_eq!(header.version, (1, 0));
+        assert_eq!(header.descr, ">f4");
+        assert_eq!(header.parse_type, ParseType::F32);
+        assert_eq!(header.byte_order, ByteOrder::BigEndian);
+        assert_eq!(header.shape, [3]);
+        assert_eq!(header.header_len, (bytes.len() - data.len()) as u64);
+    }
+
+    #[test]
+    fn rows_drop_an_incomplete_last_one() {
+        let path = temp_path("rows.npy");
+        let mut writer =
+            NpyWriter::create(&path, &[ParseType::I64], &ByteOrder::LittleEndian, Some(2)).unwrap();
+        for v in [1i64, 2, 3, 4, 5] {
+            writer.write(&v.to_le_bytes()).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 1);
+        let bytes = std::fs::read(&path).unwrap();
+        let (dict, data) = split_header(&bytes);
+        assert_eq!(
+            dict,
+            "{'descr': '<i8', 'fortran_order': False, 'shape': (2, 2)}"
+        );
+        assert_eq!(data.len(), 4 * 8);
+    }
+
+    #[test]
+    fn records_are_structured() {
+        let path = temp_path("records.npy");
+        let fields = [ParseType::U8, ParseType::U24, ParseType::F64];
+        let writer = NpyWriter::create(&path, &fields, &ByteOrder::LittleEndian, None).unwrap();
+        writer.finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let (dict, data) = split_header(&bytes);
+        assert_eq!(
+            dict,
+            "{'descr': [('f0', '|u1'), ('f1', '<u4'), ('f2', '<f8')], 'fortran_order': False, 'shape': (0,)}"
+        );
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn headers_of_other_versions() {
+        let dict = "{'descr': '<u2', 'fortran_order': True, 'shape': (3, 4), }\n";
+        let mut bytes = b"\x93NUMPY\x02\x00".to_vec();
+        bytes.extend_from_slice(&(dict.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(dict.as_bytes());
+        let header = NpyHeader::from_bytes(&bytes).unwrap().unwrap();
+        assert_eq!(header.version, (2, 0));
+        assert_eq!(header.parse_type, ParseType::U16);
+        assert!(header.fortran_order);
+        assert_eq!(header.count(), 12);
+        assert_eq!(header.header_len, bytes.len() as u64);
+        // Cut off in the middle of the dict.
+        let error = NpyHeader::from_bytes(&bytes[..20]).unwrap().unwrap_err();
+        assert_eq!(error, "Truncated NumPy header");
+        bytes[6] = 4;
+        let error = NpyHeader::from_bytes(&bytes).unwrap().unwrap_err();
+        assert_eq!(error, "Unsupported NumPy format version: 4.0");
+        assert!(NpyHeader::from_bytes(b"raw data").is_none());
+    }
+}
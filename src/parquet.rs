@@ -0,0 +1,854 @@
+//! Writer of Apache Parquet files for --parquet.
+//!
+//! The file starts and ends with the magic `PAR1`. In between are the row
+//! groups, for every column a chunk of a single PLAIN encoded data page,
+//! and the footer with the schema and where the chunks are. Page headers
+//! and the footer are Thrift structs in the compact protocol. All columns
+//! are required, so pages have no repetition or definition levels.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use clap::ValueEnum;
+
+use parsebin::ParseType;
+use parsebin::Value;
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Types of the compact protocol.
+const BOOL_TRUE: u8 = 1;
+const BOOL_FALSE: u8 = 2;
+const BYTE: u8 = 3;
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+// Physical types.
+const INT32: i32 = 1;
+const INT64: i32 = 2;
+const FLOAT: i32 = 4;
+const DOUBLE: i32 = 5;
+
+// Encodings.
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+
+const DATA_PAGE: i32 = 0;
+const REQUIRED: i32 = 0;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    /// The CompressionCodec of the format.
+    fn id(self) -> i32 {
+        match self {
+            Codec::None => 0,
+            Codec::Snappy => 1,
+            Codec::Gzip => 2,
+            Codec::Zstd => 6,
+        }
+    }
+
+    fn compress(self, bytes: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(bytes),
+            Codec::Snappy => Ok(snappy(&bytes)),
+            Codec::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&bytes)?;
+                encoder.finish()
+            }
+            Codec::Zstd => zstd::encode_all(&bytes[..], 0),
+        }
+    }
+}
+
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Compresses `bytes` to the raw Snappy format: their length followed by
+/// literals and copies of 4 or more bytes found by a hash of the next 4.
+fn snappy(bytes: &[u8]) -> Vec<u8> {
+    const HASH_BITS: u32 = 14;
+    fn literal(out: &mut Vec<u8>, bytes: &[u8]) {
+        let len = bytes.len() - 1;
+        match len {
+            0..=59 => out.push((len as u8) << 2),
+            _ => {
+                let size = (usize::BITS - len.leading_zeros()).div_ceil(8);
+                out.push((59 + size as u8) << 2);
+                out.extend(&len.to_le_bytes()[..size as usize]);
+            }
+        }
+        out.extend(bytes);
+    }
+    let mut out = Vec::new();
+    varint(&mut out, bytes.len() as u64);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let (mut at, mut pending) = (0, 0);
+    while at + 4 <= bytes.len() {
+        let next = u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let hash = (next.wrapping_mul(0x1e35_a7bd) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[hash], at);
+        if candidate == usize::MAX
+            || at - candidate > u16::MAX as usize
+            || bytes[candidate..candidate + 4] != bytes[at..at + 4]
+        {
+            at += 1;
+            continue;
+        }
+        if pending < at {
+            literal(&mut out, &bytes[pending..at]);
+        }
+        let mut len = 4;
+        while at + len < bytes.len() && bytes[candidate + len] == bytes[at + len] {
+            len += 1;
+        }
+        let offset = (at - candidate) as u16;
+        for start in (0..len).step_by(64) {
+            out.push(((len - start).min(64) as u8 - 1) << 2 | 2);
+            out.extend(offset.to_le_bytes());
+        }
+        at += len;
+        pending = at;
+    }
+    if pending < bytes.len() {
+        literal(&mut out, &bytes[pending..]);
+    }
+    out
+}
+
+/// Encoder of Thrift structs in the compact protocol. Fields are written in
+/// the order of their ids, as deltas from the previous one of the struct.
+struct Encoder {
+    bytes: Vec<u8>,
+    // Last field id of every struct being written, the innermost last.
+    last_ids: Vec<i16>,
+}
+
+impl Encoder {
+    fn new() -> Self {
+        Encoder {
+            bytes: Vec::new(),
+            last_ids: vec![0],
+        }
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_ids.last_mut().unwrap();
+        match id - *last {
+            delta @ 1..=15 => self.bytes.push((delta as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(kind);
+                varint(&mut self.bytes, ((id << 1) ^ (id >> 15)) as u16 as u64);
+            }
+        }
+        *last = id;
+    }
+
+    fn int(&mut self, value: i64) {
+        varint(&mut self.bytes, ((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn string(&mut self, s: &str) {
+        varint(&mut self.bytes, s.len() as u64);
+        self.bytes.extend(s.as_bytes());
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.int(value as i64);
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        self.int(value);
+    }
+
+    fn byte_field(&mut self, id: i16, value: i8) {
+        self.field(id, BYTE);
+        self.bytes.push(value as u8);
+    }
+
+    fn bool_field(&mut self, id: i16, value: bool) {
+        self.field(id, if value { BOOL_TRUE } else { BOOL_FALSE });
+    }
+
+    fn string_field(&mut self, id: i16, s: &str) {
+        self.field(id, BINARY);
+        self.string(s);
+    }
+
+    fn list_field(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        match len {
+            0..=14 => self.bytes.push((len as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(0xf0 | kind);
+                varint(&mut self.bytes, len as u64);
+            }
+        }
+    }
+
+    /// Starts a struct that is a field, ended with [`Encoder::end`].
+    fn struct_field(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.last_ids.push(0);
+    }
+
+    /// Starts a struct that is an element of a list.
+    fn struct_element(&mut self) {
+        self.last_ids.push(0);
+    }
+
+    fn end(&mut self) {
+        self.bytes.push(0);
+        self.last_ids.pop();
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.end();
+        self.bytes
+    }
+}
+
+/// The physical type of values of `parse_type` and the converted type, bit
+/// width and signedness of integers that aren't 32 or 64 bit signed ones.
+/// 8, 16 and 24 bit integers are widened to INT32.
+fn column_type(parse_type: &ParseType) -> (i32, Option<(i32, i8, bool)>) {
+    match parse_type {
+        ParseType::U8 => (INT32, Some((11, 8, false))),
+        ParseType::U16 => (INT32, Some((12, 16, false))),
+        ParseType::U24 | ParseType::U32 => (INT32, Some((13, 32, false))),
+        ParseType::U64 | ParseType::Uleb128 => (INT64, Some((14, 64, false))),
+        ParseType::I8 => (INT32, Some((15, 8, true))),
+        ParseType::I16 => (INT32, Some((16, 16, true))),
+        ParseType::I24 | ParseType::I32 => (INT32, None),
+        ParseType::I64 | ParseType::Sleb128 => (INT64, None),
+        ParseType::F32 => (FLOAT, None),
+        ParseType::F64 => (DOUBLE, None),
+    }
+}
+
+/// Where a column chunk of a row group was written.
+struct Chunk {
+    offset: u64,
+    uncompressed: u64,
+    compressed: u64,
+}
+
+/// Writes values a row at a time as columns of a Parquet file, a row group
+/// every `row_group_size` rows.
+pub struct ParquetWriter {
+    file: BufWriter<File>,
+    names: Vec<String>,
+    types: Vec<ParseType>,
+    codec: Codec,
+    row_group_size: usize,
+    // PLAIN encoded values of the current row group, a column after another.
+    columns: Vec<Vec<u8>>,
+    // Column of the next value.
+    column: usize,
+    rows: usize,
+    position: u64,
+    // Rows and chunks of the row groups written.
+    row_groups: Vec<(usize, Vec<Chunk>)>,
+}
+
+impl ParquetWriter {
+    /// Creates `path` with a column of every type of `types` named `names`.
+    pub fn create(
+        path: &str,
+        names: Vec<String>,
+        types: Vec<ParseType>,
+        codec: Codec,
+        row_group_size: usize,
+    ) -> std::io::Result<Self> {
+        let mut writer = ParquetWriter {
+            file: BufWriter::new(File::create(path)?),
+            columns: vec![Vec::new(); types.len()],
+            names,
+            types,
+            codec,
+            row_group_size,
+            column: 0,
+            rows: 0,
+            position: 0,
+            row_groups: Vec::new(),
+        };
+        writer.write(MAGIC)?;
+        Ok(writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.position += bytes.len() as u64;
+        self.file.write_all(bytes)
+    }
+
+    /// Adds `value` to the next column, converted to its physical type.
+    pub fn push(&mut self, value: Value) -> std::io::Result<()> {
+        let bytes = &mut self.columns[self.column];
+        // Unsigned integers keep their bits in the signed physical types.
+        match column_type(&self.types[self.column]).0 {
+            INT32 => bytes.extend((value.as_i128().unwrap_or(0) as i32).to_le_bytes()),
+            INT64 => bytes.extend((value.as_i128().unwrap_or(0) as i64).to_le_bytes()),
+            FLOAT => bytes.extend((value.as_f64() as f32).to_le_bytes()),
+            _ => bytes.extend(value.as_f64().to_le_bytes()),
+        }
+        self.column = (self.column + 1) % self.types.len();
+        if self.column == 0 {
+            self.rows += 1;
+            if self.rows == self.row_group_size {
+                self.write_row_group()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self) -> std::io::Result<()> {
+        let mut chunks = Vec::new();
+        for column in 0..self.columns.len() {
+            let values = std::mem::take(&mut self.columns[column]);
+            let uncompressed = values.len();
+            let page = self.codec.compress(values)?;
+            let mut header = Encoder::new();
+            header.i32_field(1, DATA_PAGE);
+            header.i32_field(2, uncompressed as i32);
+            header.i32_field(3, page.len() as i32);
+            header.struct_field(5);
+            header.i32_field(1, self.rows as i32);
+            header.i32_field(2, PLAIN);
+            header.i32_field(3, RLE);
+            header.i32_field(4, RLE);
+            header.end();
+            let header = header.finish();
+            chunks.push(Chunk {
+                offset: self.position,
+                uncompressed: (header.len() + uncompressed) as u64,
+                compressed: (header.len() + page.len()) as u64,
+            });
+            self.write(&header)?;
+            self.write(&page)?;
+        }
+        self.row_groups.push((self.rows, chunks));
+        self.rows = 0;
+        Ok(())
+    }
+
+    /// The FileMetaData of the footer.
+    fn metadata(&self) -> Vec<u8> {
+        let mut metadata = Encoder::new();
+        metadata.i32_field(1, 1);
+        metadata.list_field(2, STRUCT, self.names.len() + 1);
+        metadata.struct_element();
+        metadata.string_field(4, "schema");
+        metadata.i32_field(5, self.names.len() as i32);
+        metadata.end();
+        for (name, parse_type) in self.names.iter().zip(&self.types) {
+            let (physical, integer) = column_type(parse_type);
+            metadata.struct_element();
+            metadata.i32_field(1, physical);
+            metadata.i32_field(3, REQUIRED);
+            metadata.string_field(4, name);
+            if let Some((converted, bits, signed)) = integer {
+                metadata.i32_field(6, converted);
+                // The INTEGER member of the LogicalType union.
+                metadata.struct_field(10);
+                metadata.struct_field(10);
+                metadata.byte_field(1, bits);
+                metadata.bool_field(2, signed);
+                metadata.end();
+                metadata.end();
+            }
+            metadata.end();
+        }
+        let rows: usize = self.row_groups.iter().map(|(rows, _)| rows).sum();
+        metadata.i64_field(3, rows as i64);
+        metadata.list_field(4, STRUCT, self.row_groups.len());
+        for (rows, chunks) in &self.row_groups {
+            metadata.struct_element();
+            metadata.list_field(1, STRUCT, chunks.len());
+            for (chunk, (name, parse_type)) in chunks.iter().zip(self.names.iter().zip(&self.types))
+            {
+                metadata.struct_element();
+                metadata.i64_field(2, chunk.offset as i64);
+                metadata.struct_field(3);
+                metadata.i32_field(1, column_type(parse_type).0);
+                metadata.list_field(2, I32, 1);
+                metadata.int(PLAIN as i64);
+                metadata.list_field(3, BINARY, 1);
+                metadata.string(name);
+                metadata.i32_field(4, self.codec.id());
+                metadata.i64_field(5, *rows as i64);
+                metadata.i64_field(6, chunk.uncompressed as i64);
+                metadata.i64_field(7, chunk.compressed as i64);
+                metadata.i64_field(9, chunk.offset as i64);
+                metadata.end();
+                metadata.end();
+            }
+            let size: u64 = chunks.iter().map(|chunk| chunk.uncompressed).sum();
+            metadata.i64_field(2, size as i64);
+            metadata.i64_field(3, *rows as i64);
+            metadata.end();
+        }
+        let created_by = format!("parsebin version {}", env!("CARGO_PKG_VERSION"));
+        metadata.string_field(6, &created_by);
+        metadata.finish()
+    }
+
+    /// Writes the last row group and the footer and returns the number of
+    /// values of an incomplete last row, which are dropped.
+    pub fn finish(mut self) -> std::io::Result<usize> {
+        let dropped = self.column;
+        for (column, parse_type) in self.columns.iter_mut().zip(&self.types) {
+            let size = match column_type(parse_type).0 {
+                INT32 | FLOAT => 4,
+                _ => 8,
+            };
+            column.truncate(self.rows * size);
+        }
+        if self.rows > 0 {
+            self.write_row_group()?;
+        }
+        let metadata = self.metadata();
+        self.write(&metadata)?;
+        self.write(&(metadata.len() as u32).to_le_bytes())?;
+        self.write(MAGIC)?;
+        self.file.flush()?;
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    use super::*;
+
+    /// A value read with the compact protocol.
+    #[derive(Debug, PartialEq)]
+    enum Thrift {
+        Bool(bool),
+        Int(i64),
+        Binary(Vec<u8>),
+        List(Vec<Thrift>),
+        Struct(BTreeMap<i16, Thrift>),
+    }
+
+    use Thrift::*;
+
+    impl Thrift {
+        fn get(&self, id: i16) -> &Thrift {
+            match self {
+                Struct(fields) => &fields[&id],
+                _ => panic!("not a struct: {:?}", self),
+            }
+        }
+
+        fn has(&self, id: i16) -> bool {
+            matches!(self, Struct(fields) if fields.contains_key(&id))
+        }
+
+        fn int(&self) -> i64 {
+            match self {
+                Int(value) => *value,
+                _ => panic!("not an integer: {:?}", self),
+            }
+        }
+
+        fn list(&self) -> &[Thrift] {
+            match self {
+                List(values) => values,
+                _ => panic!("not a list: {:?}", self),
+            }
+        }
+
+        fn string(&self) -> &str {
+            match self {
+                Binary(bytes) => std::str::from_utf8(bytes).unwrap(),
+                _ => panic!("not binary: {:?}", self),
+            }
+        }
+    }
+
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        at: usize,
+    }
+
+    impl Reader<'_> {
+        fn byte(&mut self) -> u8 {
+            self.at += 1;
+            self.bytes[self.at - 1]
+        }
+
+        fn varint(&mut self) -> u64 {
+            let (mut value, mut shift) = (0, 0);
+            loop {
+                let byte = self.byte();
+                value |= ((byte & 0x7f) as u64) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    return value;
+                }
+            }
+        }
+
+        fn zigzag(&mut self) -> i64 {
+            let value = self.varint();
+            (value >> 1) as i64 ^ -((value & 1) as i64)
+        }
+
+        fn value(&mut self, kind: u8) -> Thrift {
+            match kind {
+                BOOL_TRUE | BOOL_FALSE => Bool(self.byte() == BOOL_TRUE),
+                BYTE => Int(self.byte() as i8 as i64),
+                4 | I32 | I64 => Int(self.zigzag()),
+                BINARY => {
+                    let len = self.varint() as usize;
+                    self.at += len;
+                    Binary(self.bytes[self.at - len..self.at].to_vec())
+                }
+                LIST => {
+                    let header = self.byte();
+                    let len = match header >> 4 {
+                        15 => self.varint() as usize,
+                        len => len as usize,
+                    };
+                    List((0..len).map(|_| self.value(header & 0xf)).collect())
+                }
+                STRUCT => {
+                    let mut fields = BTreeMap::new();
+                    let mut id = 0;
+                    loop {
+                        let header = self.byte();
+                        if header == 0 {
+                            return Struct(fields);
+                        }
+                        id = match header >> 4 {
+                            0 => self.zigzag() as i16,
+                            delta => id + delta as i16,
+                        };
+                        let value = match header & 0xf {
+                            BOOL_TRUE => Bool(true),
+                            BOOL_FALSE => Bool(false),
+                            kind => self.value(kind),
+                        };
+                        fields.insert(id, value);
+                    }
+                }
+                kind => panic!("unknown type {}", kind),
+            }
+        }
+    }
+
+    fn unsnappy(bytes: &[u8]) -> Vec<u8> {
+        let mut reader = Reader { bytes, at: 0 };
+        let len = reader.varint() as usize;
+        let mut out = Vec::new();
+        while reader.at < bytes.len() {
+            let tag = reader.byte();
+            match tag & 3 {
+                0 => {
+                    let len = match tag >> 2 {
+                        len @ 0..=59 => len as usize + 1,
+                        size => {
+                            let size = size as usize - 59;
+                            let mut len = [0; 8];
+                            len[..size].copy_from_slice(&bytes[reader.at..reader.at + size]);
+                            reader.at += size;
+                            usize::from_le_bytes(len) + 1
+                        }
+                    };
+                    out.extend(&bytes[reader.at..reader.at + len]);
+                    reader.at += len;
+                }
+                2 => {
+                    let offset = u16::from_le_bytes([reader.byte(), reader.byte()]) as usize;
+                    assert!(offset > 0);
+                    for _ in 0..(tag >> 2) as usize + 1 {
+                        out.push(out[out.len() - offset]);
+                    }
+                }
+                _ => panic!("copy tag {} isn't written", tag & 3),
+            }
+        }
+        assert_eq!(out.len(), len);
+        out
+    }
+
+    fn decompress(codec: i64, bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        match codec {
+            0 => out.extend(bytes),
+            1 => out = unsnappy(bytes),
+            2 => {
+                flate2::read::GzDecoder::new(bytes)
+                    .read_to_end(&mut out)
+                    .unwrap();
+            }
+            6 => out = zstd::decode_all(bytes).unwrap(),
+            codec => panic!("unknown codec {}", codec),
+        }
+        out
+    }
+
+    /// Reads the file like a reader going through the footer, returns its
+    /// metadata and the values of every column of every row group.
+    fn read(bytes: &[u8]) -> (Thrift, Vec<Vec<Vec<u8>>>) {
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(&bytes[bytes.len() - 4..], MAGIC);
+        let len = u32::from_le_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap());
+        let start = bytes.len() - 8 - len as usize;
+        let mut reader = Reader {
+            bytes: &bytes[..bytes.len() - 8],
+            at: start,
+        };
+        let metadata = reader.value(STRUCT);
+        assert_eq!(reader.at, bytes.len() - 8);
+        let mut row_groups = Vec::new();
+        for row_group in metadata.get(4).list() {
+            let rows = row_group.get(3).int();
+            let mut columns = Vec::new();
+            for chunk in row_group.get(1).list() {
+                let column = chunk.get(3);
+                assert_eq!(column.get(5).int(), rows);
+                let mut reader = Reader {
+                    bytes,
+                    at: column.get(9).int() as usize,
+                };
+                let header = reader.value(STRUCT);
+                let header_len = reader.at - column.get(9).int() as usize;
+                assert_eq!(header.get(1).int(), DATA_PAGE as i64);
+                assert_eq!(header.get(5).get(1).int(), rows);
+                assert_eq!(header.get(5).get(2).int(), PLAIN as i64);
+                let compressed = header.get(3).int() as usize;
+                assert_eq!(column.get(7).int() as usize, header_len + compressed);
+                let page = &bytes[reader.at..reader.at + compressed];
+                let values = decompress(column.get(4).int(), page);
+                assert_eq!(values.len() as i64, header.get(2).int());
+                assert_eq!(column.get(6).int() as usize, header_len + values.len());
+                columns.push(values);
+            }
+            row_groups.push(columns);
+        }
+        (metadata, row_groups)
+    }
+
+    fn temp_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("parsebin-parquet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name).to_string_lossy().into_owned()
+    }
+
+    fn write(
+        name: &str,
+        types: &[ParseType],
+        codec: Codec,
+        rows: usize,
+        values: &[Value],
+    ) -> Vec<u8> {
+        let path = temp_path(name);
+        let names = (0..types.len())
+            .map(|column| format!("col{}", column))
+            .collect();
+        let mut writer = ParquetWriter::create(&path, names, types.to_vec(), codec, rows).unwrap();
+        for value in values {
+            writer.push(*value).unwrap();
+        }
+        writer.finish().unwrap();
+        std::fs::read(&path).unwrap()
+    }
+
+    #[test]
+    fn columns_in_row_groups() {
+        let path = temp_path("columns.parquet");
+        let names = vec!["a".to_string(), "bee".to_string()];
+        let types = vec![ParseType::U16, ParseType::F64];
+        let mut writer = ParquetWriter::create(&path, names, types, Codec::None, 2).unwrap();
+        for row in 0..5u16 {
+            writer.push(Value::U16(row)).unwrap();
+            writer.push(Value::F64(row as f64 / 2.0)).unwrap();
+        }
+        // A row without its last column is dropped.
+        writer.push(Value::U16(9)).unwrap();
+        assert_eq!(writer.finish().unwrap(), 1);
+        let (metadata, row_groups) = read(&std::fs::read(&path).unwrap());
+        assert_eq!(metadata.get(3).int(), 5);
+        let schema = metadata.get(2).list();
+        assert_eq!(schema[0].get(4).string(), "schema");
+        assert_eq!(schema[0].get(5).int(), 2);
+        assert_eq!(schema[1].get(4).string(), "a");
+        assert_eq!(schema[2].get(4).string(), "bee");
+        let rows: Vec<i64> = metadata
+            .get(4)
+            .list()
+            .iter()
+            .map(|row_group| row_group.get(3).int())
+            .collect();
+        assert_eq!(rows, [2, 2, 1]);
+        let a: Vec<u8> = row_groups
+            .iter()
+            .flat_map(|columns| columns[0].clone())
+            .collect();
+        let expected: Vec<u8> = (0..5i32).flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(a, expected);
+        let bee: Vec<u8> = row_groups
+            .iter()
+            .flat_map(|columns| columns[1].clone())
+            .collect();
+        let expected: Vec<u8> = (0..5)
+            .flat_map(|v| (v as f64 / 2.0).to_le_bytes())
+            .collect();
+        assert_eq!(bee, expected);
+        // The path of a column is its name.
+        let chunk = metadata.get(4).list()[0].get(1).list()[1].get(3);
+        assert_eq!(chunk.get(3).list()[0].string(), "bee");
+    }
+
+    #[test]
+    fn schema_of_every_type() {
+        let types = [
+            ParseType::U8,
+            ParseType::I8,
+            ParseType::U16,
+            ParseType::I16,
+            ParseType::U24,
+            ParseType::I24,
+            ParseType::U32,
+            ParseType::I32,
+            ParseType::U64,
+            ParseType::I64,
+            ParseType::F32,
+            ParseType::F64,
+        ];
+        let bytes = write("types.parquet", &types, Codec::None, 10, &[]);
+        let (metadata, row_groups) = read(&bytes);
+        assert!(row_groups.is_empty());
+        let schema = &metadata.get(2).list()[1..];
+        let physical: Vec<i64> = schema.iter().map(|element| element.get(1).int()).collect();
+        assert_eq!(physical, [1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 4, 5]);
+        assert!(schema.iter().all(|element| element.get(3).int() == 0));
+        let converted: Vec<Option<i64>> = schema
+            .iter()
+            .map(|element| element.has(6).then(|| element.get(6).int()))
+            .collect();
+        let expected = [
+            Some(11),
+            Some(15),
+            Some(12),
+            Some(16),
+            Some(13),
+            None,
+            Some(13),
+            None,
+            Some(14),
+            None,
+            None,
+            None,
+        ];
+        assert_eq!(converted, expected);
+        let integer = schema[1].get(10).get(10);
+        assert_eq!((integer.get(1), integer.get(2)), (&Int(8), &Bool(true)));
+        let integer = schema[8].get(10).get(10);
+        assert_eq!((integer.get(1), integer.get(2)), (&Int(64), &Bool(false)));
+    }
+
+    #[test]
+    fn values_are_converted_to_the_physical_type() {
+        let types = [
+            ParseType::U32,
+            ParseType::I8,
+            ParseType::U64,
+            ParseType::F32,
+        ];
+        let values = [
+            Value::U32(u32::MAX),
+            Value::I8(-2),
+            Value::U64(u64::MAX),
+            Value::F32(1.5),
+        ];
+        let (_, row_groups) = read(&write("values.parquet", &types, Codec::None, 10, &values));
+        assert_eq!(row_groups[0][0], u32::MAX.to_le_bytes());
+        assert_eq!(row_groups[0][1], (-2i32).to_le_bytes());
+        assert_eq!(row_groups[0][2], u64::MAX.to_le_bytes());
+        assert_eq!(row_groups[0][3], 1.5f32.to_le_bytes());
+    }
+
+    #[test]
+    fn compressed_pages() {
+        let values: Vec<Value> = (0..10000).map(|v| Value::I64(v % 100)).collect();
+        let expected: Vec<u8> = (0..10000i64)
+            .flat_map(|v| (v % 100).to_le_bytes())
+            .collect();
+        for (codec, id) in [(Codec::Snappy, 1), (Codec::Gzip, 2), (Codec::Zstd, 6)] {
+            let bytes = write(
+                "compressed.parquet",
+                &[ParseType::I64],
+                codec,
+                1 << 20,
+                &values,
+            );
+            let (metadata, row_groups) = read(&bytes);
+            let chunk = metadata.get(4).list()[0].get(1).list()[0].get(3);
+            assert_eq!(chunk.get(4).int(), id);
+            assert!(chunk.get(7).int() < chunk.get(6).int() / 10);
+            assert_eq!(row_groups[0][0], expected);
+        }
+    }
+
+    #[test]
+    fn snappy_round_trip() {
+        let mut random = 1u32;
+        let noise: Vec<u8> = (0..5000)
+            .map(|_| {
+                random = random.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (random >> 16) as u8
+            })
+            .collect();
+        for bytes in [
+            Vec::new(),
+            vec![7],
+            vec![0; 100_000],
+            b"abcabcabcabcabcabcab".to_vec(),
+            noise.clone(),
+            [&noise[..], &noise[..], &noise[..100]].concat(),
+        ] {
+            assert_eq!(unsnappy(&snappy(&bytes)), bytes);
+        }
+    }
+
+    #[test]
+    fn field_ids_far_apart() {
+        let mut encoder = Encoder::new();
+        encoder.i32_field(1, -1);
+        encoder.i64_field(20, 300);
+        encoder.bool_field(21, true);
+        let bytes = encoder.finish();
+        let mut reader = Reader {
+            bytes: &bytes,
+            at: 0,
+        };
+        let value = reader.value(STRUCT);
+        assert_eq!(value.get(1), &Int(-1));
+        assert_eq!(value.get(20), &Int(300));
+        assert_eq!(value.get(21), &Bool(true));
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::Write;
+
+/// Characters of the sparkline from the lowest to the highest value, the
+/// last one fills a whole line of a taller plot.
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Stand-ins for `BLOCKS` if the output may not support Unicode.
+const ASCII_BLOCKS: [char; 8] = ['_', '.', '-', ':', '=', '+', '*', '#'];
+
+/// Sparkline of a stream of values scaled between their minimum and maximum.
+///
+/// The number of values is only known at the end, so all are kept until then.
+/// With more values than columns, each column shows the mean of consecutive
+/// values. NaN and infinite values are left out, a column of only those is blank.
+#[derive(Debug, Clone, Default)]
+pub struct Plot {
+    values: Vec<f64>,
+}
+
+impl Plot {
+    pub fn push(&mut self, value: f64) {
+        self.values.push(value);
+    }
+
+    /// Prints the plot `height` lines high in at most `width` characters,
+    /// labeled with the maximum at the top and the minimum at the bottom.
+    ///
+    /// A single line has the minimum in front and the maximum behind it.
+    /// `format` formats the labels.
+    pub fn print(
+        &self,
+        out: &mut impl Write,
+        width: usize,
+        height: usize,
+        ascii: bool,
+        format: impl Fn(f64) -> String,
+    ) -> std::io::Result<()> {
+        let finite = self.values.iter().filter(|value| value.is_finite());
+        let min = finite.clone().copied().fold(f64::INFINITY, f64::min);
+        let max = finite.copied().fold(f64::NEG_INFINITY, f64::max);
+        if max < min {
+            return writeln!(out, "no values");
+        }
+        let (min_label, max_label) = (format(min), format(max));
+        let label_width = std::cmp::max(min_label.len(), max_label.len());
+        let labels = match height {
+            1 => min_label.len() + max_label.len() + 2,
+            _ => label_width + 1,
+        };
+        let columns = width.saturating_sub(labels).clamp(1, self.values.len());
+        let means: Vec<Option<f64>> = (0..columns)
+            .map(|column| {
+                let start = column * self.values.len() / columns;
+                let end = (column + 1) * self.values.len() / columns;
+                let finite = self.values[start..end].iter().filter(|v| v.is_finite());
+                let (sum, count) = finite.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+                (count > 0).then(|| sum / count as f64)
+            })
+            .collect();
+        let blocks = match ascii {
+            true => ASCII_BLOCKS,
+            false => BLOCKS,
+        };
+        // Height of every column in eighths of a line, at least one.
+        let steps = height * blocks.len();
+        let levels: Vec<Option<usize>> = means
+            .iter()
+            .map(|mean| {
+                mean.map(|mean| match max == min {
+                    true => 1,
+                    false => ((mean - min) / (max - min) * (steps - 1) as f64).round() as usize + 1,
+                })
+            })
+            .collect();
+        for line in 0..height {
+            let floor = (height - 1 - line) * blocks.len();
+            let plot: String = levels
+                .iter()
+                .map(|level| match level {
+                    Some(level) if *level > floor => {
+                        blocks[std::cmp::min(level - floor, blocks.len()) - 1]
+                    }
+                    _ => ' ',
+                })
+                .collect();
+            match (height, line) {
+                (1, _) => writeln!(out, "{} {} {}", min_label, plot, max_label)?,
+                (_, 0) => writeln!(out, "{:>w$} {}", max_label, plot, w = label_width)?,
+                _ if line + 1 == height => {
+                    writeln!(out, "{:>w$} {}", min_label, plot, w = label_width)?
+                }
+                _ => writeln!(out, "{:>w$} {}", "", plot, w = label_width)?,
+            }
+        }
+        Ok(())
+    }
+}
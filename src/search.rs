@@ -0,0 +1,219 @@
+use std::io::Read;
+
+use parsebin::decode;
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+use parsebin::SizeOf;
+use parsebin::Value;
+
+/// Parses a hex byte string like `0A`, `deadbeef` or `0xDE AD BE EF`.
+pub fn parse_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    let digits: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() {
+        return Err("expected at least one byte".to_string());
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!(
+            "expected an even number of hex digits, found {}",
+            digits.len()
+        ));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16).map_err(|_| format!("invalid hex byte '{}'", pair))
+        })
+        .collect()
+}
+
+/// Finds the position of the `nth` (1-based) occurrence of `pattern` in `reader`.
+///
+/// The input is scanned in chunks, keeping the last `pattern.len() - 1` bytes
+/// of every chunk so matches that straddle two reads are found as well.
+/// Occurrences may overlap, and the match has to end within the first `limit`
+/// bytes if a limit is given.
+pub fn find_pattern(
+    reader: &mut impl Read,
+    pattern: &[u8],
+    mut nth: u64,
+    limit: Option<u64>,
+) -> std::io::Result<Option<u64>> {
+    let limit = limit.unwrap_or(u64::MAX);
+    let keep = pattern.len() - 1;
+    let mut buffer = vec![0; std::cmp::max(4096, pattern.len() * 2)];
+    // Position in the input of buffer[0].
+    let mut start = 0u64;
+    let mut valid = 0;
+    // Index in the buffer the search continues from.
+    let mut from = 0;
+    loop {
+        let remaining = limit.saturating_sub(start + valid as u64);
+        let to_read = std::cmp::min((buffer.len() - valid) as u64, remaining) as usize;
+        let n = match to_read {
+            0 => 0,
+            _ => reader.read(&mut buffer[valid..valid + to_read])?,
+        };
+        if n == 0 {
+            return Ok(None);
+        }
+        valid += n;
+        while let Some(i) = buffer[from..valid]
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+        {
+            nth -= 1;
+            if nth == 0 {
+                return Ok(Some(start + (from + i) as u64));
+            }
+            from += i + 1;
+        }
+        let cut = std::cmp::max(from, valid - std::cmp::min(keep, valid));
+        buffer.copy_within(cut..valid, 0);
+        start += cut as u64;
+        valid -= cut;
+        from = 0;
+    }
+}
+
+/// Decodes a value of `parse_type` at every `step` bytes of `reader` and
+/// calls `found` with the position of every value `matches` accepts, until
+/// it returns false.
+///
+/// A `step` of the value size checks every aligned value, a `step` of 1
+/// slides over the input byte by byte.
+pub fn find_values(
+    reader: &mut impl Read,
+    parse_type: &ParseType,
+    byte_order: &ByteOrder,
+    step: usize,
+    matches: impl Fn(Value) -> bool,
+    mut found: impl FnMut(u64, Value) -> bool,
+) -> std::io::Result<()> {
+    let size = parse_type.size_of() as usize;
+    let mut buffer = vec![0; std::cmp::max(65536, 2 * (size + step))];
+    // Position in the input of buffer[0].
+    let mut start = 0u64;
+    let mut valid = 0;
+    // Index in the buffer of the next value to check, may be past `valid`.
+    let mut next = 0;
+    loop {
+        let n = reader.read(&mut buffer[valid..])?;
+        valid += n;
+        while let Some((value, _)) = buffer
+            .get(next..valid)
+            .and_then(|rest| decode(parse_type, rest, byte_order))
+        {
+            if matches(value) && !found(start + next as u64, value) {
+                return Ok(());
+            }
+            next += step;
+        }
+        if n == 0 {
+            return Ok(());
+        }
+        let cut = std::cmp::min(next, valid);
+        buffer.copy_within(cut..valid, 0);
+        start += cut as u64;
+        valid -= cut;
+        next -= cut;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10000 bytes counting up, with `pattern` written at `at`.
+    fn input(pattern: &[u8], at: usize) -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..10000).map(|i| (i % 200) as u8).collect();
+        bytes[at..at + pattern.len()].copy_from_slice(pattern);
+        bytes
+    }
+
+    const PATTERN: &[u8] = &[0xde, 0xad, 0xbe, 0xef];
+
+    #[test]
+    fn pattern_at_the_start() {
+        let bytes = input(PATTERN, 0);
+        let found = find_pattern(&mut &bytes[..], PATTERN, 1, None).unwrap();
+        assert_eq!(found, Some(0));
+    }
+
+    #[test]
+    fn pattern_straddling_the_chunk_boundary() {
+        for at in 4093..4096 {
+            let bytes = input(PATTERN, at);
+            let found = find_pattern(&mut &bytes[..], PATTERN, 1, None).unwrap();
+            assert_eq!(found, Some(at as u64));
+        }
+    }
+
+    #[test]
+    fn absent_pattern() {
+        let bytes = input(&[], 0);
+        assert_eq!(
+            find_pattern(&mut &bytes[..], PATTERN, 1, None).unwrap(),
+            None
+        );
+        // Found, but not within the limit.
+        let bytes = input(PATTERN, 5000);
+        assert_eq!(
+            find_pattern(&mut &bytes[..], PATTERN, 1, Some(5003)).unwrap(),
+            None
+        );
+        assert_eq!(
+            find_pattern(&mut &bytes[..], PATTERN, 1, Some(5004)).unwrap(),
+            Some(5000)
+        );
+    }
+
+    #[test]
+    fn overlapping_occurrences() {
+        let bytes = [1, 1, 1, 1];
+        let found = find_pattern(&mut &bytes[..], &[1, 1], 3, None).unwrap();
+        assert_eq!(found, Some(2));
+        assert_eq!(
+            find_pattern(&mut &bytes[..], &[1, 1], 4, None).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(parse_hex_bytes("0xDE AD be ef"), Ok(PATTERN.to_vec()));
+        assert!(parse_hex_bytes("abc").is_err());
+        assert!(parse_hex_bytes("zz").is_err());
+        assert!(parse_hex_bytes("").is_err());
+    }
+
+    #[test]
+    fn values_aligned_and_unaligned() {
+        let bytes: Vec<u8> = [0x0200u16, 0, 2]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let search = |step| {
+            let mut positions = Vec::new();
+            let matches = |value: Value| value == Value::U16(2);
+            let found = |position, _| {
+                positions.push(position);
+                true
+            };
+            find_values(
+                &mut &bytes[..],
+                &ParseType::U16,
+                &ByteOrder::LittleEndian,
+                step,
+                matches,
+                found,
+            )
+            .unwrap();
+            positions
+        };
+        assert_eq!(search(2), [4]);
+        // Sliding by a byte also finds the 2 made of the bytes of two values.
+        assert_eq!(search(1), [1, 4]);
+    }
+}
@@ -0,0 +1,333 @@
+//! Writer of values as MessagePack or CBOR numbers.
+//!
+//! Integers are written as integers in the shortest encoding, unsigned for
+//! values of at least 0, so u64 values above `i64::MAX` keep their full
+//! range. f32 and f64 values stay single and double precision floats.
+
+use std::io::Write;
+
+use parsebin::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Msgpack,
+    Cbor,
+}
+
+/// Writes values as a sequence of numbers, or as one array of all of them.
+///
+/// The length of the array is part of its header, so its values are kept
+/// until [`SerialWriter::finish`].
+pub struct SerialWriter {
+    encoding: Encoding,
+    array: Option<Vec<u8>>,
+    count: u64,
+}
+
+impl SerialWriter {
+    pub fn new(encoding: Encoding, array: bool) -> Self {
+        SerialWriter {
+            encoding,
+            array: array.then(Vec::new),
+            count: 0,
+        }
+    }
+
+    pub fn push(&mut self, value: Value, out: &mut impl Write) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(9);
+        match (self.encoding, value) {
+            (Encoding::Msgpack, Value::F32(v)) => {
+                bytes.push(0xca);
+                bytes.extend(v.to_be_bytes());
+            }
+            (Encoding::Msgpack, Value::F64(v)) => {
+                bytes.push(0xcb);
+                bytes.extend(v.to_be_bytes());
+            }
+            (Encoding::Cbor, Value::F32(v)) => {
+                bytes.push(0xfa);
+                bytes.extend(v.to_be_bytes());
+            }
+            (Encoding::Cbor, Value::F64(v)) => {
+                bytes.push(0xfb);
+                bytes.extend(v.to_be_bytes());
+            }
+            (encoding, value) => {
+                let value = value.as_i128().expect("integer value");
+                match encoding {
+                    Encoding::Msgpack => msgpack_int(&mut bytes, value),
+                    // A negative integer n is encoded as -1 - n.
+                    Encoding::Cbor if value < 0 => cbor_head(&mut bytes, 1, (-1 - value) as u64),
+                    Encoding::Cbor => cbor_head(&mut bytes, 0, value as u64),
+                }
+            }
+        }
+        self.count += 1;
+        match &mut self.array {
+            Some(array) => {
+                array.extend(bytes);
+                Ok(())
+            }
+            None => out.write_all(&bytes),
+        }
+    }
+
+    /// Writes the array, if the values are written as one.
+    pub fn finish(self, out: &mut impl Write) -> std::io::Result<()> {
+        let array = match self.array {
+            Some(array) => array,
+            None => return Ok(()),
+        };
+        let mut header = Vec::with_capacity(9);
+        match self.encoding {
+            Encoding::Msgpack => match self.count {
+                count @ 0..=15 => header.push(0x90 | count as u8),
+                count @ 16..=0xffff => {
+                    header.push(0xdc);
+                    header.extend((count as u16).to_be_bytes());
+                }
+                count @ 0x1_0000..=0xffff_ffff => {
+                    header.push(0xdd);
+                    header.extend((count as u32).to_be_bytes());
+                }
+                count => {
+                    return Err(std::io::Error::other(format!(
+                        "{} values don't fit into a MessagePack array",
+                        count
+                    )))
+                }
+            },
+            Encoding::Cbor => cbor_head(&mut header, 4, self.count),
+        }
+        out.write_all(&header)?;
+        out.write_all(&array)
+    }
+}
+
+fn msgpack_int(bytes: &mut Vec<u8>, value: i128) {
+    match value {
+        0..=0x7f => bytes.push(value as u8),
+        0x80..=0xff => bytes.extend([0xcc, value as u8]),
+        0x100..=0xffff => {
+            bytes.push(0xcd);
+            bytes.extend((value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            bytes.push(0xce);
+            bytes.extend((value as u32).to_be_bytes());
+        }
+        0x1_0000_0000.. => {
+            bytes.push(0xcf);
+            bytes.extend((value as u64).to_be_bytes());
+        }
+        -32..=-1 => bytes.push(value as i8 as u8),
+        -0x80..=-33 => bytes.extend([0xd0, value as i8 as u8]),
+        -0x8000..=-0x81 => {
+            bytes.push(0xd1);
+            bytes.extend((value as i16).to_be_bytes());
+        }
+        -0x8000_0000..=-0x8001 => {
+            bytes.push(0xd2);
+            bytes.extend((value as i32).to_be_bytes());
+        }
+        _ => {
+            bytes.push(0xd3);
+            bytes.extend((value as i64).to_be_bytes());
+        }
+    }
+}
+
+/// Writes the head of a CBOR item of `major` type with the `argument` that
+/// is an unsigned integer itself, the magnitude of a negative one or a length.
+fn cbor_head(bytes: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => bytes.push(major | argument as u8),
+        24..=0xff => bytes.extend([major | 24, argument as u8]),
+        0x100..=0xffff => {
+            bytes.push(major | 25);
+            bytes.extend((argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            bytes.push(major | 26);
+            bytes.extend((argument as u32).to_be_bytes());
+        }
+        _ => {
+            bytes.push(major | 27);
+            bytes.extend(argument.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A decoded number or array, floats as their bits so NaN compares equal.
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Integer(i128),
+        F32(u32),
+        F64(u64),
+        Array(Vec<Item>),
+    }
+
+    fn expected(value: Value) -> Item {
+        match value {
+            Value::F32(v) => Item::F32(v.to_bits()),
+            Value::F64(v) => Item::F64(v.to_bits()),
+            v => Item::Integer(v.as_i128().unwrap()),
+        }
+    }
+
+    /// Takes the big-endian integer of `len` bytes at the start of `bytes`.
+    fn take(bytes: &mut &[u8], len: usize) -> u64 {
+        let (head, rest) = bytes.split_at(len);
+        *bytes = rest;
+        head.iter().fold(0, |n, b| n << 8 | *b as u64)
+    }
+
+    /// Decodes one MessagePack item of the kinds [`SerialWriter`] writes.
+    fn msgpack(bytes: &mut &[u8]) -> Item {
+        let first = take(bytes, 1) as u8;
+        let array =
+            |bytes: &mut &[u8], len: u64| Item::Array((0..len).map(|_| msgpack(bytes)).collect());
+        match first {
+            0x00..=0x7f => Item::Integer(first as i128),
+            0x90..=0x9f => array(bytes, (first & 0x0f) as u64),
+            0xdc => {
+                let len = take(bytes, 2);
+                array(bytes, len)
+            }
+            0xdd => {
+                let len = take(bytes, 4);
+                array(bytes, len)
+            }
+            0xca => Item::F32(take(bytes, 4) as u32),
+            0xcb => Item::F64(take(bytes, 8)),
+            0xcc..=0xcf => Item::Integer(take(bytes, 1 << (first - 0xcc)) as i128),
+            0xd0 => Item::Integer(take(bytes, 1) as i8 as i128),
+            0xd1 => Item::Integer(take(bytes, 2) as i16 as i128),
+            0xd2 => Item::Integer(take(bytes, 4) as i32 as i128),
+            0xd3 => Item::Integer(take(bytes, 8) as i64 as i128),
+            0xe0..=0xff => Item::Integer(first as i8 as i128),
+            _ => panic!("unexpected MessagePack byte {:#x}", first),
+        }
+    }
+
+    /// Decodes one CBOR item of the kinds [`SerialWriter`] writes.
+    fn cbor(bytes: &mut &[u8]) -> Item {
+        let first = take(bytes, 1) as u8;
+        let argument = match first & 0x1f {
+            info @ 0..=23 => info as u64,
+            info @ 24..=27 => take(bytes, 1 << (info - 24)),
+            info => panic!("unexpected CBOR additional info {}", info),
+        };
+        match first >> 5 {
+            0 => Item::Integer(argument as i128),
+            1 => Item::Integer(-1 - argument as i128),
+            4 => Item::Array((0..argument).map(|_| cbor(bytes)).collect()),
+            7 if first == 0xfa => Item::F32(argument as u32),
+            7 if first == 0xfb => Item::F64(argument),
+            major => panic!("unexpected CBOR major type {}", major),
+        }
+    }
+
+    fn values() -> Vec<Value> {
+        let mut values = vec![
+            Value::U64(u64::MAX),
+            Value::I64(i64::MIN),
+            Value::I64(i64::MAX),
+            Value::F32(-1.5),
+            Value::F32(f32::NAN),
+            Value::F64(f64::NEG_INFINITY),
+            Value::F64(1e-300),
+            Value::U24(0xff_ffff),
+            Value::I24(-0x80_0000),
+        ];
+        // Both sides of every boundary between encodings.
+        for edge in [
+            0i64,
+            23,
+            24,
+            0x7f,
+            0xff,
+            0xffff,
+            0xffff_ffff,
+            -1,
+            -24,
+            -25,
+            -32,
+            -33,
+            -0x80,
+            -0x81,
+            -0x8000,
+            -0x8001,
+            -0x8000_0000,
+            -0x8000_0001,
+        ] {
+            values.extend([edge - 1, edge, edge + 1].map(Value::I64));
+        }
+        values
+    }
+
+    fn written(encoding: Encoding, array: bool, values: &[Value]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut writer = SerialWriter::new(encoding, array);
+        for value in values {
+            writer.push(*value, &mut out).unwrap();
+        }
+        writer.finish(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let values = values();
+        for (encoding, decode) in [
+            (Encoding::Msgpack, msgpack as fn(&mut &[u8]) -> Item),
+            (Encoding::Cbor, cbor),
+        ] {
+            let bytes = written(encoding, false, &values);
+            let mut rest = &bytes[..];
+            for value in &values {
+                assert_eq!(
+                    decode(&mut rest),
+                    expected(*value),
+                    "{:?} {:?}",
+                    encoding,
+                    value
+                );
+            }
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn arrays_round_trip() {
+        for count in [0, 15, 16, 0xffff, 0x1_0000] {
+            let values: Vec<Value> = (0..count).map(|v| Value::U32(v as u32)).collect();
+            let expected = Item::Array(values.iter().map(|v| expected(*v)).collect());
+            let bytes = written(Encoding::Msgpack, true, &values);
+            let mut rest = &bytes[..];
+            assert_eq!(msgpack(&mut rest), expected, "{} values", count);
+            assert!(rest.is_empty());
+            let bytes = written(Encoding::Cbor, true, &values);
+            let mut rest = &bytes[..];
+            assert_eq!(cbor(&mut rest), expected, "{} values", count);
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn shortest_encodings() {
+        let one = |encoding, value| written(encoding, false, &[value]);
+        assert_eq!(one(Encoding::Msgpack, Value::U8(5)), [0x05]);
+        assert_eq!(one(Encoding::Msgpack, Value::I8(-1)), [0xff]);
+        assert_eq!(one(Encoding::Msgpack, Value::U16(200)), [0xcc, 200]);
+        assert_eq!(one(Encoding::Cbor, Value::U8(23)), [0x17]);
+        assert_eq!(one(Encoding::Cbor, Value::I8(-24)), [0x37]);
+        assert_eq!(one(Encoding::Cbor, Value::U16(500)), [0x19, 0x01, 0xf4]);
+        assert_eq!(written(Encoding::Cbor, true, &[]), [0x80]);
+    }
+}
@@ -0,0 +1,98 @@
+/// Explanation of the accepted size syntax, shown at the end of `--help`.
+pub const SIZE_HELP: &str =
+    "Sizes and offsets accept plain decimal numbers (4096), hexadecimal with a 0x
+prefix (0x1A40) and decimal numbers with a unit suffix. KB, MB, GB and TB are
+powers of 1000, KiB, MiB, GiB and TiB as well as the bare K, M, G and T are
+powers of 1024. A trailing B alone means bytes. Suffixes are case-insensitive
+and can't be combined with hexadecimal numbers, since B is a hex digit.";
+
+/// Parses a size like `4096`, `0x1000`, `4KiB`, `4K` or `4KB`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).map_err(|e| format!("invalid hex number: {}", e));
+    }
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, suffix) = s.split_at(split);
+    if digits.is_empty() {
+        return Err(format!("expected a number, found '{}'", s));
+    }
+    let multiplier: u64 = match suffix.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(format!("unknown size suffix '{}'", suffix)),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|e| format!("invalid number: {}", e))?;
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("'{}' does not fit in 64 bits", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_numbers() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size(" 12 "), Ok(12));
+        assert_eq!(parse_size("7B"), Ok(7));
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse_size("0x1A40"), Ok(0x1a40));
+        assert_eq!(parse_size("0X1a40"), Ok(0x1a40));
+        assert_eq!(parse_size("0xffffffffffffffff"), Ok(u64::MAX));
+        assert!(parse_size("0x").is_err());
+        assert!(parse_size("0x1g").is_err());
+        assert!(parse_size("0x10000000000000000").is_err());
+    }
+
+    #[test]
+    fn binary_suffixes() {
+        assert_eq!(parse_size("4K"), Ok(4096));
+        assert_eq!(parse_size("4KiB"), Ok(4096));
+        assert_eq!(parse_size("4kib"), Ok(4096));
+        assert_eq!(parse_size("2M"), Ok(2 << 20));
+        assert_eq!(parse_size("2MiB"), Ok(2 << 20));
+        assert_eq!(parse_size("3GiB"), Ok(3 << 30));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn decimal_suffixes() {
+        assert_eq!(parse_size("4KB"), Ok(4000));
+        assert_eq!(parse_size("2mb"), Ok(2_000_000));
+        assert_eq!(parse_size("3GB"), Ok(3_000_000_000));
+        assert_eq!(parse_size("1TB"), Ok(1_000_000_000_000));
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(parse_size("18446744073709551615"), Ok(u64::MAX));
+        assert!(parse_size("18446744073709551616").is_err());
+        assert!(parse_size("16777216T").is_err());
+        assert_eq!(parse_size("16777215T"), Ok(16777215 << 40));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("1.5K").is_err());
+        assert!(parse_size("4KiBB").is_err());
+        assert!(parse_size("4XB").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::Write;
+
+use crate::Value;
+
+/// Floats that usually point at broken data rather than a measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Special {
+    Nan,
+    Inf,
+    NegInf,
+    Subnormal,
+}
+
+const CATEGORIES: [Special; 4] = [
+    Special::Nan,
+    Special::Inf,
+    Special::NegInf,
+    Special::Subnormal,
+];
+
+impl Special {
+    fn name(self) -> &'static str {
+        match self {
+            Special::Nan => "nan",
+            Special::Inf => "inf",
+            Special::NegInf => "-inf",
+            Special::Subnormal => "subnormal",
+        }
+    }
+}
+
+/// Returns the category of a special float, `None` for integers and other floats.
+pub fn classify(value: Value) -> Option<Special> {
+    let v = match value {
+        Value::F32(v) if v.is_subnormal() => return Some(Special::Subnormal),
+        Value::F32(v) => v as f64,
+        Value::F64(v) if v.is_subnormal() => return Some(Special::Subnormal),
+        Value::F64(v) => v,
+        _ => return None,
+    };
+    match v {
+        _ if v.is_nan() => Some(Special::Nan),
+        f64::INFINITY => Some(Special::Inf),
+        f64::NEG_INFINITY => Some(Special::NegInf),
+        _ => None,
+    }
+}
+
+/// Counts the special floats of each category and keeps the byte offsets of
+/// the first `limit` of them.
+#[derive(Debug, Clone)]
+pub struct SpecialCounts {
+    counts: [u64; 4],
+    offsets: [Vec<u64>; 4],
+    limit: usize,
+}
+
+impl SpecialCounts {
+    pub fn new(limit: usize) -> Self {
+        SpecialCounts {
+            counts: [0; 4],
+            offsets: Default::default(),
+            limit,
+        }
+    }
+
+    pub fn push(&mut self, value: Value, offset: u64) {
+        if let Some(special) = classify(value) {
+            let index = CATEGORIES.iter().position(|c| *c == special).unwrap();
+            self.counts[index] += 1;
+            if self.offsets[index].len() < self.limit {
+                self.offsets[index].push(offset);
+            }
+        }
+    }
+
+    /// Prints a line per category with its count and the offsets found.
+    pub fn print(&self, out: &mut impl Write) -> std::io::Result<()> {
+        for (index, special) in CATEGORIES.iter().enumerate() {
+            write!(out, "{}: {}", special.name(), self.counts[index])?;
+            let offsets: Vec<String> = self.offsets[index]
+                .iter()
+                .map(|offset| format!("0x{:x}", offset))
+                .collect();
+            if !offsets.is_empty() {
+                write!(out, " at {}", offsets.join(", "))?;
+            }
+            if self.counts[index] > offsets.len() as u64 {
+                write!(out, ", ...")?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+/// Writes the columns of CSV rows into several files, `columns_per_file`
+/// columns each, in a single pass over the values.
+///
+/// Every file starts with an `index` and `offset` column. Value columns are
+/// named after their global column index (`v037`) so the files can be joined
+/// back together, and a manifest lists which columns went into which file.
+pub struct SplitWriter {
+    writers: Vec<BufWriter<File>>,
+    line_open: Vec<bool>,
+    columns_per_file: usize,
+    row_size: usize,
+    delimiter: char,
+    row: u64,
+    offset: u64,
+    row_bytes: u64,
+}
+
+fn column_name(column: usize, row_size: usize) -> String {
+    let width = std::cmp::max(3, (row_size - 1).to_string().len());
+    format!("v{:0width$}", column, width = width)
+}
+
+impl SplitWriter {
+    pub fn new(
+        template: &str,
+        columns_per_file: usize,
+        row_size: usize,
+        delimiter: char,
+        offset: u64,
+        row_bytes: u64,
+    ) -> std::io::Result<Self> {
+        let file_count = row_size.div_ceil(columns_per_file);
+        let width = (file_count - 1).to_string().len();
+        let mut writers = Vec::with_capacity(file_count);
+        let mut manifest = BufWriter::new(File::create(template.replace("{}", "manifest"))?);
+        writeln!(manifest, "file{}first{}last", delimiter, delimiter)?;
+        for part in 0..file_count {
+            let path = template.replace("{}", &format!("{:0width$}", part, width = width));
+            let mut writer = BufWriter::new(File::create(&path)?);
+            let first = part * columns_per_file;
+            let last = std::cmp::min(first + columns_per_file, row_size) - 1;
+            write!(writer, "index{}offset", delimiter)?;
+            for column in first..=last {
+                write!(writer, "{}{}", delimiter, column_name(column, row_size))?;
+            }
+            writeln!(writer)?;
+            writeln!(
+                manifest,
+                "{}{}{}{}{}",
+                path,
+                delimiter,
+                column_name(first, row_size),
+                delimiter,
+                column_name(last, row_size)
+            )?;
+            writers.push(writer);
+        }
+        manifest.flush()?;
+        Ok(SplitWriter {
+            line_open: vec![false; writers.len()],
+            writers,
+            columns_per_file,
+            row_size,
+            delimiter,
+            row: 0,
+            offset,
+            row_bytes,
+        })
+    }
+
+    pub fn push(&mut self, column: usize, value: impl Display) -> std::io::Result<()> {
+        let part = column / self.columns_per_file;
+        let writer = &mut self.writers[part];
+        if column.is_multiple_of(self.columns_per_file) {
+            write!(
+                writer,
+                "{}{}{}",
+                self.row,
+                self.delimiter,
+                self.offset + self.row * self.row_bytes
+            )?;
+            self.line_open[part] = true;
+        }
+        write!(writer, "{}{}", self.delimiter, value)?;
+        if column % self.columns_per_file == self.columns_per_file - 1
+            || column == self.row_size - 1
+        {
+            writeln!(writer)?;
+            self.line_open[part] = false;
+        }
+        if column == self.row_size - 1 {
+            self.row += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        for (writer, line_open) in self.writers.iter_mut().zip(&self.line_open) {
+            if *line_open {
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::Write;
+
+use crate::Value;
+
+/// Running summary of a stream of values.
+///
+/// Mean and variance are accumulated with Welford's algorithm so that long
+/// streams don't lose precision. NaN and infinite values are counted apart
+/// and left out of the rest, a single infinity would make the mean NaN.
+/// For integers the minimum, maximum and sum are also kept exactly.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    count: u64,
+    nan_count: u64,
+    negative_infinite: u64,
+    positive_infinite: u64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+    sum: f64,
+    exact: Option<Exact>,
+}
+
+/// Exact minimum, maximum and sum of integers, the sum is `None` once it
+/// overflowed.
+#[derive(Debug, Clone)]
+struct Exact {
+    min: i128,
+    max: i128,
+    sum: Option<i128>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            count: 0,
+            nan_count: 0,
+            negative_infinite: 0,
+            positive_infinite: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+            sum: 0.0,
+            exact: None,
+        }
+    }
+}
+
+impl Stats {
+    pub fn push(&mut self, value: Value) {
+        if let Some(v) = value.as_i128() {
+            self.exact = Some(match self.exact.take() {
+                None => Exact {
+                    min: v,
+                    max: v,
+                    sum: Some(v),
+                },
+                Some(exact) => Exact {
+                    min: exact.min.min(v),
+                    max: exact.max.max(v),
+                    sum: exact.sum.and_then(|sum| sum.checked_add(v)),
+                },
+            });
+        }
+        let value = value.as_f64();
+        if value.is_nan() {
+            self.nan_count += 1;
+            return;
+        }
+        if value.is_infinite() {
+            match value > 0.0 {
+                true => self.positive_infinite += 1,
+                false => self.negative_infinite += 1,
+            }
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Population standard deviation of the values seen so far.
+    pub fn stddev(&self) -> f64 {
+        (self.m2 / self.count as f64).sqrt()
+    }
+
+    pub fn print(&self, out: &mut impl Write, float: bool) -> std::io::Result<()> {
+        writeln!(out, "count: {}", self.count)?;
+        if float {
+            writeln!(out, "nan: {}", self.nan_count)?;
+            writeln!(out, "-inf: {}", self.negative_infinite)?;
+            writeln!(out, "inf: {}", self.positive_infinite)?;
+        }
+        if self.count == 0 {
+            return Ok(());
+        }
+        match &self.exact {
+            Some(exact) => {
+                writeln!(out, "min: {}", exact.min)?;
+                writeln!(out, "max: {}", exact.max)?;
+                match exact.sum {
+                    Some(sum) => writeln!(out, "sum: {}", sum)?,
+                    None => writeln!(out, "sum: overflow")?,
+                }
+            }
+            None => {
+                writeln!(out, "min: {}", self.min)?;
+                writeln!(out, "max: {}", self.max)?;
+                writeln!(out, "sum: {}", self.sum)?;
+            }
+        }
+        writeln!(out, "mean: {}", self.mean)?;
+        writeln!(out, "stddev: {}", self.stddev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(stats: &Stats, float: bool) -> String {
+        let mut out = Vec::new();
+        stats.print(&mut out, float).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn non_finite_values_are_counted_apart() {
+        let mut stats = Stats::default();
+        for v in [1.0, f64::INFINITY, 3.0, f64::NAN, f64::NEG_INFINITY, 5.0] {
+            stats.push(Value::F64(v));
+        }
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.mean, 3.0);
+        assert_eq!(stats.stddev(), (8.0f64 / 3.0).sqrt());
+        let text = printed(&stats, true);
+        assert!(
+            text.starts_with(
+                "count: 3\nnan: 1\n-inf: 1\ninf: 1\nmin: 1\nmax: 5\nsum: 9\nmean: 3\n"
+            ),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn mean_and_stddev_of_sines() {
+        // Whole periods of samples, so the mean is exact apart from rounding.
+        let samples = 1000;
+        let x = |i: usize| 2.0 * std::f64::consts::PI * 50.0 * i as f64 / samples as f64;
+        let mut sine = Stats::default();
+        let mut cosine = Stats::default();
+        for i in 0..samples {
+            sine.push(Value::F64(3.0 + 2.0 * x(i).sin()));
+            cosine.push(Value::F32(x(i).cos() as f32));
+        }
+        assert!((sine.mean - 3.0).abs() < 1e-12, "{}", sine.mean);
+        assert!(
+            (sine.stddev() - 2.0f64.sqrt()).abs() < 1e-12,
+            "{}",
+            sine.stddev()
+        );
+        assert!(cosine.mean.abs() < 1e-7, "{}", cosine.mean);
+        let expected = std::f64::consts::FRAC_1_SQRT_2;
+        assert!(
+            (cosine.stddev() - expected).abs() < 1e-7,
+            "{}",
+            cosine.stddev()
+        );
+    }
+
+    #[test]
+    fn integers_are_exact() {
+        let mut stats = Stats::default();
+        for v in [i64::MAX, i64::MAX, -1] {
+            stats.push(Value::I64(v));
+        }
+        let text = printed(&stats, false);
+        let expected = format!(
+            "count: 3\nmin: -1\nmax: {}\nsum: {}\n",
+            i64::MAX,
+            2 * i64::MAX as i128 - 1
+        );
+        assert!(text.starts_with(&expected), "{}", text);
+    }
+
+    #[test]
+    fn no_values() {
+        let mut stats = Stats::default();
+        stats.push(Value::F32(f32::NAN));
+        assert_eq!(printed(&stats, true), "count: 0\nnan: 1\n-inf: 0\ninf: 0\n");
+    }
+}
@@ -0,0 +1,366 @@
+//! Reader of the RIFF header of .wav files and writer of .wav files.
+//!
+//! A WAV file is a `RIFF` chunk of form type `WAVE` holding subchunks, each an
+//! id of 4 bytes, a little-endian u32 size and the data padded to an even
+//! size. The `fmt ` chunk describes the samples, the `data` chunk holds them
+//! as interleaved frames of one sample per channel.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+
+use parsebin::ByteOrder;
+use parsebin::ParseType;
+use parsebin::Value;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+#[derive(Debug)]
+pub struct WavInfo {
+    pub format: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits: u16,
+    pub parse_type: ParseType,
+    /// Position of the samples in the file.
+    pub data_offset: u64,
+    /// Size of the samples, `None` if the header leaves it open, as when streaming.
+    pub data_len: Option<u64>,
+}
+
+/// Reads `reader` up to the start of the samples and returns where they are
+/// and how to parse them.
+///
+/// Errors list the chunks found so far, which helps to tell a broken file
+/// from one with an unsupported sample format.
+pub fn read_header(reader: &mut impl Read) -> Result<WavInfo, String> {
+    let mut found = Vec::new();
+    let fail = |message: &str, found: &[String]| match found.is_empty() {
+        true => Err(message.to_string()),
+        false => Err(format!("{}, found {}", message, found.join(", "))),
+    };
+    let mut riff = [0; 12];
+    if reader.read_exact(&mut riff).is_err() {
+        return fail("Not a WAV file: too short for a RIFF header", &found);
+    }
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return fail("Not a WAV file: no RIFF/WAVE header", &found);
+    }
+    found.push("RIFF/WAVE".to_string());
+    let mut position = 12u64;
+    let mut format = None;
+    loop {
+        let mut header = [0; 8];
+        if reader.read_exact(&mut header).is_err() {
+            return fail("Malformed WAV file: no data chunk", &found);
+        }
+        let id = String::from_utf8_lossy(&header[0..4]).to_string();
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        position += 8;
+        found.push(format!("'{}' of {} bytes at {}", id, size, position - 8));
+        if &header[0..4] == b"data" {
+            let (format, channels, sample_rate, bits) = match format {
+                Some(format) => format,
+                None => {
+                    return fail(
+                        "Malformed WAV file: data chunk before the fmt chunk",
+                        &found,
+                    )
+                }
+            };
+            let parse_type = match (format, bits) {
+                (FORMAT_PCM, 8) => ParseType::U8,
+                (FORMAT_PCM, 16) => ParseType::I16,
+                (FORMAT_PCM, 24) => ParseType::I24,
+                (FORMAT_PCM, 32) => ParseType::I32,
+                (FORMAT_IEEE_FLOAT, 32) => ParseType::F32,
+                (FORMAT_IEEE_FLOAT, 64) => ParseType::F64,
+                _ => {
+                    let message = format!(
+                        "Unsupported WAV sample format {} with {} bits (only PCM and IEEE float)",
+                        format, bits
+                    );
+                    return fail(&message, &found);
+                }
+            };
+            return Ok(WavInfo {
+                format,
+                channels,
+                sample_rate,
+                bits,
+                parse_type,
+                data_offset: position,
+                // Streaming writers leave the size at 0 or the maximum.
+                data_len: match size {
+                    0 | u32::MAX => None,
+                    size => Some(size as u64),
+                },
+            });
+        }
+        let padded = size as u64 + size as u64 % 2;
+        let mut chunk = Vec::new();
+        let read = reader
+            .by_ref()
+            .take(padded)
+            .read_to_end(&mut chunk)
+            .map_err(|e| format!("Error: {}", e))?;
+        if (read as u64) < std::cmp::min(padded, size as u64) {
+            return fail("Malformed WAV file: truncated chunk", &found);
+        }
+        position += read as u64;
+        if &header[0..4] == b"fmt " {
+            if chunk.len() < 16 {
+                return fail(
+                    "Malformed WAV file: fmt chunk shorter than 16 bytes",
+                    &found,
+                );
+            }
+            let u16_at = |i: usize| u16::from_le_bytes([chunk[i], chunk[i + 1]]);
+            let mut tag = u16_at(0);
+            // The extensible format keeps the actual one in the sub format GUID.
+            if tag == FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+                tag = u16_at(24);
+            }
+            let sample_rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            let channels = u16_at(2);
+            if channels == 0 {
+                return fail("Malformed WAV file: 0 channels", &found);
+            }
+            format = Some((tag, channels, sample_rate, u16_at(14)));
+        }
+    }
+}
+
+/// Name of a WAV format tag for messages.
+pub fn format_name(format: u16) -> &'static str {
+    match format {
+        FORMAT_PCM => "PCM",
+        FORMAT_IEEE_FLOAT => "IEEE float",
+        _ => "unknown format",
+    }
+}
+
+/// Format tag and bits per sample of WAV samples of `parse_type`, `None` if
+/// WAV has no such samples. Unsigned integers are stored with the
+/// signedness of WAV, 8 bit samples are unsigned and longer ones signed.
+pub fn sample_format(parse_type: &ParseType) -> Option<(u16, u16)> {
+    match parse_type {
+        ParseType::U8 | ParseType::I8 => Some((FORMAT_PCM, 8)),
+        ParseType::U16 | ParseType::I16 => Some((FORMAT_PCM, 16)),
+        ParseType::U24 | ParseType::I24 => Some((FORMAT_PCM, 24)),
+        ParseType::U32 | ParseType::I32 => Some((FORMAT_PCM, 32)),
+        ParseType::F32 => Some((FORMAT_IEEE_FLOAT, 32)),
+        ParseType::F64 => Some((FORMAT_IEEE_FLOAT, 64)),
+        _ => None,
+    }
+}
+
+/// Writer of a canonical .wav file, a `fmt ` chunk of 16 bytes followed by
+/// the `data` chunk, whose size is filled in by [`WavWriter::finish`].
+pub struct WavWriter {
+    file: BufWriter<File>,
+    frame_size: u64,
+    bytes: u64,
+}
+
+impl WavWriter {
+    /// Creates `path` for samples of `parse_type`, which must have a
+    /// [`sample_format`].
+    pub fn create(
+        path: &str,
+        parse_type: &ParseType,
+        channels: u16,
+        sample_rate: u32,
+    ) -> std::io::Result<Self> {
+        let (format, bits) = sample_format(parse_type).expect("a WAV sample type");
+        let block_align = channels * bits / 8;
+        let mut header = Vec::with_capacity(44);
+        header.extend(b"RIFF");
+        header.extend(36u32.to_le_bytes());
+        header.extend(b"WAVEfmt ");
+        header.extend(16u32.to_le_bytes());
+        header.extend(format.to_le_bytes());
+        header.extend(channels.to_le_bytes());
+        header.extend(sample_rate.to_le_bytes());
+        header.extend(sample_rate.saturating_mul(block_align as u32).to_le_bytes());
+        header.extend(block_align.to_le_bytes());
+        header.extend(bits.to_le_bytes());
+        header.extend(b"data");
+        header.extend(0u32.to_le_bytes());
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&header)?;
+        Ok(WavWriter {
+            file,
+            frame_size: block_align as u64,
+            bytes: 0,
+        })
+    }
+
+    pub fn write(&mut self, value: Value) -> std::io::Result<()> {
+        // Flipping the most significant bit turns offset binary into two's
+        // complement and back.
+        let value = match value {
+            Value::I8(v) => Value::U8(v as u8 ^ 0x80),
+            Value::U16(v) => Value::U16(v ^ 0x8000),
+            Value::U24(v) => Value::U24(v ^ 0x80_0000),
+            Value::U32(v) => Value::U32(v ^ 0x8000_0000),
+            v => v,
+        };
+        let bytes = value.to_bytes(&ByteOrder::LittleEndian);
+        self.bytes += bytes.len() as u64;
+        self.file.write_all(&bytes)
+    }
+
+    /// Writes the chunk sizes into the header and returns the number of bytes
+    /// of an incomplete last frame which are dropped.
+    pub fn finish(self) -> std::io::Result<u64> {
+        let dropped = self.bytes % self.frame_size;
+        let data_len = self.bytes - dropped;
+        // Chunks are padded to an even size.
+        let padding = data_len % 2;
+        let riff_len = u32::try_from(36 + data_len + padding).map_err(|_| {
+            std::io::Error::other(format!(
+                "{} bytes of samples are too many for a .wav file",
+                data_len
+            ))
+        })?;
+        let mut file = self.file.into_inner().map_err(|e| e.into_error())?;
+        file.set_len(44 + data_len)?;
+        if padding > 0 {
+            file.seek(std::io::SeekFrom::End(0))?;
+            file.write_all(&[0])?;
+        }
+        file.seek(std::io::SeekFrom::Start(4))?;
+        file.write_all(&riff_len.to_le_bytes())?;
+        file.seek(std::io::SeekFrom::Start(40))?;
+        file.write_all(&(data_len as u32).to_le_bytes())?;
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A RIFF/WAVE header with a `fmt ` chunk of `format`, the chunks of
+    /// `extra` and a `data` chunk of 400 bytes.
+    fn header(format: u16, channels: u16, bits: u16, extra: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(format.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(48000u32.to_le_bytes());
+        bytes.extend((48000 * (channels * bits / 8) as u32).to_le_bytes());
+        bytes.extend((channels * bits / 8).to_le_bytes());
+        bytes.extend(bits.to_le_bytes());
+        for (id, data) in extra {
+            bytes.extend(*id);
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend(*data);
+            if data.len() % 2 == 1 {
+                bytes.push(0);
+            }
+        }
+        bytes.extend(b"data");
+        bytes.extend(400u32.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn pcm16_header() {
+        let bytes = header(FORMAT_PCM, 2, 16, &[]);
+        let wav = read_header(&mut &bytes[..]).unwrap();
+        assert_eq!(wav.format, FORMAT_PCM);
+        assert_eq!(wav.channels, 2);
+        assert_eq!(wav.sample_rate, 48000);
+        assert_eq!(wav.bits, 16);
+        assert_eq!(wav.parse_type, ParseType::I16);
+        assert_eq!(wav.data_offset, 44);
+        assert_eq!(wav.data_len, Some(400));
+    }
+
+    #[test]
+    fn float32_header_after_other_chunks() {
+        // A chunk of an odd size is padded to an even one.
+        let bytes = header(
+            FORMAT_IEEE_FLOAT,
+            1,
+            32,
+            &[(b"LIST", b"INFOabc"), (b"fact", b"1234")],
+        );
+        let wav = read_header(&mut &bytes[..]).unwrap();
+        assert_eq!(wav.parse_type, ParseType::F32);
+        assert_eq!(wav.channels, 1);
+        assert_eq!(wav.data_offset, 44 + 16 + 12);
+        assert_eq!(wav.data_offset, bytes.len() as u64);
+    }
+
+    #[test]
+    fn extensible_header() {
+        let mut bytes = header(FORMAT_EXTENSIBLE, 1, 64, &[]);
+        // Grow the fmt chunk to 40 bytes with the sub format at 24.
+        let mut extension = vec![22, 0, 64, 0, 0, 0, 0, 0];
+        extension.extend(FORMAT_IEEE_FLOAT.to_le_bytes());
+        extension.extend([0; 14]);
+        bytes.splice(36..36, extension);
+        bytes[16..20].copy_from_slice(&40u32.to_le_bytes());
+        let wav = read_header(&mut &bytes[..]).unwrap();
+        assert_eq!(wav.parse_type, ParseType::F64);
+    }
+
+    #[test]
+    fn truncated_header() {
+        let bytes = header(FORMAT_PCM, 2, 16, &[]);
+        let error = read_header(&mut &bytes[..8]).unwrap_err();
+        assert_eq!(error, "Not a WAV file: too short for a RIFF header");
+        let error = read_header(&mut &bytes[..30]).unwrap_err();
+        assert_eq!(
+            error,
+            "Malformed WAV file: truncated chunk, found RIFF/WAVE, 'fmt ' of 16 bytes at 12"
+        );
+        let error = read_header(&mut &bytes[..40]).unwrap_err();
+        assert!(
+            error.starts_with("Malformed WAV file: no data chunk"),
+            "{}",
+            error
+        );
+        let error = read_header(&mut &b"RIFF\0\0\0\0AVI LIST"[..]).unwrap_err();
+        assert_eq!(error, "Not a WAV file: no RIFF/WAVE header");
+    }
+
+    #[test]
+    fn unsupported_samples() {
+        let bytes = header(FORMAT_PCM, 1, 12, &[]);
+        let error = read_header(&mut &bytes[..]).unwrap_err();
+        assert!(
+            error.starts_with("Unsupported WAV sample format 1 with 12 bits"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn written_files_read_back() {
+        let dir = std::env::temp_dir().join(format!("parsebin-wav-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("written.wav").to_string_lossy().into_owned();
+        let mut writer = WavWriter::create(&path, &ParseType::I16, 2, 8000).unwrap();
+        for v in [1i16, -1, 2, -2, 3] {
+            writer.write(Value::I16(v)).unwrap();
+        }
+        // The last frame has one of two samples.
+        assert_eq!(writer.finish().unwrap(), 2);
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[4..8], &44u32.to_le_bytes());
+        let wav = read_header(&mut &bytes[..]).unwrap();
+        assert_eq!((wav.channels, wav.sample_rate), (2, 8000));
+        assert_eq!(wav.parse_type, ParseType::I16);
+        assert_eq!(wav.data_len, Some(8));
+        assert_eq!(&bytes[44..46], &1i16.to_le_bytes());
+    }
+}
@@ -0,0 +1,1565 @@
+//! Runs the parsebin binary on small files generated by the tests.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+
+fn parsebin(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Writes `bytes` to a file named `name` in a directory of this test run.
+fn fixture(name: &str, bytes: &[u8]) -> String {
+    let dir: PathBuf = std::env::temp_dir().join(format!("parsebin-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+/// The little-endian f32 values 0, 1, 2, ... up to `count`.
+fn f32_file(name: &str, count: u32) -> String {
+    let bytes: Vec<u8> = (0..count).flat_map(|v| (v as f32).to_le_bytes()).collect();
+    fixture(name, &bytes)
+}
+
+#[test]
+fn options_before_and_after_type() {
+    let file = f32_file("options.bin", 300);
+    let orders: [&[&str]; 4] = [
+        &["f32", "-o", "400", "-n", "3", &file],
+        &["f32", &file, "-o", "400", "-n", "3"],
+        &["-o", "400", "f32", &file, "-n", "3"],
+        &["-n", "3", "-o", "400", "f32", &file],
+    ];
+    for args in orders {
+        let output = parsebin(args);
+        assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stdout(&output), "100\n101\n102\n", "{:?}", args);
+    }
+}
+
+#[test]
+fn auto_type_needs_a_header() {
+    let file = f32_file("no_header.bin", 4);
+    let output = parsebin(&["auto", &file]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("TYPE auto needs"));
+}
+
+#[test]
+fn header_round_trip() {
+    let file = f32_file("plain.bin", 8);
+    let output = parsebin(&["f32", &file, "-n", "5", "--emit-binary", "--with-header"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let with_header = fixture("with_header.bin", &output.stdout);
+
+    let output = parsebin(&["auto", &with_header]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0\n1\n2\n3\n4\n");
+    assert!(stderr(&output).contains("Found parsebin header: version 1, F32"));
+
+    // A given type must match the header.
+    let output = parsebin(&["u8", &with_header]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Type mismatch"));
+}
+
+#[cfg(unix)]
+#[test]
+fn sigint_finishes_the_output() {
+    let child = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(["u8", "/dev/zero", "--count-only"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    assert!(stdout(&output).ends_with(" bytes\n"), "{}", stdout(&output));
+}
+
+#[test]
+fn negative_offset_reads_the_tail() {
+    let file = f32_file("tail.bin", 1000);
+    let expected: String = (990..1000).map(|v| format!("{}\n", v)).collect();
+    let output = parsebin(&["f32", &file, "--offset=-40"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), expected);
+    let output = parsebin(&["f32", &file, "-o", "-0x28"]);
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn negative_offset_before_the_start() {
+    let file = f32_file("short_tail.bin", 10);
+    let output = parsebin(&["f32", &file, "--offset=-44"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    assert!(stderr(&output).contains("-44 reaches before the start of the file (40 bytes)"));
+}
+
+#[test]
+fn sizes_in_hex_and_with_suffixes() {
+    let file = f32_file("sizes.bin", 2048);
+    for offset in ["0x1000", "4KiB", "4K"] {
+        let output = parsebin(&["f32", &file, "-o", offset, "-n", "0x2"]);
+        assert_eq!(stdout(&output), "1024\n1025\n", "{}", offset);
+    }
+    let output = parsebin(&["f32", &file, "-o", "4KB", "-n", "1"]);
+    assert_eq!(stdout(&output), "1000\n");
+}
+
+#[test]
+fn invalid_size_is_a_usage_error() {
+    let file = f32_file("invalid_size.bin", 4);
+    let output = parsebin(&["f32", &file, "-o", "4XB"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        stderr(&output).contains("invalid value '4XB'"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+/// The little-endian f64 values 0, 1, 2, ... up to `count`.
+fn f64_file(name: &str, count: u32) -> String {
+    let bytes: Vec<u8> = (0..count).flat_map(|v| (v as f64).to_le_bytes()).collect();
+    fixture(name, &bytes)
+}
+
+#[test]
+fn offset_in_elements() {
+    let file = f64_file("elements.bin", 10);
+    let elements = parsebin(&["f64", &file, "--offset-elements", "3"]);
+    let bytes = parsebin(&["f64", &file, "--offset", "24"]);
+    assert!(elements.status.success(), "{}", stderr(&elements));
+    assert_eq!(stdout(&elements), "3\n4\n5\n6\n7\n8\n9\n");
+    assert_eq!(stdout(&elements), stdout(&bytes));
+}
+
+#[test]
+fn offset_in_elements_out_of_range() {
+    let file = f64_file("elements_range.bin", 10);
+    let output = parsebin(&["f64", &file, "--offset-elements", "11"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("element 11 (byte offset 88) > 80"));
+    let output = parsebin(&["f64", &file, "--offset-elements", "0x2000000000000000"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("overflows a byte offset"));
+}
+
+#[test]
+fn offset_in_elements_excludes_offset() {
+    let file = f64_file("elements_conflict.bin", 10);
+    let output = parsebin(&["f64", &file, "--offset-elements", "1", "--offset", "8"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// Runs parsebin with `input` written to its stdin, read as /dev/stdin.
+#[cfg(unix)]
+fn parsebin_piped(args: &[&str], input: Vec<u8>) -> Output {
+    use std::io::Write;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    output
+}
+
+#[cfg(unix)]
+#[test]
+fn histogram_bounds_of_files_and_pipes() {
+    // A value above all of the first million.
+    let mut bytes = vec![0; 1 << 20];
+    bytes.push(255);
+    let file = fixture("histogram_bounds.bin", &bytes);
+    let output = parsebin(&["u8", &file, "--histogram", "2"]);
+    let text = stdout(&output);
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim_end_matches([' ', '#']))
+        .collect();
+    assert_eq!(lines, ["[0, 127]   1048576", "[128, 255]       1"]);
+    assert_eq!(stderr(&output), "");
+    // A pipe can't be read twice, the bins span the first values.
+    let output = parsebin_piped(&["u8", "/dev/stdin", "--histogram", "2"], bytes);
+    assert!(stdout(&output).starts_with("below 0: 0\nabove 0: 1\n[0, 0] 1048576 "));
+    assert!(stderr(&output).contains("the first 1048576 values"));
+    let output = parsebin(&["u8", &file, "--histogram", "2", "--row-size", "2"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn last_values_of_a_file() {
+    let file = f32_file("last.bin", 300);
+    let output = parsebin(&["f32", &file, "--last", "3"]);
+    assert_eq!(stdout(&output), "297\n298\n299\n");
+    // Fewer values than asked for are all printed.
+    let output = parsebin(&["f32", &file, "--last", "1000"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output).lines().count(), 300);
+}
+
+#[cfg(unix)]
+#[test]
+fn last_values_of_a_pipe() {
+    let file = f32_file("last_pipe.bin", 100_000);
+    let bytes = std::fs::read(&file).unwrap();
+    for args in [
+        &["--last", "3"][..],
+        &["--last", "3", "--addr=hex"],
+        &["--last", "5", "--row-size", "2", "--offset", "4"],
+        &["--last", "200000"],
+    ] {
+        let piped: Vec<&str> = ["f32", "/dev/stdin"].iter().chain(args).copied().collect();
+        let sized: Vec<&str> = ["f32", &file].iter().chain(args).copied().collect();
+        let output = parsebin_piped(&piped, bytes.clone());
+        assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stdout(&output), stdout(&parsebin(&sized)), "{:?}", args);
+    }
+    // A partial value at the end doesn't shift the values.
+    let output = parsebin_piped(&["f32", "/dev/stdin", "--last", "2"], bytes[..402].to_vec());
+    assert_eq!(stdout(&output), "98\n99\n");
+}
+
+/// Runs parsebin with `input` written to its stdin in bursts of odd sizes
+/// with pauses in between, so reads return fewer bytes than asked for.
+#[cfg(unix)]
+fn parsebin_in_bursts(args: &[&str], input: Vec<u8>) -> Output {
+    use std::io::Write;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || -> std::io::Result<()> {
+        let mut start = 0;
+        for burst in [1, 3, 7, 1001, 2, 13].iter().cycle() {
+            let end = std::cmp::min(start + burst, input.len());
+            stdin.write_all(&input[start..end])?;
+            stdin.flush()?;
+            start = end;
+            if start == input.len() {
+                return Ok(());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        Ok(())
+    });
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    output
+}
+
+#[cfg(unix)]
+#[test]
+fn short_reads_from_a_pipe() {
+    let file = f64_file("bursts.bin", 500);
+    let bytes = std::fs::read(&file).unwrap();
+    for args in [
+        &["f64", "--skip-before", "3"][..],
+        &["u24", "--row-size", "5"],
+        &["i16", "--stride", "3"],
+        &["f32", "--channels", "3", "--channel", "1"],
+        &["u32", "--last", "7"],
+        &["uleb128"],
+    ] {
+        let (name, options) = args.split_first().unwrap();
+        let piped: Vec<&str> = [name, "/dev/stdin"]
+            .iter()
+            .chain(options)
+            .copied()
+            .collect();
+        let sized: Vec<&str> = [name, &file[..]].iter().chain(options).copied().collect();
+        let output = parsebin_in_bursts(&piped, bytes.clone());
+        assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stdout(&output), stdout(&parsebin(&sized)), "{:?}", args);
+    }
+}
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn f32_bytes(count: u32) -> Vec<u8> {
+    (0..count).flat_map(|v| (v as f32).to_le_bytes()).collect()
+}
+
+#[test]
+fn decompress_by_extension() {
+    let gz = fixture("values.bin.gz", &gzip(&f32_bytes(100)));
+    let output = parsebin(&["f32", &gz, "-o", "8", "-n", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "2\n3\n4\n");
+    let zst = fixture(
+        "values.bin.zst",
+        &zstd::encode_all(&f32_bytes(100)[..], 0).unwrap(),
+    );
+    let output = parsebin(&["f32", &zst, "--last", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "98\n99\n");
+}
+
+#[test]
+fn raw_data_that_looks_compressed() {
+    // 0x8b1f is the gzip magic read as a little-endian u16.
+    let file = fixture("looks_gzip.bin", &[0x1f, 0x8b, 0x01, 0x00]);
+    for args in [&[][..], &["--compression", "magic"]] {
+        let all: Vec<&str> = ["u16", &file].iter().chain(args).copied().collect();
+        let output = parsebin(&all);
+        assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stdout(&output), "35615\n1\n", "{:?}", args);
+    }
+}
+
+#[test]
+fn decompress_by_magic_bytes_only_if_asked() {
+    let file = fixture("gzip_without_extension", &gzip(&f32_bytes(4)));
+    let output = parsebin(&["f32", &file, "-n", "2", "--compression", "magic"]);
+    assert_eq!(stdout(&output), "0\n1\n");
+    let output = parsebin(&["u8", &file, "-n", "2"]);
+    assert_eq!(stdout(&output), "31\n139\n");
+}
+
+#[test]
+fn negative_values_of_every_signed_type() {
+    let cases: [(&str, Vec<u8>, &str); 6] = [
+        ("i8", vec![0xfe, 0x80], "-2\n-128\n"),
+        ("i16", vec![0xfe, 0xff, 0x00, 0x80], "-2\n-32768\n"),
+        (
+            "i24",
+            vec![0xfe, 0xff, 0xff, 0x00, 0x00, 0x80],
+            "-2\n-8388608\n",
+        ),
+        ("i32", (-2i32).to_le_bytes().to_vec(), "-2\n"),
+        (
+            "i64",
+            i64::MIN.to_le_bytes().to_vec(),
+            "-9223372036854775808\n",
+        ),
+        ("sleb128", vec![0x7e, 0x80, 0x7f], "-2\n-128\n"),
+    ];
+    for (name, bytes, expected) in cases {
+        let file = fixture(&format!("negative_{}.bin", name), &bytes);
+        let output = parsebin(&[name, &file]);
+        assert!(output.status.success(), "{}: {}", name, stderr(&output));
+        assert_eq!(stdout(&output), expected, "{}", name);
+        let swapped: Vec<u8> = match name {
+            "i8" | "sleb128" => continue,
+            _ => bytes
+                .chunks(bytes.len() / expected.lines().count())
+                .flat_map(|value| value.iter().rev().copied())
+                .collect(),
+        };
+        let file = fixture(&format!("negative_{}_be.bin", name), &swapped);
+        let output = parsebin(&[name, &file, "--byte-order", "big-endian"]);
+        assert_eq!(stdout(&output), expected, "{} big-endian", name);
+    }
+}
+
+/// The little-endian u64 values 2^53 + 1, u64::MAX and 5.
+fn big_u64_file(name: &str) -> String {
+    let values = [(1u64 << 53) + 1, u64::MAX, 5];
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    fixture(name, &bytes)
+}
+
+#[test]
+fn json_integers_are_lossless() {
+    let file = big_u64_file("json_u64.bin");
+    let output = parsebin(&["u64", &file, "--json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "[\n9007199254740993,\n18446744073709551615,\n5\n]\n"
+    );
+    // Every number reads back as the value it came from.
+    let parsed: Vec<u64> = stdout(&output)
+        .lines()
+        .filter_map(|line| line.trim_end_matches(',').parse().ok())
+        .collect();
+    assert_eq!(parsed, [(1 << 53) + 1, u64::MAX, 5]);
+}
+
+#[test]
+fn json_big_integers_as_strings() {
+    let file = big_u64_file("json_bigint.bin");
+    let output = parsebin(&["u64", &file, "--json", "--json-bigint-as-string"]);
+    assert_eq!(
+        stdout(&output),
+        "[\n\"9007199254740993\",\n\"18446744073709551615\",\n5\n]\n"
+    );
+    // Negative integers past -2^53 are quoted too, small ones aren't.
+    let output = parsebin(&["i64", &file, "-r", "2", "--json", "--json-bigint-as-string"]);
+    assert_eq!(stdout(&output), "[\n[\"9007199254740993\",-1],\n[5]\n]\n");
+}
+
+#[test]
+fn json_nan_and_infinities() {
+    let values = [1.5, f64::NAN, f64::NEG_INFINITY, f64::INFINITY];
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let file = fixture("json_nan.bin", &bytes);
+    let output = parsebin(&["f64", &file, "--json"]);
+    assert_eq!(stdout(&output), "[\n1.5,\nnull,\nnull,\nnull\n]\n");
+    let output = parsebin(&["f64", &file, "--json", "--nan-as", "null"]);
+    assert_eq!(stdout(&output), "[\n1.5,\nnull,\nnull,\nnull\n]\n");
+    let output = parsebin(&["f64", &file, "--json", "--nan-as", "string"]);
+    assert_eq!(
+        stdout(&output),
+        "[\n1.5,\n\"NaN\",\n\"-Infinity\",\n\"Infinity\"\n]\n"
+    );
+}
+
+#[test]
+fn json_objects_with_offsets() {
+    let file = big_u64_file("json_addr.bin");
+    let output = parsebin(&["u64", &file, "--json", "--addr"]);
+    assert_eq!(
+        stdout(&output),
+        "[\n{\"offset\":0,\"value\":9007199254740993},\n{\"offset\":8,\"value\":18446744073709551615},\n{\"offset\":16,\"value\":5}\n]\n"
+    );
+    let output = parsebin(&[
+        "u64",
+        &file,
+        "--output-format",
+        "jsonl",
+        "--addr",
+        "-r",
+        "2",
+    ]);
+    assert_eq!(
+        stdout(&output),
+        "{\"offset\":0,\"values\":[9007199254740993,18446744073709551615]}\n{\"offset\":16,\"values\":[5]}\n"
+    );
+    let output = parsebin(&["u64", &file, "--output-format", "jsonl"]);
+    assert_eq!(
+        stdout(&output),
+        "[9007199254740993]\n[18446744073709551615]\n[5]\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn bit_fields_of_endless_input() {
+    let output = parsebin(&["--bit-width", "4", "u8", "/dev/zero", "-n", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0\n0\n0\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn leb128_of_endless_input() {
+    let output = parsebin(&["-n", "3", "uleb128", "/dev/zero"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0\n0\n0\n");
+}
+
+#[test]
+fn leb128_lengths_across_chunks() {
+    // More than a read buffer of values, some of them split between reads.
+    let bytes: Vec<u8> = (0..50_000u32).flat_map(|_| [0x80, 0x01, 0x05]).collect();
+    let file = fixture("leb128_lengths.bin", &bytes);
+    let output = parsebin(&["uleb128", &file, "-v"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let text = stdout(&output);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 100_000);
+    assert!(lines
+        .chunks(2)
+        .all(|pair| pair == ["128 (2 bytes)", "5 (1 byte)"]));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn arrow_file_of_columns() {
+    let input = fixture("arrow.bin", &[1, 0, 2, 0, 3, 0, 4, 0, 5, 0]);
+    let path = fixture("columns.arrow", &[]);
+    let output = parsebin(&[
+        "u16",
+        &input,
+        "--row-size",
+        "2",
+        "--header",
+        "left,right",
+        "--arrow",
+        &path,
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        stderr(&output),
+        "Warning: dropping 1 values of the incomplete last row from the Arrow file\n"
+    );
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(bytes.starts_with(b"ARROW1\0\0"));
+    assert!(bytes.ends_with(b"ARROW1"));
+    let find = |needle: &[u8]| bytes.windows(needle.len()).any(|window| window == needle);
+    assert!(find(b"left\0") && find(b"right\0"));
+    // The body has the values of a column after the other.
+    assert!(find(&[1, 0, 3, 0, 0, 0, 0, 0, 2, 0, 4, 0]));
+}
+
+#[test]
+fn header_needs_named_columns() {
+    let input = fixture("header.bin", &[1, 0]);
+    let output = parsebin(&["u16", &input, "--header", "x"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn one_output_mode_at_a_time() {
+    let input = fixture("output_mode.bin", &[1, 0]);
+    let output = parsebin(&["u16", &input, "--reencode", "big-endian", "--json"]);
+    assert_eq!(output.status.code(), Some(2));
+    // An --output-format takes what its flag takes.
+    let output = parsebin(&["u16", &input, "--output-format", "json", "--bits"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--bits"));
+    let output = parsebin(&["u16", &input, "--output-format", "csv", "--addr"]);
+    assert_eq!(stdout(&output), "0x0,1\n");
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_file_of_columns() {
+    let input = fixture("parquet.bin", &[1, 0, 2, 0, 3, 0, 4, 0, 5, 0]);
+    let path = fixture("columns.parquet", &[]);
+    let output = parsebin(&[
+        "u16",
+        &input,
+        "--row-size",
+        "2",
+        "--header",
+        "left,right",
+        "--parquet",
+        &path,
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        stderr(&output),
+        "Warning: dropping 1 values of the incomplete last row from the Parquet file\n"
+    );
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(bytes.starts_with(b"PAR1") && bytes.ends_with(b"PAR1"));
+    let find = |needle: &[u8]| bytes.windows(needle.len()).any(|window| window == needle);
+    assert!(find(b"left") && find(b"right"));
+    // The pages have the values of a column widened to INT32.
+    assert!(find(&[1, 0, 0, 0, 3, 0, 0, 0]) && find(&[2, 0, 0, 0, 4, 0, 0, 0]));
+}
+
+#[test]
+fn completions_for_every_shell() {
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let output = parsebin(&["--completions", shell]);
+        assert!(output.status.success(), "{}", shell);
+        let script = stdout(&output);
+        assert!(script.contains("byte-order"), "{}", shell);
+        assert!(script.contains("u16"), "{}", shell);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn bash_completions_complete_options() {
+    let script = stdout(&parsebin(&["--completions", "bash"]));
+    assert!(script.ends_with("complete -o filenames -F _parsebin parsebin\n"));
+    let complete = |words: &str| {
+        let test = format!(
+            "{}\nCOMP_WORDS=({}); COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1)); _parsebin; echo \"${{COMPREPLY[@]}}\"",
+            script, words
+        );
+        let output = Command::new("bash").args(["-c", &test]).output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+    assert_eq!(complete("parsebin --byte-or"), "--byte-order\n");
+    assert_eq!(
+        complete("parsebin --byte-order ''"),
+        "little-endian big-endian native auto\n"
+    );
+    assert!(complete("parsebin ''").contains("u16"));
+}
+
+#[test]
+fn offset_at_the_end_is_empty() {
+    let file = f32_file("end.bin", 10);
+    for offset in ["--offset=40", "--offset=0x28", "--offset=-0"] {
+        let output = parsebin(&["f32", &file, offset]);
+        assert!(output.status.success(), "{}: {}", offset, stderr(&output));
+        assert_eq!(stdout(&output), "", "{}", offset);
+        assert_eq!(stderr(&output), "", "{}", offset);
+    }
+    let output = parsebin(&["f32", &file, "--offset=40", "--count-only"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "0 values, 0 bytes\n");
+}
+
+#[test]
+fn offset_past_the_end() {
+    let file = f32_file("past_end.bin", 10);
+    let output = parsebin(&["f32", &file, "--offset=41"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    assert_eq!(stderr(&output), "Offset is out of range: 41 > 40\n");
+}
+
+#[test]
+fn negative_offset_of_the_whole_file() {
+    let file = f32_file("whole.bin", 10);
+    let expected: String = (0..10).map(|v| format!("{}\n", v)).collect();
+    let output = parsebin(&["f32", &file, "--offset=-40"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn follow_reads_appended_values_up_to_number() {
+    use std::io::Write;
+    let file = f32_file("follow.bin", 2);
+    let child = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(["f32", &file, "--follow", "--number", "4"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Appended after startup, the values are only seen by following the file.
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    let mut appended = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&file)
+        .unwrap();
+    for value in [2f32, 3.0, 4.0] {
+        appended.write_all(&value.to_le_bytes()).unwrap();
+    }
+    drop(appended);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0\n1\n2\n3\n");
+}
+
+#[test]
+fn one_stream_of_interleaved_types() {
+    // A u32 timestamp before every f32 sample, over several 4096 byte reads.
+    let bytes: Vec<u8> = (0..1000u32)
+        .flat_map(|i| {
+            let timestamp = (1_000_000 + i).to_le_bytes();
+            let sample = (i as f32 * 0.5).to_le_bytes();
+            timestamp.into_iter().chain(sample)
+        })
+        .collect();
+    let file = fixture("interleaved.bin", &bytes);
+    let samples: String = (0..1000).map(|i| format!("{}\n", i as f32 * 0.5)).collect();
+    let output = parsebin(&["f32", &file, "--skip-before", "4"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), samples);
+    let timestamps: String = (0..1000).map(|i| format!("{}\n", 1_000_000 + i)).collect();
+    let output = parsebin(&["u32", &file, "--stride", "4"]);
+    assert_eq!(stdout(&output), timestamps);
+    // --number counts the values kept, not the ones skipped.
+    let output = parsebin(&["f32", &file, "--skip-before", "4", "-o", "4080", "-n", "3"]);
+    assert_eq!(stdout(&output), "255\n255.5\n256\n");
+}
+
+/// Three sines a third of a period apart, interleaved sample by sample.
+fn sine(frame: u32, channel: u32) -> f32 {
+    let phase = 2.0 * std::f32::consts::PI * channel as f32 / 3.0;
+    (frame as f32 * 0.1 + phase).sin()
+}
+
+#[test]
+fn channels_of_interleaved_sines() {
+    let mut bytes: Vec<u8> = (0..100)
+        .flat_map(|frame| (0..3).flat_map(move |channel| sine(frame, channel).to_le_bytes()))
+        .collect();
+    // Two samples of an incomplete last frame.
+    bytes.extend_from_slice(&[0; 8]);
+    let file = fixture("sines.bin", &bytes);
+    for channel in 0..3 {
+        let output = parsebin(&[
+            "f32",
+            &file,
+            "--channels",
+            "3",
+            "--channel",
+            &channel.to_string(),
+        ]);
+        assert!(output.status.success(), "{}", stderr(&output));
+        let expected: String = (0..100)
+            .map(|frame| format!("{}\n", sine(frame, channel)))
+            .collect();
+        assert_eq!(stdout(&output), expected, "channel {}", channel);
+        assert!(stderr(&output).contains("incomplete trailing frame with 2 of 3 channels"));
+    }
+    // Without --channel every frame is a row, and --number counts frames.
+    let output = parsebin(&[
+        "f32",
+        &file,
+        "--channels",
+        "3",
+        "-n",
+        "2",
+        "--row-size",
+        "5",
+    ]);
+    let expected: String = (0..2)
+        .map(|frame| {
+            let row: Vec<String> = (0..3).map(|c| sine(frame, c).to_string()).collect();
+            format!("{}\n", row.join(" "))
+        })
+        .collect();
+    assert_eq!(stdout(&output), expected);
+}
+
+/// Every type of a fixed size and its size in bytes.
+const FIXED_SIZE_TYPES: [(&str, usize); 12] = [
+    ("u8", 1),
+    ("i8", 1),
+    ("u16", 2),
+    ("i16", 2),
+    ("u24", 3),
+    ("i24", 3),
+    ("u32", 4),
+    ("i32", 4),
+    ("u64", 8),
+    ("i64", 8),
+    ("f32", 4),
+    ("f64", 8),
+];
+
+/// The `index`th value of a fixture of type `name`, as little-endian bytes and
+/// as printed. Integers spread over their whole range, negative ones included.
+fn typed_value(name: &str, size: usize, index: u64) -> (Vec<u8>, String) {
+    match name {
+        "f32" => {
+            let value = index as f32 * 0.25 - 100.0;
+            (value.to_le_bytes().to_vec(), value.to_string())
+        }
+        "f64" => {
+            let value = index as f64 * 0.25 - 100.0;
+            (value.to_le_bytes().to_vec(), value.to_string())
+        }
+        _ => {
+            let shift = 64 - 8 * size as u32;
+            let raw = index.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> shift;
+            let text = match name.starts_with('i') {
+                true => (((raw << shift) as i64) >> shift).to_string(),
+                false => raw.to_string(),
+            };
+            (raw.to_le_bytes()[..size].to_vec(), text)
+        }
+    }
+}
+
+#[test]
+fn values_straddling_reads_at_every_alignment() {
+    for (name, size) in FIXED_SIZE_TYPES {
+        // Over a few 4096 byte reads whatever the size.
+        let count = 3 * 4096 / size as u64 + 5;
+        let values: Vec<(Vec<u8>, String)> =
+            (0..count).map(|i| typed_value(name, size, i)).collect();
+        let expected: String = values
+            .iter()
+            .map(|(_, text)| format!("{}\n", text))
+            .collect();
+        for alignment in 0..size {
+            let mut bytes = vec![0xa5; alignment];
+            bytes.extend(values.iter().flat_map(|(bytes, _)| bytes));
+            let file = fixture(&format!("straddle_{}_{}.bin", name, alignment), &bytes);
+            // Two files split at 4096 bytes make a short read in the middle of a value.
+            let first = fixture(
+                &format!("straddle_{}_{}.0", name, alignment),
+                &bytes[..4096],
+            );
+            let second = fixture(
+                &format!("straddle_{}_{}.1", name, alignment),
+                &bytes[4096..],
+            );
+            let offset = alignment.to_string();
+            for args in [
+                &[name, &file, "-o", &offset, "--no-mmap"][..],
+                &[name, &file, "-o", &offset, "--mmap"],
+                &[name, &first, &second, "-o", &offset],
+            ] {
+                let output = parsebin(args);
+                assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+                assert!(stdout(&output) == expected, "{:?}", args);
+            }
+        }
+    }
+}
+
+/// Creates a named pipe `name` and writes `bytes` to it from a thread once
+/// it's opened for reading, the rest is dropped if the reader stops early.
+#[cfg(unix)]
+fn fifo(name: &str, bytes: Vec<u8>) -> (String, std::thread::JoinHandle<()>) {
+    let path = fixture(name, &[]);
+    std::fs::remove_file(&path).unwrap();
+    let status = Command::new("mkfifo").arg(&path).status().unwrap();
+    assert!(status.success());
+    let writer_path = path.clone();
+    let writer = std::thread::spawn(move || {
+        let _ = std::fs::write(writer_path, bytes);
+    });
+    (path, writer)
+}
+
+#[cfg(unix)]
+#[test]
+fn values_from_a_fifo() {
+    let bytes: Vec<u8> = [7u32, 8, 9, 0xffff_ffff]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let (path, writer) = fifo("values.fifo", bytes);
+    let output = parsebin(&["u32", &path]);
+    // A writer left waiting for a reader would never be joined.
+    assert!(output.status.success(), "{}", stderr(&output));
+    writer.join().unwrap();
+    assert_eq!(stdout(&output), "7\n8\n9\n4294967295\n");
+}
+
+#[test]
+fn mapped_and_streamed_output_is_identical() {
+    // A partial value at the end, too.
+    let mut bytes: Vec<u8> = (0..5000).flat_map(|v| (v as f64).to_le_bytes()).collect();
+    bytes.extend_from_slice(&[1, 2, 3]);
+    let file = fixture("mapped.bin", &bytes);
+    for args in [
+        &["f64"][..],
+        &["f64", "--row-size", "4", "--addr=hex"],
+        &["i16", "--skip-before", "3", "--stride", "1", "--align"],
+        &["u24", "-o", "7", "--last", "9"],
+        &["f32", "--csv", "--row-size", "3", "-n", "1000"],
+    ] {
+        let (name, options) = args.split_first().unwrap();
+        let run = |mode: &str| {
+            let args: Vec<&str> = [name, &file[..], mode]
+                .iter()
+                .chain(options)
+                .copied()
+                .collect();
+            let output = parsebin(&args);
+            assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+            output.stdout
+        };
+        let mapped = run("--mmap");
+        assert!(mapped == run("--no-mmap"), "{:?}", args);
+        // Mapped automatically above the threshold.
+        assert!(mapped == run("--mmap-threshold=1"), "{:?}", args);
+    }
+}
+
+#[test]
+fn multi_megabyte_output() {
+    // 4 MiB, a thousand reads of the default buffer.
+    let count = 1 << 20;
+    let bytes: Vec<u8> = (0..count)
+        .flat_map(|v: u32| v.wrapping_mul(2_654_435_761).to_le_bytes())
+        .collect();
+    let file = fixture("large.bin", &bytes);
+    let expected: String = (0..count)
+        .map(|v: u32| format!("{}\n", v.wrapping_mul(2_654_435_761)))
+        .collect();
+    let output = parsebin(&["u32", &file, "--no-mmap"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output) == expected);
+    let rows: String = expected
+        .lines()
+        .collect::<Vec<_>>()
+        .chunks(7)
+        .map(|row| format!("{}\n", row.join(" ")))
+        .collect();
+    let output = parsebin(&["u32", &file, "--no-mmap", "--row-size", "7"]);
+    assert!(stdout(&output) == rows);
+}
+
+#[test]
+fn closed_stdout_exits_cleanly() {
+    use std::io::BufRead;
+    let file = f32_file("closed.bin", 1 << 20);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(["f32", &file])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = std::io::BufReader::new(child.stdout.take().unwrap()).lines();
+    for expected in ["0", "1", "2"] {
+        assert_eq!(lines.next().unwrap().unwrap(), expected);
+    }
+    // Like head, stop reading long before the end.
+    drop(lines);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn every_type_in_both_byte_orders() {
+    for (name, size) in FIXED_SIZE_TYPES {
+        let values: Vec<(Vec<u8>, String)> = (0..50).map(|i| typed_value(name, size, i)).collect();
+        let expected: String = values
+            .iter()
+            .map(|(_, text)| format!("{}\n", text))
+            .collect();
+        let little: Vec<u8> = values.iter().flat_map(|(bytes, _)| bytes.clone()).collect();
+        let big: Vec<u8> = values
+            .iter()
+            .flat_map(|(bytes, _)| bytes.iter().rev().copied())
+            .collect();
+        for (order, bytes) in [("little-endian", little), ("big-endian", big)] {
+            let file = fixture(&format!("every_{}_{}.bin", name, order), &bytes);
+            let output = parsebin(&[name, &file, "--byte-order", order]);
+            assert!(
+                output.status.success(),
+                "{} {}: {}",
+                name,
+                order,
+                stderr(&output)
+            );
+            assert_eq!(stdout(&output), expected, "{} {}", name, order);
+        }
+    }
+    // LEB128 values are a byte at a time, whatever the byte order.
+    let cases: [(&str, Vec<u8>, &str); 2] = [
+        (
+            "uleb128",
+            vec![
+                0x00, 0x7f, 0x80, 0x01, 0xac, 0x02, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff, 0x01,
+            ],
+            "0\n127\n128\n300\n18446744073709551615\n",
+        ),
+        (
+            "sleb128",
+            vec![
+                0x00, 0x3f, 0x40, 0xc0, 0x00, 0xbf, 0x7f, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+                0x80, 0x80, 0x7f,
+            ],
+            "0\n63\n-64\n64\n-65\n-9223372036854775808\n",
+        ),
+    ];
+    for (name, bytes, expected) in cases {
+        let file = fixture(&format!("every_{}.bin", name), &bytes);
+        for order in ["little-endian", "big-endian"] {
+            let output = parsebin(&[name, &file, "--byte-order", order]);
+            assert!(
+                output.status.success(),
+                "{} {}: {}",
+                name,
+                order,
+                stderr(&output)
+            );
+            assert_eq!(stdout(&output), expected, "{} {}", name, order);
+        }
+    }
+}
+
+#[test]
+fn large_offset_into_a_sparse_file() {
+    use std::io::Seek;
+    use std::io::Write;
+    let path = fixture("sparse.bin", &[]);
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    // Mostly a hole, only the values at the end take up space.
+    file.seek(std::io::SeekFrom::Start(5 << 30)).unwrap();
+    for value in [11u32, 12, 13] {
+        file.write_all(&value.to_le_bytes()).unwrap();
+    }
+    drop(file);
+    let output = parsebin(&["u32", &path, "-o", "5GiB"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "11\n12\n13\n");
+    let output = parsebin(&["u32", &path, "-o", "0x140000004", "-n", "1"]);
+    assert_eq!(stdout(&output), "12\n");
+    // Past i64::MAX is out of range rather than wrapping around.
+    let output = parsebin(&["u32", &path, "-o", "10000000000000000000"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("Offset is out of range"));
+}
+
+#[cfg(unix)]
+#[test]
+fn offset_into_a_fifo() {
+    let bytes: Vec<u8> = (0..2000u32).flat_map(|v| v.to_le_bytes()).collect();
+    let (path, writer) = fifo("offset.fifo", bytes);
+    // A FIFO can't seek, so the bytes before the offset are read and discarded.
+    let output = parsebin(&["u32", &path, "-o", "4000", "-n", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1000\n1001\n1002\n");
+    writer.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn carried_bytes_across_reads_of_a_pipe() {
+    for (name, size) in [("f64", 8), ("u24", 3)] {
+        let count = 5000;
+        let values: Vec<(Vec<u8>, String)> =
+            (0..count).map(|i| typed_value(name, size, i)).collect();
+        let mut bytes = vec![0xff];
+        bytes.extend(values.iter().flat_map(|(bytes, _)| bytes));
+        // Neither size divides the 4096 byte reads, so every read ends within a value.
+        let output = parsebin_piped(&[name, "/dev/stdin", "-o", "1", "--addr=dec"], bytes);
+        assert!(output.status.success(), "{}: {}", name, stderr(&output));
+        let text = stdout(&output);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), count as usize, "{}", name);
+        for (index, (line, (_, expected))) in lines.iter().zip(&values).enumerate() {
+            let address = 1 + index * size;
+            assert_eq!(*line, format!("{:08}: {}", address, expected), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn csv_output_with_a_header() {
+    let bytes: Vec<u8> = [1i16, -1, 3, 4, 5]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let file = fixture("csv.bin", &bytes);
+    let output = parsebin(&["i16", &file, "--csv", "--row-size", "2", "--header", "a,b"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "a,b\n1,-1\n3,4\n5\n");
+    // Names with a quote are quoted, the offset column gets a name too.
+    let output = parsebin(&[
+        "i16",
+        &file,
+        "--csv",
+        "--row-size",
+        "2",
+        "--header",
+        "a,b \"x\"",
+        "--addr=dec",
+    ]);
+    assert_eq!(
+        stdout(&output),
+        "offset,a,\"b \"\"x\"\"\"\n0,1,-1\n4,3,4\n8,5\n"
+    );
+    let output = parsebin(&[
+        "i16",
+        &file,
+        "--csv",
+        "--row-size",
+        "2",
+        "--auto-header",
+        "--delimiter",
+        ";",
+    ]);
+    assert_eq!(stdout(&output), "col0;col1\n1;-1\n3;4\n5\n");
+}
+
+/// A parsed JSON value, numbers are kept as written so they can be read
+/// back without going through f64.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// Parses a JSON document strictly enough for the output of parsebin, there
+/// is no JSON crate among the dependencies.
+fn parse_json(text: &str) -> Json {
+    let mut chars = text.trim().chars().peekable();
+    let json = parse_json_value(&mut chars);
+    assert_eq!(chars.next(), None, "trailing characters in {:?}", text);
+    json
+}
+
+fn parse_json_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Json {
+    let skip_whitespace = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+    skip_whitespace(chars);
+    let json = match chars.next().expect("unexpected end of JSON") {
+        'n' => {
+            assert_eq!(chars.take(3).collect::<String>(), "ull");
+            Json::Null
+        }
+        't' => {
+            assert_eq!(chars.take(3).collect::<String>(), "rue");
+            Json::Bool(true)
+        }
+        'f' => {
+            assert_eq!(chars.take(4).collect::<String>(), "alse");
+            Json::Bool(false)
+        }
+        '"' => Json::String(parse_json_string(chars)),
+        '[' => {
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_none() {
+                loop {
+                    items.push(parse_json_value(chars));
+                    match chars.next() {
+                        Some(',') => (),
+                        Some(']') => break,
+                        other => panic!("expected , or ] but found {:?}", other),
+                    }
+                }
+            }
+            Json::Array(items)
+        }
+        '{' => {
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_none() {
+                loop {
+                    skip_whitespace(chars);
+                    assert_eq!(chars.next(), Some('"'));
+                    let name = parse_json_string(chars);
+                    skip_whitespace(chars);
+                    assert_eq!(chars.next(), Some(':'));
+                    members.push((name, parse_json_value(chars)));
+                    match chars.next() {
+                        Some(',') => (),
+                        Some('}') => break,
+                        other => panic!("expected , or }} but found {:?}", other),
+                    }
+                }
+            }
+            Json::Object(members)
+        }
+        c @ ('-' | '0'..='9') => {
+            let mut number = c.to_string();
+            while let Some(c) =
+                chars.next_if(|c| matches!(c, '0'..='9' | '.' | 'e' | 'E' | '+' | '-'))
+            {
+                number.push(c);
+            }
+            Json::Number(number)
+        }
+        c => panic!("unexpected {:?} in JSON", c),
+    };
+    skip_whitespace(chars);
+    json
+}
+
+/// Parses the rest of a string after its opening quote.
+fn parse_json_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut string = String::new();
+    loop {
+        match chars.next().expect("unterminated JSON string") {
+            '"' => return string,
+            '\\' => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.take(4).collect();
+                    string.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+                }
+                Some(c) => string.push(c),
+                None => panic!("unterminated JSON string"),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+impl Json {
+    fn items(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            other => panic!("expected an array, found {:?}", other),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&self) -> T {
+        match self {
+            Json::Number(number) => number.parse().ok().expect("not a number of that type"),
+            other => panic!("expected a number, found {:?}", other),
+        }
+    }
+
+    fn member(&self, name: &str) -> &Json {
+        match self {
+            Json::Object(members) => &members.iter().find(|(n, _)| n == name).unwrap().1,
+            other => panic!("expected an object, found {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn json_parses_back_to_the_values() {
+    let values: Vec<f32> = (0..100).map(|i| (i as f32 * 0.37).sin() * 1e6).collect();
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let file = fixture("json_values.bin", &bytes);
+    let output = parsebin(&["f32", &file, "--json", "-r", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json = parse_json(&stdout(&output));
+    let rows = json.items();
+    assert_eq!(rows.len(), 34);
+    let parsed: Vec<f32> = rows
+        .iter()
+        .flat_map(|row| row.items().iter().map(|value| value.number::<f32>()))
+        .collect();
+    assert_eq!(parsed, values);
+    // Every line of JSON lines is a document of its own.
+    let output = parsebin(&["f32", &file, "--output-format", "jsonl", "--addr"]);
+    let text = stdout(&output);
+    let lines: Vec<Json> = text.lines().map(parse_json).collect();
+    assert_eq!(lines.len(), values.len());
+    for (index, (line, value)) in lines.iter().zip(&values).enumerate() {
+        assert_eq!(line.member("offset").number::<usize>(), index * 4);
+        assert_eq!(line.member("values").items()[0].number::<f32>(), *value);
+    }
+    // Integers are exact, NaN and infinities are strings if asked for.
+    let file = big_u64_file("json_parsed_u64.bin");
+    let output = parsebin(&["u64", &file, "--json"]);
+    let parsed: Vec<u64> = parse_json(&stdout(&output))
+        .items()
+        .iter()
+        .map(|value| value.number())
+        .collect();
+    assert_eq!(parsed, [(1 << 53) + 1, u64::MAX, 5]);
+    let bytes: Vec<u8> = [f64::NAN, -1.5, f64::NEG_INFINITY]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let file = fixture("json_parsed_nan.bin", &bytes);
+    let output = parsebin(&["f64", &file, "--json", "--nan-as", "string"]);
+    assert_eq!(
+        parse_json(&stdout(&output)),
+        Json::Array(vec![
+            Json::String("NaN".to_string()),
+            Json::Number("-1.5".to_string()),
+            Json::String("-Infinity".to_string()),
+        ])
+    );
+    let output = parsebin(&["f64", &file, "--json"]);
+    assert_eq!(parse_json(&stdout(&output)).items()[0], Json::Null);
+}
+
+#[test]
+fn color_always_and_never() {
+    let file = fixture(
+        "colors.bin",
+        &[0f32, 1.0, 2.0, f32::NAN]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    );
+    let output = parsebin(&["f32", &file, "--color", "always", "-r", "4"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "\x1b[38;2;68;1;84m0\x1b[0m \x1b[38;2;33;145;140m1\x1b[0m \x1b[38;2;253;231;37m2\x1b[0m \x1b[1;31mNaN\x1b[0m\n"
+    );
+    let output = parsebin(&["f32", &file, "--color", "never", "-r", "4"]);
+    assert_eq!(stdout(&output), "0 1 2 NaN\n");
+}
+
+#[test]
+fn gzip_is_read_to_the_end_of_the_decompressed_data() {
+    // Far larger decompressed than the file, so its size can't bound the read.
+    let bytes: Vec<u8> = (0..100_000u32)
+        .flat_map(|v| (v % 16).to_le_bytes())
+        .collect();
+    let compressed = gzip(&bytes);
+    assert!(compressed.len() * 10 < bytes.len());
+    let expected: String = (0..100_000).map(|v| format!("{}\n", v % 16)).collect();
+    let gz = fixture("long.bin.gz", &compressed);
+    let output = parsebin(&["u32", &gz]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output) == expected);
+    // Forced for a file without the extension, the offset is of decompressed bytes.
+    let file = fixture("long_gzip", &compressed);
+    let output = parsebin(&["u32", &file, "--compression", "gzip", "-o", "399996"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "15\n");
+}
+
+#[test]
+fn stats_of_a_sine_and_a_cosine() {
+    // Ten whole periods of 1 + 2 sin x and cos x side by side.
+    let bytes: Vec<u8> = (0..1000)
+        .flat_map(|i| {
+            let x = 2.0 * std::f64::consts::PI * i as f64 / 100.0;
+            [1.0 + 2.0 * x.sin(), x.cos()]
+        })
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let file = fixture("stats_sines.bin", &bytes);
+    let output = parsebin(&["f64", &file, "--stats-only", "-r", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let text = stdout(&output);
+    let stat = |name: &str| -> Vec<f64> {
+        text.lines()
+            .filter_map(|line| line.strip_prefix(name))
+            .map(|value| value.parse().unwrap())
+            .collect()
+    };
+    let (means, stddevs) = (stat("mean: "), stat("stddev: "));
+    assert_eq!(means.len(), 2, "{}", text);
+    assert!((means[0] - 1.0).abs() < 1e-9, "{}", text);
+    assert!(means[1].abs() < 1e-9, "{}", text);
+    assert!((stddevs[0] - 2.0f64.sqrt()).abs() < 1e-9, "{}", text);
+    assert!(
+        (stddevs[1] - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-9,
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn npy_output_of_rows() {
+    let file = f32_file("to_npy.bin", 7);
+    let npy = fixture("rows.npy", &[]);
+    let output = parsebin(&["f32", &file, "--npy", &npy, "-r", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("dropping 1 values of the incomplete last row"));
+    let bytes = std::fs::read(&npy).unwrap();
+    let len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    assert_eq!((10 + len) % 64, 0);
+    let dict = String::from_utf8_lossy(&bytes[10..10 + len]);
+    assert!(dict.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3)"));
+    assert_eq!(&bytes[10 + len..], &f32_bytes(6)[..]);
+    // Read back with rows of the last axis.
+    let output = parsebin(&["auto", &npy]);
+    assert_eq!(stdout(&output), "0 1 2\n3 4 5\n");
+}
+
+/// A version 1.0 .npy file of `data` with the dtype `descr` and `shape`,
+/// written the way NumPy does.
+fn npy_file(name: &str, descr: &str, shape: &str, data: &[u8]) -> String {
+    let dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    let padding = 63 - (10 + dict.len()) % 64;
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&((dict.len() + padding + 1) as u16).to_le_bytes());
+    bytes.extend_from_slice(dict.as_bytes());
+    bytes.extend(std::iter::repeat_n(b' ', padding));
+    bytes.push(b'\n');
+    bytes.extend_from_slice(data);
+    fixture(name, &bytes)
+}
+
+#[test]
+fn npy_files_of_both_byte_orders() {
+    let floats = [1.5f32, -0.25, 1e30, 7.0];
+    let integers = [i64::MIN, -1, 0, i64::MAX];
+    let cases = [
+        (
+            npy_file(
+                "le.f4.npy",
+                "<f4",
+                "(4,)",
+                &floats
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+            "F32",
+            "1.5\n-0.25\n1000000000000000000000000000000\n7\n",
+        ),
+        (
+            npy_file(
+                "be.f4.npy",
+                ">f4",
+                "(4,)",
+                &floats
+                    .iter()
+                    .flat_map(|v| v.to_be_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+            "F32",
+            "1.5\n-0.25\n1000000000000000000000000000000\n7\n",
+        ),
+        (
+            npy_file(
+                "le.i8.npy",
+                "<i8",
+                "(2, 2)",
+                &integers
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+            "I64",
+            "-9223372036854775808 -1\n0 9223372036854775807\n",
+        ),
+        (
+            npy_file(
+                "be.i8.npy",
+                ">i8",
+                "(2, 2)",
+                &integers
+                    .iter()
+                    .flat_map(|v| v.to_be_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+            "I64",
+            "-9223372036854775808 -1\n0 9223372036854775807\n",
+        ),
+    ];
+    for (file, parse_type, expected) in cases {
+        let output = parsebin(&["auto", &file]);
+        assert!(output.status.success(), "{}: {}", file, stderr(&output));
+        assert_eq!(stdout(&output), expected, "{}", file);
+        assert!(stderr(&output).contains("Found NumPy header: version 1.0"));
+        // The type may be given if it matches the header.
+        let name = parse_type.to_lowercase();
+        let output = parsebin(&[&name, &file]);
+        assert_eq!(stdout(&output), expected, "{}", file);
+        let output = parsebin(&["u8", &file]);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(stderr(&output).contains(&format!("U8 given but header says {}", parse_type)));
+    }
+}
+
+/// A canonical .wav file of `channels` channels of `samples` with the format
+/// tag `format` and `bits` bits per sample.
+fn wav_file(name: &str, format: u16, channels: u16, bits: u16, samples: &[u8]) -> String {
+    let block_align = channels * bits / 8;
+    let mut bytes = b"RIFF".to_vec();
+    bytes.extend((36 + samples.len() as u32).to_le_bytes());
+    bytes.extend(b"WAVEfmt ");
+    bytes.extend(16u32.to_le_bytes());
+    bytes.extend(format.to_le_bytes());
+    bytes.extend(channels.to_le_bytes());
+    bytes.extend(8000u32.to_le_bytes());
+    bytes.extend((8000 * block_align as u32).to_le_bytes());
+    bytes.extend(block_align.to_le_bytes());
+    bytes.extend(bits.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend((samples.len() as u32).to_le_bytes());
+    bytes.extend(samples);
+    fixture(name, &bytes)
+}
+
+#[test]
+fn wav_samples_of_pcm16_and_float32() {
+    let samples: Vec<u8> = [1i16, -1, 300, -300]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let file = wav_file("pcm16.wav", 1, 2, 16, &samples);
+    let output = parsebin(&["auto", &file, "--wav"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1 -1\n300 -300\n");
+    assert!(stderr(&output).contains("Found WAV header: PCM 16 bit, 2 channels, 8000 Hz"));
+    let output = parsebin(&["auto", &file, "--wav", "--channel", "1"]);
+    assert_eq!(stdout(&output), "-1\n-300\n");
+    let samples: Vec<u8> = [0.5f32, -0.125]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let file = wav_file("float32.wav", 3, 1, 32, &samples);
+    let output = parsebin(&["auto", &file, "--wav"]);
+    assert_eq!(stdout(&output), "0.5\n-0.125\n");
+    // Cut off in the fmt chunk.
+    let bytes = std::fs::read(&file).unwrap();
+    let truncated = fixture("truncated.wav", &bytes[..30]);
+    let output = parsebin(&["auto", &truncated, "--wav"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("Malformed WAV file: truncated chunk"));
+}
+
+#[test]
+fn color_heatmap_of_a_grid() {
+    let values = [0f32, 10.0, 20.0, f32::INFINITY, 5.0, -5.0];
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let file = fixture("heatmap.bin", &bytes);
+    let output = parsebin(&[
+        "f32",
+        &file,
+        "--color",
+        "always",
+        "-r",
+        "3",
+        "--color-min",
+        "0",
+        "--color-max",
+        "10",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    // Values past the bounds get the color of the bound, infinities are red.
+    let low = "\x1b[38;2;68;1;84m";
+    let middle = "\x1b[38;2;33;145;140m";
+    let high = "\x1b[38;2;253;231;37m";
+    let expected = format!(
+        "{low}0\x1b[0m {high}10\x1b[0m {high}20\x1b[0m\n\x1b[1;31minf\x1b[0m {middle}5\x1b[0m {low}-5\x1b[0m\n"
+    );
+    assert_eq!(stdout(&output), expected);
+    // Output that isn't a terminal isn't colored, nor is any with NO_COLOR.
+    let output = parsebin(&["f32", &file, "-r", "3"]);
+    assert_eq!(stdout(&output), "0 10 20\ninf 5 -5\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_parsebin"))
+        .args(["f32", &file, "-r", "3", "--color", "auto"])
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output), "0 10 20\ninf 5 -5\n");
+}
+
+#[test]
+fn checksums_of_a_carved_range() {
+    let bytes: Vec<u8> = (0..40).flat_map(|_| 0..=255u8).collect();
+    let file = fixture("carved.bin", &bytes);
+    // As sha256sum, md5sum and crc32 print them for bytes 1000 to 7000 of the file.
+    for (algorithm, expected) in [
+        (
+            "sha256",
+            "5299f13ee0fa5d04bda57ea8255b23cd6d65d2d4732b76ffdde0e215ccfb2c2b",
+        ),
+        ("md5", "8fe7148699a302ee95a55fcb8c73e1e0"),
+        ("crc32", "ce7bdbc6"),
+    ] {
+        let output = parsebin(&[
+            "u8",
+            &file,
+            "-o",
+            "1000",
+            "--length",
+            "6000",
+            "--checksum",
+            algorithm,
+        ]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            algorithm,
+            stderr(&output)
+        );
+        assert_eq!(stdout(&output), format!("{}  6000 bytes\n", expected));
+    }
+}